@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: MIT
+
+//! Emission and parsing of a full `nlctrl` family dump, as captured from the
+//! kernel in the seed corpus of the fuzz targets
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use netlink_packet_core::NetlinkMessage;
+use netlink_packet_generic::{
+    ctrl::GenlCtrl, visit::visit_dump, GenlMessage, NlaVisitor,
+};
+use std::{fs, path::Path};
+
+/// Return the `CTRL_CMD_NEWFAMILY` replies of the dump
+fn dump() -> Vec<Vec<u8>> {
+    let dir =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/genl_ctrl");
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| {
+            name.starts_with("getfamily-")
+                && name.as_bytes()[10].is_ascii_digit()
+        })
+        .collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| fs::read(dir.join(name)).unwrap())
+        .collect()
+}
+
+fn parse(frames: &[Vec<u8>]) -> Vec<NetlinkMessage<GenlMessage<GenlCtrl>>> {
+    frames
+        .iter()
+        .map(|frame| NetlinkMessage::deserialize(frame).unwrap())
+        .collect()
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let frames = dump();
+    c.bench_function("parse nlctrl dump", |b| {
+        b.iter(|| parse(black_box(&frames)))
+    });
+}
+
+fn bench_emit(c: &mut Criterion) {
+    let mut messages = parse(&dump());
+    let mut buf = vec![0u8; 16384];
+    c.bench_function("emit nlctrl dump", |b| {
+        b.iter(|| {
+            for message in black_box(&mut messages) {
+                message.finalize();
+                message.serialize(&mut buf[..message.buffer_len()]);
+            }
+        })
+    });
+}
+
+/// Count the top-level attributes of the messages
+struct Counter(usize);
+
+impl NlaVisitor for Counter {
+    fn attribute(&mut self, _kind: u16, _value: &[u8]) {
+        self.0 += 1;
+    }
+}
+
+fn bench_visit(c: &mut Criterion) {
+    let dump = dump().concat();
+    c.bench_function("visit nlctrl dump", |b| {
+        b.iter(|| {
+            let mut counter = Counter(0);
+            visit_dump(black_box(&dump), 0, &mut counter).unwrap();
+            counter.0
+        })
+    });
+}
+
+criterion_group!(benches, bench_parse, bench_emit, bench_visit);
+criterion_main!(benches);
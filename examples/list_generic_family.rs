@@ -1,11 +1,9 @@
 // SPDX-License-Identifier: MIT
 
-use netlink_packet_core::{
-    NetlinkMessage, NetlinkPayload, NLM_F_DUMP, NLM_F_REQUEST,
-};
+use netlink_packet_core::{NetlinkPayload, NLM_F_DUMP, NLM_F_REQUEST};
 use netlink_packet_generic::{
     ctrl::{nlas::GenlCtrlAttrs, GenlCtrl, GenlCtrlCmd},
-    GenlMessage,
+    GenlBatch, GenlMessage, GenlMessages,
 };
 use netlink_sys::{protocols::NETLINK_GENERIC, Socket, SocketAddr};
 
@@ -14,34 +12,25 @@ fn main() {
     socket.bind_auto().unwrap();
     socket.connect(&SocketAddr::new(0, 0)).unwrap();
 
-    let mut genlmsg = GenlMessage::from_payload(GenlCtrl {
-        cmd: GenlCtrlCmd::GetFamily,
-        nlas: vec![],
-    });
-    genlmsg.finalize();
-    let mut nlmsg = NetlinkMessage::from(genlmsg);
-    nlmsg.header.flags = NLM_F_REQUEST | NLM_F_DUMP;
-    nlmsg.finalize();
-
-    let mut txbuf = vec![0u8; nlmsg.buffer_len()];
-    nlmsg.serialize(&mut txbuf);
-
-    socket.send(&txbuf, 0).unwrap();
+    let mut batch = GenlBatch::new();
+    batch.push(
+        GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetFamily,
+            nlas: vec![],
+        }),
+        NLM_F_REQUEST | NLM_F_DUMP,
+    );
+    socket.send(batch.as_bytes(), 0).unwrap();
 
     let mut rxbuf = Vec::with_capacity(8192);
-    let mut offset = 0;
-    let mut size = 0;
-    'outer: loop {
-        size += socket.recv(&mut rxbuf, 0).unwrap();
+    loop {
+        rxbuf.clear();
+        socket.recv(&mut rxbuf, 0).unwrap();
 
-        loop {
-            let buf = &rxbuf[offset..];
-            // Parse the message
-            let msg = <NetlinkMessage<GenlMessage<GenlCtrl>>>::deserialize(buf)
-                .unwrap();
-
-            match msg.payload {
-                NetlinkPayload::Done(_) => break 'outer,
+        for msg in GenlMessages::<GenlCtrl>::new(&rxbuf) {
+            #[allow(clippy::collapsible_match)]
+            match msg.unwrap().payload {
+                NetlinkPayload::Done(_) => return,
                 NetlinkPayload::InnerMessage(genlmsg) => {
                     if GenlCtrlCmd::NewFamily == genlmsg.payload.cmd {
                         print_entry(genlmsg.payload.nlas);
@@ -53,11 +42,6 @@ fn main() {
                 }
                 _ => {}
             }
-
-            offset += msg.header.length as usize;
-            if offset == size || msg.header.length == 0 {
-                break;
-            }
         }
     }
 }
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: MIT
+
+//! Parse arbitrary bytes as a message of any family, walk its attributes,
+//! and check that it survives an emit/parse round trip unchanged
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use netlink_packet_core::{NetlinkMessage, NetlinkPayload};
+use netlink_packet_generic::{GenlMessage, GenlRaw};
+
+fuzz_target!(|data: &[u8]| {
+    let mut message =
+        match NetlinkMessage::<GenlMessage<GenlRaw>>::deserialize(data) {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+    if let NetlinkPayload::InnerMessage(msg) = &message.payload {
+        for nla in msg.payload.nlas().flatten() {
+            let _ = (nla.kind(), nla.value().len());
+        }
+    }
+    message.finalize();
+    let mut emitted = vec![0u8; message.buffer_len()];
+    message.serialize(&mut emitted);
+    let parsed = NetlinkMessage::<GenlMessage<GenlRaw>>::deserialize(&emitted)
+        .expect("failed to parse an emitted message");
+    assert_eq!(message, parsed);
+});
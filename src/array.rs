@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: MIT
+
+//! Arrays of nested attribute sets keyed by their index
+//!
+//! Several families encode lists as an attribute whose value is a sequence of
+//! nested attributes, the type of each element being its 1-based index in the
+//! list: the commands and multicast groups of the controller, the bands of
+//! nl80211 or the bits of an ethtool bitset.
+//!
+//! [`NlaArray`] is the value of such an attribute, the attribute type being
+//! given by the variant holding it, like [`GenlCtrlAttrs::Ops`]. It keeps the
+//! index of each element as it was parsed, so the array is emitted back
+//! unchanged even when the sender skipped or reordered indices.
+//!
+//! [`NlaArrayAttr`] is the whole attribute, for the families handling the
+//! array as an attribute of its own.
+//!
+//! [`GenlCtrlAttrs::Ops`]: crate::ctrl::nlas::GenlCtrlAttrs::Ops
+
+use crate::canonical::{canonicalize_nlas, Canonicalize};
+use alloc::{format, vec::Vec};
+use core::slice;
+use netlink_packet_core::{
+    DecodeError, Emitable, ErrorContext, Nla, NlaBuffer, NlasIterator,
+    Parseable, NLA_TYPE_MASK,
+};
+
+/// Value of an attribute holding an array of nested attribute sets
+///
+/// The array emits and parses the value of the attribute only: the attribute
+/// type and header are handled by the enclosing attribute.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct NlaArray<T> {
+    /// Elements of the array, in the order they are emitted
+    pub items: Vec<NlaArrayItem<T>>,
+}
+
+impl<T> Default for NlaArray<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<T> NlaArray<T> {
+    /// Create an array holding `sets`, indexed from 1
+    pub fn new<I>(sets: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<T>>,
+    {
+        let mut array = Self::default();
+        for nlas in sets {
+            array.push(nlas);
+        }
+        array
+    }
+
+    /// Append a set, indexed after the last element
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index of the last element is the largest attribute
+    /// type, `NLA_TYPE_MASK`.
+    pub fn push(&mut self, nlas: Vec<T>) {
+        let index = match self.items.last() {
+            Some(item) if item.index >= NLA_TYPE_MASK => {
+                panic!("no array index left after {}", item.index)
+            }
+            Some(item) => item.index + 1,
+            None => 1,
+        };
+        self.items.push(NlaArrayItem { index, nlas });
+    }
+
+    /// Return the set at `index`
+    pub fn get(&self, index: u16) -> Option<&[T]> {
+        self.items
+            .iter()
+            .find(|item| item.index == index)
+            .map(|item| item.nlas.as_slice())
+    }
+
+    /// Return an iterator over the elements of the array
+    pub fn iter(&self) -> slice::Iter<'_, NlaArrayItem<T>> {
+        self.items.iter()
+    }
+
+    /// Return the number of elements of the array
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Return whether the array has no element
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a NlaArray<T> {
+    type Item = &'a NlaArrayItem<T>;
+    type IntoIter = slice::Iter<'a, NlaArrayItem<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Nla + Canonicalize> Canonicalize for NlaArray<T> {
+    fn canonicalize(&mut self) {
+        self.items.sort_by_key(|item| item.index);
+        for (item, index) in self.items.iter_mut().zip(1..) {
+            item.index = index;
+            canonicalize_nlas(&mut item.nlas);
+        }
+    }
+}
+
+impl<T: Nla> Emitable for NlaArray<T> {
+    fn buffer_len(&self) -> usize {
+        self.items.as_slice().buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) {
+        self.items.as_slice().emit(buffer);
+    }
+}
+
+/// Parse the value of the attribute `buf`, whatever its type
+impl<'a, T, B> Parseable<NlaBuffer<&'a B>> for NlaArray<T>
+where
+    T: for<'b> Parseable<NlaBuffer<&'b [u8]>>,
+    B: AsRef<[u8]> + ?Sized,
+{
+    fn parse(buf: &NlaBuffer<&'a B>) -> Result<Self, DecodeError> {
+        let kind = buf.kind();
+        let items = NlasIterator::new(buf.value())
+            .map(|nla| nla.and_then(|nla| NlaArrayItem::parse(&nla)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                e.context(format!("failed to parse attribute array {kind}"))
+            })?;
+        Ok(Self { items })
+    }
+}
+
+/// Attribute holding an array of nested attribute sets
+///
+/// Unlike the controller attributes holding an [`NlaArray`], which emit it
+/// without flag like the kernel does, the attribute is emitted with
+/// `NLA_F_NESTED`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NlaArrayAttr<T> {
+    /// Attribute type
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_nla_kind))]
+    pub kind: u16,
+    /// Value of the attribute
+    pub array: NlaArray<T>,
+}
+
+impl<T> NlaArrayAttr<T> {
+    /// Create an attribute of type `kind` holding `array`
+    pub fn new(kind: u16, array: NlaArray<T>) -> Self {
+        Self { kind, array }
+    }
+}
+
+impl<T: Nla + Canonicalize> Canonicalize for NlaArrayAttr<T> {
+    fn canonicalize(&mut self) {
+        self.array.canonicalize();
+    }
+}
+
+impl<T: Nla> Nla for NlaArrayAttr<T> {
+    fn value_len(&self) -> usize {
+        self.array.buffer_len()
+    }
+
+    fn kind(&self) -> u16 {
+        self.kind
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        self.array.emit(buffer);
+    }
+
+    fn is_nested(&self) -> bool {
+        true
+    }
+}
+
+impl<'a, T, B> Parseable<NlaBuffer<&'a B>> for NlaArrayAttr<T>
+where
+    T: for<'b> Parseable<NlaBuffer<&'b [u8]>>,
+    B: AsRef<[u8]> + ?Sized,
+{
+    fn parse(buf: &NlaBuffer<&'a B>) -> Result<Self, DecodeError> {
+        Ok(Self {
+            kind: buf.kind(),
+            array: NlaArray::parse(buf)?,
+        })
+    }
+}
+
+/// Element of an [`NlaArray`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NlaArrayItem<T> {
+    /// Attribute type of the element, which is its 1-based index
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_nla_kind))]
+    pub index: u16,
+    /// Attributes of the element
+    pub nlas: Vec<T>,
+}
+
+impl<T: Nla> Nla for NlaArrayItem<T> {
+    fn value_len(&self) -> usize {
+        self.nlas.as_slice().buffer_len()
+    }
+
+    fn kind(&self) -> u16 {
+        self.index
+    }
+
+    fn emit_value(&self, buffer: &mut [u8]) {
+        self.nlas.as_slice().emit(buffer);
+    }
+}
+
+impl<'a, T, B> Parseable<NlaBuffer<&'a B>> for NlaArrayItem<T>
+where
+    T: for<'b> Parseable<NlaBuffer<&'b [u8]>>,
+    B: AsRef<[u8]> + ?Sized,
+{
+    fn parse(buf: &NlaBuffer<&'a B>) -> Result<Self, DecodeError> {
+        let index = buf.kind();
+        let nlas = NlasIterator::new(buf.value())
+            .map(|nla| nla.and_then(|nla| T::parse(&nla)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                e.context(format!("failed to parse array element {index}"))
+            })?;
+        Ok(Self { index, nlas })
+    }
+}
+
+/// Generate an attribute type, which does not include the flags of
+/// `nla_type`
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_nla_kind(
+    u: &mut arbitrary::Unstructured,
+) -> arbitrary::Result<u16> {
+    u.int_in_range(0..=netlink_packet_core::NLA_TYPE_MASK)
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: MIT
+
+//! Several netlink messages per datagram
+//!
+//! A [`GenlBatch`] packs requests, possibly to different families, into one
+//! buffer sent with a single `send`, and [`GenlMessages`] splits a received
+//! buffer, which usually holds many messages of a dump, into parsed
+//! messages. [`NetlinkFrames`] splits a buffer without parsing the messages.
+
+use crate::{header::GenlHeader, message::GenlMessage, traits::GenlFamily};
+use alloc::vec::Vec;
+use core::{fmt::Debug, marker::PhantomData};
+use netlink_packet_core::{
+    DecodeError, Emitable, NetlinkBuffer, NetlinkMessage,
+    ParseableParametrized, NLMSG_ALIGNTO,
+};
+
+/// Round `len` up to the alignment of netlink messages
+pub(crate) fn align(len: usize) -> usize {
+    let align = NLMSG_ALIGNTO as usize;
+    (len + align - 1) & !(align - 1)
+}
+
+/// Buffer of netlink messages sent together
+///
+/// The messages are numbered from the first sequence number of the batch,
+/// skipping 0 which the kernel uses for notifications.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GenlBatch {
+    buf: Vec<u8>,
+    messages: usize,
+    sequence_number: u32,
+}
+
+impl Default for GenlBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GenlBatch {
+    /// Create an empty batch numbering its messages from 1
+    pub fn new() -> Self {
+        Self::with_sequence_number(1)
+    }
+
+    /// Create an empty batch numbering its messages from `sequence_number`
+    pub fn with_sequence_number(sequence_number: u32) -> Self {
+        Self {
+            buf: Vec::new(),
+            messages: 0,
+            sequence_number: sequence_number.max(1),
+        }
+    }
+
+    /// Append `message` with the given netlink header `flags`
+    ///
+    /// Return the sequence number assigned to the message.
+    pub fn push<F>(&mut self, mut message: GenlMessage<F>, flags: u16) -> u32
+    where
+        F: GenlFamily + Emitable + Debug,
+    {
+        let sequence_number = self.sequence_number;
+        self.sequence_number = sequence_number.wrapping_add(1).max(1);
+
+        message.finalize();
+        let mut nlmsg = NetlinkMessage::from(message);
+        nlmsg.header.flags = flags;
+        nlmsg.header.sequence_number = sequence_number;
+        nlmsg.finalize();
+
+        let offset = self.buf.len();
+        self.buf.resize(offset + align(nlmsg.buffer_len()), 0);
+        nlmsg.serialize(&mut self.buf[offset..]);
+        self.messages += 1;
+        sequence_number
+    }
+
+    /// Return the sequence number of the next message
+    pub fn next_sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    /// Return the number of messages of the batch
+    pub fn len(&self) -> usize {
+        self.messages
+    }
+
+    /// Return whether the batch has no message
+    pub fn is_empty(&self) -> bool {
+        self.messages == 0
+    }
+
+    /// Return the messages, ready to be sent
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Consume the batch and return its messages
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Iterator over the netlink messages of a buffer
+///
+/// Each item is the slice holding exactly one message, without its
+/// alignment padding. Iteration stops after the first malformed message.
+#[derive(Clone, Debug)]
+pub struct NetlinkFrames<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> NetlinkFrames<'a> {
+    /// Split `buf`
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+}
+
+impl<'a> Iterator for NetlinkFrames<'a> {
+    type Item = Result<&'a [u8], DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let length = match NetlinkBuffer::new_checked(self.buf) {
+            Ok(packet) => packet.length() as usize,
+            Err(e) => {
+                self.buf = &[];
+                return Some(Err(e));
+            }
+        };
+        let frame = &self.buf[..length];
+        self.buf = self.buf.get(align(length)..).unwrap_or_default();
+        Some(Ok(frame))
+    }
+}
+
+/// Iterator parsing the netlink messages of a buffer
+///
+/// Iteration stops after the first malformed message.
+#[derive(Clone, Debug)]
+pub struct GenlMessages<'a, F> {
+    frames: NetlinkFrames<'a>,
+    family: PhantomData<F>,
+}
+
+impl<'a, F> GenlMessages<'a, F> {
+    /// Split and parse `buf`
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            frames: NetlinkFrames::new(buf),
+            family: PhantomData,
+        }
+    }
+}
+
+impl<F> Iterator for GenlMessages<'_, F>
+where
+    F: ParseableParametrized<[u8], GenlHeader> + Debug,
+{
+    type Item = Result<NetlinkMessage<GenlMessage<F>>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = match self.frames.next()? {
+            Ok(frame) => frame,
+            Err(e) => return Some(Err(e)),
+        };
+        let message = NetlinkMessage::deserialize(frame);
+        if message.is_err() {
+            self.frames = NetlinkFrames::new(&[]);
+        }
+        Some(message)
+    }
+}
@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: MIT
+
+//! Query the generic netlink controller, like `genl ctrl` of iproute2
+
+use netlink_packet_generic::{
+    ctrl::{
+        FamilyDoc, FamilyInfo, FamilySnapshot, FamilySpec, GenlCtrl,
+        GenlCtrlCmd,
+    },
+    GenlClient, GenlFamily, McastSubscription, RequestGenerator,
+};
+use netlink_sys::Socket;
+use serde_json::json;
+use std::{convert::TryFrom, env, fs, io, path::Path, process};
+
+const USAGE: &str = "\
+Usage: genl-ctrl [--json] COMMAND
+
+Commands:
+  list                   list the registered families
+  show FAMILY            show a family, by name or id
+  policy FAMILY          dump the attribute policies of a family
+  doc FAMILY             print the reference of a family, in Markdown
+  spec FAMILY            print a skeleton of the YAML spec of a family
+  snapshot FAMILY        save the information and the policy of a family,
+                         in JSON
+  diff OLD NEW           compare two snapshots, and exit with status 3 if
+                         a change is breaking
+  seed FAMILY DIR        write requests at the limits of the policy of a
+                         family in DIR, e.g. to seed a fuzzing corpus
+  monitor                print the controller notifications";
+
+/// Command querying the kernel
+enum Command {
+    List,
+    Show(String),
+    Policy(String),
+    Doc(String),
+    Spec(String),
+    Snapshot(String),
+    Seed(String, String),
+    Monitor,
+}
+
+fn main() {
+    let mut json = false;
+    let mut args = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-j" | "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ => args.push(arg),
+        }
+    }
+    let command = match args.iter().map(String::as_str).collect::<Vec<_>>()[..]
+    {
+        ["list"] => Command::List,
+        ["show", family] => Command::Show(family.to_owned()),
+        ["policy", family] => Command::Policy(family.to_owned()),
+        ["doc", family] => Command::Doc(family.to_owned()),
+        ["spec", family] => Command::Spec(family.to_owned()),
+        ["snapshot", family] => Command::Snapshot(family.to_owned()),
+        ["seed", family, dir] => {
+            Command::Seed(family.to_owned(), dir.to_owned())
+        }
+        ["monitor"] => Command::Monitor,
+        // Comparing snapshots does not need the kernel
+        ["diff", old, new] => {
+            exit_on_error(diff(old, new, json));
+            return;
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    // The reference and the spec have a format of their own
+    if json && matches!(command, Command::Doc(_) | Command::Spec(_)) {
+        eprintln!("genl-ctrl: the doc and spec commands do not support --json");
+        process::exit(2);
+    }
+    exit_on_error(run(command, json));
+}
+
+fn exit_on_error(result: io::Result<()>) {
+    if let Err(e) = result {
+        eprintln!("genl-ctrl: {e}");
+        process::exit(1);
+    }
+}
+
+fn run(command: Command, json: bool) -> io::Result<()> {
+    let mut client = GenlClient::connect()?;
+    match command {
+        Command::List => {
+            let families = client.list_families()?;
+            if json {
+                print_json(&families)
+            } else {
+                families.iter().for_each(|family| print!("{family}"));
+            }
+        }
+        Command::Show(family) => {
+            let family = find_family(&mut client, &family)?;
+            if json {
+                print_json(&family)
+            } else {
+                print!("{family}")
+            }
+        }
+        Command::Policy(family) => {
+            let name = find_family(&mut client, &family)?.name;
+            let policy = client.get_policy(&name)?;
+            if json {
+                print_json(&policy)
+            } else {
+                print!("{policy}")
+            }
+        }
+        Command::Doc(family) => {
+            let info = find_family(&mut client, &family)?;
+            let policy = client.get_policy(&info.name)?;
+            print!("{}", FamilyDoc::new(&info, &policy))
+        }
+        Command::Spec(family) => {
+            let info = find_family(&mut client, &family)?;
+            let policy = client.get_policy(&info.name)?;
+            print!("{}", FamilySpec::new(&info, &policy))
+        }
+        Command::Snapshot(family) => {
+            let info = find_family(&mut client, &family)?;
+            let policy = client.get_policy(&info.name)?;
+            print_json(&FamilySnapshot { info, policy })
+        }
+        Command::Seed(family, dir) => {
+            let info = find_family(&mut client, &family)?;
+            let policy = client.get_policy(&info.name)?;
+            seed(&FamilySnapshot { info, policy }, &dir)?
+        }
+        Command::Monitor => monitor(client, json)?,
+    }
+    Ok(())
+}
+
+/// Return the family called `family`, or whose id is `family`
+fn find_family(
+    client: &mut GenlClient<Socket>,
+    family: &str,
+) -> io::Result<FamilyInfo> {
+    let id = match family.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => family.parse().ok(),
+    };
+    match id {
+        Some(id) => client
+            .list_families()?
+            .into_iter()
+            .find(|info| info.id == id)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no family with id {family}"),
+                )
+            }),
+        None => client.get_family(family).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                io::Error::new(e.kind(), format!("no family called {family}"))
+            } else {
+                e
+            }
+        }),
+    }
+}
+
+/// Print the changes between the snapshots saved in files `old` and `new`
+fn diff(old: &str, new: &str, json: bool) -> io::Result<()> {
+    let old = read_snapshot(old)?;
+    let new = read_snapshot(new)?;
+    let changes = old.diff(&new);
+    if json {
+        print_json(&changes)
+    } else {
+        changes.iter().for_each(|change| println!("{change}"));
+    }
+    if changes.iter().any(|change| change.is_breaking()) {
+        process::exit(3);
+    }
+    Ok(())
+}
+
+/// Write the requests at the limits of the policy of a family in `dir`, one
+/// message per file
+fn seed(snapshot: &FamilySnapshot, dir: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let requests = RequestGenerator::new(snapshot).boundary_requests();
+    for (i, request) in requests.iter().enumerate() {
+        let message = request.to_netlink();
+        let mut buf = vec![0; message.buffer_len()];
+        message.serialize(&mut buf);
+        let name = format!(
+            "{}-{:03}-{}",
+            snapshot.info.name,
+            i,
+            if request.valid { "valid" } else { "invalid" }
+        );
+        fs::write(Path::new(dir).join(name), buf)?;
+    }
+    println!("{} requests written in {dir}", requests.len());
+    Ok(())
+}
+
+fn read_snapshot(path: &str) -> io::Result<FamilySnapshot> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{path}: {e}")))?;
+    serde_json::from_str(&text).map_err(|e| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{path}: {e}"))
+    })
+}
+
+fn monitor(client: GenlClient<Socket>, json: bool) -> io::Result<()> {
+    let mut subscription = McastSubscription::new(client)?;
+    subscription.subscribe(GenlCtrl::family_name(), "notify")?;
+    loop {
+        let message = subscription.recv::<GenlCtrl>()?;
+        let GenlCtrl { cmd, nlas } = message.payload;
+        let info = FamilyInfo::try_from(&nlas[..]).ok();
+        if json {
+            print_json(&json!({ "cmd": cmd, "family": info }));
+            continue;
+        }
+        let event = match cmd {
+            GenlCtrlCmd::NewFamily => "new family",
+            GenlCtrlCmd::DelFamily => "deleted family",
+            GenlCtrlCmd::NewMcastGrp => "new multicast group",
+            GenlCtrlCmd::DelMcastGrp => "deleted multicast group",
+            _ => "notification",
+        };
+        match info {
+            Some(info) => print!("{event}: {info}"),
+            None => println!("{event}: {nlas:?}"),
+        }
+    }
+}
+
+fn print_json<T: serde::Serialize>(value: &T) {
+    match serde_json::to_string(value) {
+        Ok(text) => println!("{text}"),
+        Err(e) => eprintln!("genl-ctrl: {e}"),
+    }
+}
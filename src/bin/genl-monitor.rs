@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MIT
+
+//! Print the notifications of all the multicast groups of all the generic
+//! netlink families
+//!
+//! The controller notifications are decoded with the types of this crate,
+//! and the notifications of the other families with the policy dumped from
+//! the kernel, or as raw attributes for the families without policy.
+
+use netlink_packet_core::{NetlinkMessage, NetlinkPayload};
+use netlink_packet_generic::{
+    constants::GENL_ID_CTRL,
+    ctrl::{FamilyInfo, FamilyPolicy, GenlCtrl, GenlCtrlCmd},
+    GenlClient, GenlFamily, GenlMessage, GenlRaw, McastSubscription,
+};
+use netlink_sys::Socket;
+use std::{collections::HashMap, convert::TryFrom, env, io, process};
+
+const USAGE: &str = "\
+Usage: genl-monitor
+
+Print the notifications of all the generic netlink multicast groups.";
+
+/// A monitored family
+struct Family {
+    info: FamilyInfo,
+    /// Policy dumped from the kernel, empty if the kernel could not dump it
+    policy: FamilyPolicy,
+}
+
+struct Monitor {
+    subscription: McastSubscription<Socket>,
+    families: HashMap<u16, Family>,
+}
+
+fn main() {
+    if env::args().len() > 1 {
+        eprintln!("{USAGE}");
+        process::exit(2);
+    }
+    if let Err(e) = run() {
+        eprintln!("genl-monitor: {e}");
+        process::exit(1);
+    }
+}
+
+fn run() -> io::Result<()> {
+    let mut subscription = McastSubscription::new(GenlClient::connect()?)?;
+    subscription.subscribe(GenlCtrl::family_name(), "notify")?;
+    let mut monitor = Monitor {
+        subscription,
+        families: HashMap::new(),
+    };
+    for info in monitor.subscription.client_mut().list_families()? {
+        monitor.add_family(info);
+    }
+    loop {
+        let frame = monitor.subscription.recv_frame()?;
+        if let Err(e) = monitor.handle(&frame) {
+            eprintln!("genl-monitor: {e}");
+        }
+    }
+}
+
+impl Monitor {
+    /// Join all the groups of a family, and remember its policy
+    fn add_family(&mut self, info: FamilyInfo) {
+        if info.id == GENL_ID_CTRL {
+            return;
+        }
+        for group in &info.mcast_groups {
+            if let Err(e) = self.subscription.subscribe(&info.name, &group.name)
+            {
+                eprintln!(
+                    "genl-monitor: cannot join {}/{}: {e}",
+                    info.name, group.name
+                );
+            }
+        }
+        let policy = self
+            .subscription
+            .client_mut()
+            .get_policy(&info.name)
+            .unwrap_or_default();
+        self.families.insert(info.id, Family { info, policy });
+    }
+
+    fn handle(&mut self, frame: &[u8]) -> io::Result<()> {
+        let message =
+            NetlinkMessage::<GenlMessage<GenlRaw>>::deserialize(frame)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let family_id = message.header.message_type;
+        let NetlinkPayload::InnerMessage(message) = message.payload else {
+            return Ok(());
+        };
+        if family_id == GENL_ID_CTRL {
+            return self.handle_ctrl(frame);
+        }
+        let GenlRaw {
+            cmd,
+            version,
+            payload,
+        } = message.payload;
+        let Some(family) = self.families.get(&family_id) else {
+            println!("[0x{family_id:04x}] cmd {cmd} version {version}");
+            return Ok(());
+        };
+        println!("[{}] cmd {cmd} version {version}", family.info.name);
+        let hdrsize = (family.info.hdrsize as usize).min(payload.len());
+        let policy = family.policy.message_policy(cmd);
+        let text = family
+            .policy
+            .format_nlas(policy, &payload[hdrsize..])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        for line in text.lines() {
+            println!("  {line}");
+        }
+        Ok(())
+    }
+
+    fn handle_ctrl(&mut self, frame: &[u8]) -> io::Result<()> {
+        let message =
+            NetlinkMessage::<GenlMessage<GenlCtrl>>::deserialize(frame)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let NetlinkPayload::InnerMessage(message) = message.payload else {
+            return Ok(());
+        };
+        let ctrl = message.payload;
+        println!("[{}] {:?}", GenlCtrl::family_name(), ctrl);
+        let Ok(info) = FamilyInfo::try_from(&ctrl.nlas[..]) else {
+            return Ok(());
+        };
+        match ctrl.cmd {
+            GenlCtrlCmd::NewFamily => self.add_family(info),
+            GenlCtrlCmd::NewMcastGrp => {
+                for group in &info.mcast_groups {
+                    self.subscription.subscribe(&info.name, &group.name)?;
+                }
+            }
+            GenlCtrlCmd::DelFamily => {
+                self.families.remove(&info.id);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
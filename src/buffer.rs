@@ -2,8 +2,8 @@
 
 //! Buffer definition of generic netlink packet
 use crate::{constants::GENL_HDRLEN, header::GenlHeader, message::GenlMessage};
+use core::fmt::Debug;
 use netlink_packet_core::{DecodeError, Parseable, ParseableParametrized};
-use std::fmt::Debug;
 
 buffer!(GenlBuffer(GENL_HDRLEN) {
     cmd: (u8, 0),
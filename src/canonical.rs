@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: MIT
+
+//! Canonical form and semantic comparison of generic netlink messages
+//!
+//! Netlink does not specify the order of the attributes of a message, so two
+//! messages carrying the same attributes in a different order are equivalent,
+//! although they are not equal, neither as bytes nor with the derived
+//! `PartialEq`. [`Canonicalize`] rewrites a message into a canonical form, in
+//! which equivalent messages are equal and emit the same bytes:
+//!
+//! - the attributes of each set are sorted by type, and attributes of the
+//!   same type by their encoding;
+//! - the elements of each [`NlaArray`](crate::NlaArray) are sorted by index
+//!   and renumbered from 1, as the order of their elements is significant but
+//!   their indices are not.
+//!
+//! [`diff_nlas`] and [`diff_messages`] then report the attributes which
+//! differ between two messages of the same family, and the path to the
+//! nested attributes which differ inside them.
+
+use crate::{
+    message::GenlMessage, traits::GenlFamily, visit::MAX_NESTING_DEPTH,
+};
+use alloc::{vec, vec::Vec};
+use netlink_packet_core::{Emitable, Nla, NlasIterator, NLA_HEADER_SIZE};
+
+/// Value with a canonical form
+///
+/// The default implementation leaves the value unchanged, which fits the
+/// attributes not holding nested attributes.
+pub trait Canonicalize {
+    /// Rewrite the value into its canonical form
+    fn canonicalize(&mut self) {}
+}
+
+/// Payload of a generic netlink family made of a set of attributes
+pub trait AttributeSet {
+    /// Attributes of the family
+    type Nla;
+
+    /// Return the attributes of the payload
+    fn nlas(&self) -> &[Self::Nla];
+}
+
+/// Rewrite a set of attributes into its canonical form
+pub fn canonicalize_nlas<T: Nla + Canonicalize>(nlas: &mut [T]) {
+    for nla in nlas.iter_mut() {
+        nla.canonicalize();
+    }
+    nlas.sort_by_cached_key(|nla| (nla.kind(), encode(nla)));
+}
+
+/// Return the canonical encoding of `value`
+pub fn canonical_bytes<T>(value: &T) -> Vec<u8>
+where
+    T: Emitable + Canonicalize + Clone,
+{
+    let mut value = value.clone();
+    value.canonicalize();
+    encode(&value)
+}
+
+/// Return whether `a` and `b` are equal once in their canonical form
+pub fn semantic_eq<T>(a: &T, b: &T) -> bool
+where
+    T: Canonicalize + Clone + PartialEq,
+{
+    let (mut a, mut b) = (a.clone(), b.clone());
+    a.canonicalize();
+    b.canonicalize();
+    a == b
+}
+
+fn encode<T: Emitable + ?Sized>(value: &T) -> Vec<u8> {
+    let mut buf = vec![0; value.buffer_len()];
+    value.emit(&mut buf);
+    buf
+}
+
+/// Difference on one attribute between two sets of attributes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NlaDiff<T> {
+    /// Attribute only in the new set
+    Added(T),
+    /// Attribute only in the old set
+    Removed(T),
+    /// Attribute whose value changed
+    Changed {
+        /// Attribute in the old set
+        old: T,
+        /// Attribute in the new set
+        new: T,
+        /// Types of the nested attributes leading from the value of the
+        /// attribute to each nested attribute which was added, removed or
+        /// changed, empty when the attribute is not nested
+        paths: Vec<Vec<u16>>,
+    },
+}
+
+impl<T: Nla> NlaDiff<T> {
+    /// Return the type of the attribute
+    pub fn kind(&self) -> u16 {
+        match self {
+            Self::Added(nla) | Self::Removed(nla) => nla.kind(),
+            Self::Changed { new, .. } => new.kind(),
+        }
+    }
+}
+
+/// Return the differences between two sets of attributes, ordered by type
+///
+/// Both sets are compared in their canonical form. The attributes of a type
+/// occurring several times are paired in their canonical order. The nested
+/// attributes of the attributes nested on both sides, per [`Nla::is_nested`],
+/// are compared the same way, descending into the ones flagged with
+/// `NLA_F_NESTED`.
+pub fn diff_nlas<T>(old: &[T], new: &[T]) -> Vec<NlaDiff<T>>
+where
+    T: Nla + Canonicalize + Clone + PartialEq,
+{
+    let (mut old, mut new) = (old.to_vec(), new.to_vec());
+    canonicalize_nlas(&mut old);
+    canonicalize_nlas(&mut new);
+
+    let mut diffs = Vec::new();
+    let mut old = old.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+    loop {
+        let (old_kind, new_kind) = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (old, new) => (old.map(Nla::kind), new.map(Nla::kind)),
+        };
+        match (old_kind, new_kind) {
+            (Some(o), Some(n)) if o == n => {
+                let (old, new) = (old.next().unwrap(), new.next().unwrap());
+                if old != new {
+                    let mut paths = Vec::new();
+                    if old.is_nested() && new.is_nested() {
+                        diff_nested(
+                            &encode_value(&old),
+                            &encode_value(&new),
+                            &mut Vec::new(),
+                            &mut paths,
+                        );
+                    }
+                    diffs.push(NlaDiff::Changed { old, new, paths });
+                }
+            }
+            (Some(o), Some(n)) if o < n => {
+                diffs.push(NlaDiff::Removed(old.next().unwrap()))
+            }
+            (Some(_), None) => {
+                diffs.push(NlaDiff::Removed(old.next().unwrap()))
+            }
+            _ => diffs.push(NlaDiff::Added(new.next().unwrap())),
+        }
+    }
+    diffs
+}
+
+fn encode_value<T: Nla>(nla: &T) -> Vec<u8> {
+    let mut buf = vec![0; nla.value_len()];
+    nla.emit_value(&mut buf);
+    buf
+}
+
+/// Nested attribute, as its type, whether it is flagged with `NLA_F_NESTED`,
+/// and its value
+type RawNla<'a> = (u16, bool, &'a [u8]);
+
+fn raw_nlas(buf: &[u8]) -> Option<Vec<RawNla<'_>>> {
+    let mut nlas = NlasIterator::new(buf)
+        .map(|nla| {
+            let nla = nla.ok()?;
+            let (kind, flagged) = (nla.kind(), nla.nested_flag());
+            let len = nla.length() as usize;
+            Some((kind, flagged, &nla.into_inner()[NLA_HEADER_SIZE..len]))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    nlas.sort();
+    Some(nlas)
+}
+
+/// Append to `paths` the path to each nested attribute differing between the
+/// values `old` and `new`, below `path`
+///
+/// Values which do not parse as attributes are reported as a whole.
+fn diff_nested(
+    old: &[u8],
+    new: &[u8],
+    path: &mut Vec<u16>,
+    paths: &mut Vec<Vec<u16>>,
+) {
+    let (old, new) = match (raw_nlas(old), raw_nlas(new)) {
+        (Some(old), Some(new)) if path.len() < MAX_NESTING_DEPTH => (old, new),
+        _ => {
+            paths.push(path.clone());
+            return;
+        }
+    };
+    let mut old = old.into_iter().peekable();
+    let mut new = new.into_iter().peekable();
+    loop {
+        let (kind, o, n) = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (Some(o), Some(n)) if o.0 == n.0 => (o.0, old.next(), new.next()),
+            (Some(o), Some(n)) if o.0 < n.0 => (o.0, old.next(), None),
+            (Some(o), None) => (o.0, old.next(), None),
+            (_, Some(n)) => (n.0, None, new.next()),
+        };
+        path.push(kind);
+        match (o, n) {
+            (Some((_, o_flag, o)), Some((_, n_flag, n))) if o != n => {
+                if o_flag && n_flag {
+                    diff_nested(o, n, path, paths);
+                } else {
+                    paths.push(path.clone());
+                }
+            }
+            (Some(_), Some(_)) => (),
+            _ => paths.push(path.clone()),
+        }
+        path.pop();
+    }
+}
+
+/// Differences between two generic netlink messages of the same family
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageDiff<T> {
+    /// Old and new commands, if they differ
+    pub command: Option<(u8, u8)>,
+    /// Old and new family versions, if they differ
+    pub version: Option<(u8, u8)>,
+    /// Differences between the attributes
+    pub nlas: Vec<NlaDiff<T>>,
+}
+
+impl<T> MessageDiff<T> {
+    /// Return whether the messages are equivalent
+    pub fn is_empty(&self) -> bool {
+        self.command.is_none() && self.version.is_none() && self.nlas.is_empty()
+    }
+}
+
+/// Return the differences between two messages of the same family
+pub fn diff_messages<F>(
+    old: &GenlMessage<F>,
+    new: &GenlMessage<F>,
+) -> MessageDiff<F::Nla>
+where
+    F: GenlFamily + AttributeSet,
+    F::Nla: Nla + Canonicalize + Clone + PartialEq,
+{
+    let (old, new) = (&old.payload, &new.payload);
+    let changed = |old: u8, new: u8| (old != new).then_some((old, new));
+    MessageDiff {
+        command: changed(old.command(), new.command()),
+        version: changed(old.version(), new.version()),
+        nlas: diff_nlas(old.nlas(), new.nlas()),
+    }
+}
@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: MIT
+
+//! Probing of the generic netlink features of the running kernel
+//!
+//! [`Capabilities`] answers whether a family is registered, in which
+//! version, which requests its commands support and which attributes they
+//! accept, from the replies of the controller. Most families are registered
+//! in all the network namespaces at once, but the families which do not
+//! support namespaces (`netnsok` false in the kernel) are only visible in the
+//! initial one, so the replies are cached per network namespace.
+//!
+//! ```no_run
+//! # #[cfg(feature = "socket")]
+//! # fn main() -> std::io::Result<()> {
+//! use netlink_packet_generic::{
+//!     ctrl::RequestKind, Capabilities, GenlClient, NetnsId,
+//! };
+//!
+//! let mut client = GenlClient::connect()?;
+//! let mut capabilities = Capabilities::new();
+//! let mut probe = capabilities.probe(&mut client, NetnsId::current()?);
+//! if probe.supports("ethtool", 1, RequestKind::Dump)? {
+//!     // ...
+//! }
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "socket"))]
+//! # fn main() {}
+//! ```
+
+use crate::{
+    client::GenlClient,
+    constants::{CTRL_ATTR_OP, CTRL_CMD_GETPOLICY},
+    ctrl::{FamilyInfo, FamilyPolicy, GenlCtrl, RequestKind},
+    errno::{EINVAL, ENODATA, ENOENT, EOPNOTSUPP},
+    traits::GenlFamily,
+    transport::GenlTransport,
+};
+use std::{collections::HashMap, fs, io, os::unix::fs::MetadataExt};
+
+/// Identifier of a network namespace
+///
+/// This is the device and inode numbers of the `/proc/<pid>/ns/net` file of
+/// the namespace, which are compared by `ip netns identify` too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NetnsId {
+    /// Device number of the namespace file
+    pub dev: u64,
+    /// Inode number of the namespace file
+    pub ino: u64,
+}
+
+impl NetnsId {
+    /// Return the network namespace of the calling thread, which is the
+    /// namespace of the sockets it opens
+    pub fn current() -> io::Result<Self> {
+        let metadata = fs::metadata("/proc/thread-self/ns/net")
+            .or_else(|_| fs::metadata("/proc/self/ns/net"))?;
+        Ok(Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        })
+    }
+}
+
+/// Cache of the replies of the controllers of several network namespaces
+///
+/// See the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct Capabilities {
+    namespaces: HashMap<NetnsId, NamespaceCache>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct NamespaceCache {
+    /// Families by name, `None` if not registered
+    families: HashMap<String, Option<FamilyInfo>>,
+    /// Policies of the commands by family name and command, `None` if the
+    /// kernel cannot dump it
+    op_policies: HashMap<(String, u8), Option<FamilyPolicy>>,
+}
+
+/// Queries of the capabilities of a network namespace, through a client
+/// opened in this namespace
+#[derive(Debug)]
+pub struct Probe<'a, T> {
+    cache: &'a mut NamespaceCache,
+    client: &'a mut GenlClient<T>,
+}
+
+impl Capabilities {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Query the capabilities of network namespace `netns`, with `client`
+    /// which must be opened in this namespace
+    pub fn probe<'a, T>(
+        &'a mut self,
+        client: &'a mut GenlClient<T>,
+        netns: NetnsId,
+    ) -> Probe<'a, T> {
+        Probe {
+            cache: self.namespaces.entry(netns).or_default(),
+            client,
+        }
+    }
+
+    /// Forget the replies cached for network namespace `netns`, e.g. after
+    /// a module was loaded in it
+    pub fn invalidate(&mut self, netns: NetnsId) {
+        self.namespaces.remove(&netns);
+    }
+}
+
+impl<T: GenlTransport> Probe<'_, T> {
+    /// Return the family called `name`, if registered
+    pub fn family(&mut self, name: &str) -> io::Result<Option<&FamilyInfo>> {
+        if !self.cache.families.contains_key(name) {
+            let info = match self.client.get_family(name) {
+                Ok(info) => Some(info),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            self.cache.families.insert(name.to_owned(), info);
+        }
+        Ok(self.cache.families[name].as_ref())
+    }
+
+    /// Return whether the family called `name` is registered
+    pub fn has_family(&mut self, name: &str) -> io::Result<bool> {
+        Ok(self.family(name)?.is_some())
+    }
+
+    /// Return the version of the family called `name`, if registered
+    pub fn version(&mut self, name: &str) -> io::Result<Option<u32>> {
+        Ok(self.family(name)?.map(|info| info.version))
+    }
+
+    /// Return whether command `cmd` of the family called `name` supports
+    /// `request` requests
+    pub fn supports(
+        &mut self,
+        name: &str,
+        cmd: u8,
+        request: RequestKind,
+    ) -> io::Result<bool> {
+        let op = self
+            .family(name)?
+            .and_then(|info| info.op(u32::from(cmd)))
+            .copied();
+        Ok(op.is_some_and(|op| match request {
+            RequestKind::Do => op.can_do(),
+            RequestKind::Dump => op.can_dump(),
+        }))
+    }
+
+    /// Return whether the `request` requests of command `cmd` of the family
+    /// called `name` accept attribute `kind`
+    ///
+    /// This is `None` if the kernel cannot dump the policy of the command,
+    /// which requires Linux 5.10 or later, or if the command has no policy.
+    pub fn accepts(
+        &mut self,
+        name: &str,
+        cmd: u8,
+        request: RequestKind,
+        kind: u16,
+    ) -> io::Result<Option<bool>> {
+        if !self.supports(name, cmd, request)? {
+            return Ok(Some(false));
+        }
+        let Some(policy) = self.op_policy(name, cmd)? else {
+            return Ok(None);
+        };
+        let index = policy.op(cmd).and_then(|op| match request {
+            RequestKind::Do => op.do_policy,
+            RequestKind::Dump => op.dump_policy,
+        });
+        Ok(index.map(|index| policy.attribute(index, kind).is_some()))
+    }
+
+    /// Return the policy of command `cmd` of the family called `name`, if
+    /// the kernel can dump it
+    pub fn op_policy(
+        &mut self,
+        name: &str,
+        cmd: u8,
+    ) -> io::Result<Option<&FamilyPolicy>> {
+        let key = (name.to_owned(), cmd);
+        if !self.cache.op_policies.contains_key(&key) {
+            let policy = match self.client.get_op_policy(name, cmd) {
+                Ok(policy) => Some(policy),
+                Err(e) if is_unsupported(&e) => None,
+                Err(e) => return Err(e),
+            };
+            self.cache.op_policies.insert(key.clone(), policy);
+        }
+        Ok(self.cache.op_policies[&key].as_ref())
+    }
+
+    /// Return whether the kernel can dump the policy of a single command,
+    /// which is the case since Linux 5.10
+    pub fn op_policy_dump(&mut self) -> io::Result<bool> {
+        let accepted = self.accepts(
+            GenlCtrl::family_name(),
+            CTRL_CMD_GETPOLICY,
+            RequestKind::Dump,
+            CTRL_ATTR_OP,
+        )?;
+        Ok(accepted == Some(true))
+    }
+}
+
+/// Return whether `e` means that the kernel cannot dump a policy: older
+/// kernels reject the request, and some commands have no policy to dump.
+/// Other errors, e.g. a lack of memory, may not happen on the next query.
+fn is_unsupported(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(EOPNOTSUPP | EINVAL | ENODATA | ENOENT)
+    )
+}
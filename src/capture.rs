@@ -0,0 +1,705 @@
+// SPDX-License-Identifier: MIT
+
+//! Dissection of generic netlink traffic captured on a `nlmon` device
+//!
+//! `tcpdump -i nlmon0 -w capture.pcap` records every netlink message with the
+//! `LINKTYPE_NETLINK` link type. [`CaptureReader`] extracts the packets of
+//! such a pcap or pcapng file, and [`GenlDissector`] decodes their generic
+//! netlink messages, naming the families from the controller messages seen
+//! earlier in the capture.
+//!
+//! Conversely, [`PcapngWriter`] writes generic netlink messages to a pcapng
+//! file in the same format, and [`PcapngTap`] captures the traffic of a
+//! transport with it.
+use crate::{
+    client::{invalid_data, split_frames},
+    constants::GENL_ID_CTRL,
+    ctrl::{FamilyNames, GenlCtrl},
+    message::GenlMessage,
+    raw::GenlRaw,
+    session::Direction,
+    traits::GenlFamily,
+    transport::GenlTransport,
+};
+use netlink_packet_core::{
+    DoneMessage, Emitable, ErrorMessage, NetlinkBuffer, NetlinkHeader,
+    NetlinkMessage, NetlinkPayload,
+};
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Link type of the packets captured on a `nlmon` device
+pub const LINKTYPE_NETLINK: u16 = 253;
+/// Netlink protocol number of generic netlink
+pub const NETLINK_GENERIC: u16 = 16;
+
+/// Smallest message type of a netlink family, the lower types are control
+/// messages
+const NLMSG_MIN_TYPE: u16 = 0x10;
+/// Length of the `struct sll_header` preceding the netlink messages
+const COOKED_HEADER_LEN: usize = 16;
+/// Hardware type of the `nlmon` devices
+const ARPHRD_NETLINK: u16 = 824;
+/// Packet type of the messages sent by a user space socket
+const PACKET_USER: u16 = 6;
+/// Packet type of the messages sent by a kernel socket
+const PACKET_KERNEL: u16 = 7;
+
+/// Largest packet read from a capture, the default snapshot length of
+/// tcpdump, so that a corrupt length field does not cause a huge allocation
+const MAX_PACKET_LEN: usize = 256 * 1024;
+/// Largest pcapng block read from a capture, a packet with its headers and
+/// options
+const MAX_BLOCK_LEN: usize = MAX_PACKET_LEN + 4096;
+/// Number of unanswered requests remembered by a dissector, the oldest
+/// being forgotten first
+const MAX_PENDING_REQUESTS: usize = 1024;
+
+const PCAP_MAGIC_USEC: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NSEC: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+
+/// Packet of a capture file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedPacket {
+    /// Time since the Unix epoch
+    pub timestamp: Duration,
+    /// Link type of the interface the packet was captured on
+    pub linktype: u16,
+    /// Captured bytes, including the link layer header
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Interface {
+    linktype: u16,
+    /// Number of timestamp units per second
+    resolution: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Format {
+    Pcap { linktype: u16, nanoseconds: bool },
+    Pcapng,
+}
+
+/// Reader of the packets of a pcap or pcapng file
+///
+/// The format is detected from the first bytes of the file. Both byte orders
+/// are supported.
+#[derive(Debug)]
+pub struct CaptureReader<R> {
+    reader: R,
+    format: Format,
+    big_endian: bool,
+    interfaces: Vec<Interface>,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Read the file header of the capture
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER {
+            let mut capture = Self {
+                reader,
+                format: Format::Pcapng,
+                big_endian: false,
+                interfaces: Vec::new(),
+            };
+            capture.read_section_header()?;
+            return Ok(capture);
+        }
+        let (big_endian, nanoseconds) =
+            match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                (PCAP_MAGIC_USEC, _) => (false, false),
+                (PCAP_MAGIC_NSEC, _) => (false, true),
+                (_, PCAP_MAGIC_USEC) => (true, false),
+                (_, PCAP_MAGIC_NSEC) => (true, true),
+                _ => return Err(invalid("not a pcap or pcapng file")),
+            };
+        let mut header = [0u8; 20];
+        reader.read_exact(&mut header)?;
+        // The upper bits of the link type field carry the FCS length
+        let linktype = if big_endian {
+            u16::from_be_bytes([header[18], header[19]])
+        } else {
+            u16::from_le_bytes([header[16], header[17]])
+        };
+        Ok(Self {
+            reader,
+            format: Format::Pcap {
+                linktype,
+                nanoseconds,
+            },
+            big_endian,
+            interfaces: Vec::new(),
+        })
+    }
+
+    /// Read the next packet, or return `None` at the end of the file
+    pub fn next_packet(&mut self) -> io::Result<Option<CapturedPacket>> {
+        match self.format {
+            Format::Pcap {
+                linktype,
+                nanoseconds,
+            } => self.next_pcap_packet(linktype, nanoseconds),
+            Format::Pcapng => self.next_pcapng_packet(),
+        }
+    }
+
+    fn next_pcap_packet(
+        &mut self,
+        linktype: u16,
+        nanoseconds: bool,
+    ) -> io::Result<Option<CapturedPacket>> {
+        let mut header = [0u8; 16];
+        if !self.read_or_eof(&mut header)? {
+            return Ok(None);
+        }
+        let secs = self.u32(&header[0..4]);
+        let fraction = self.u32(&header[4..8]);
+        let length = self.u32(&header[8..12]) as usize;
+        if length > MAX_PACKET_LEN {
+            return Err(invalid("pcap packet too large"));
+        }
+        let mut data = vec![0u8; length];
+        self.reader.read_exact(&mut data)?;
+        let nanos = u64::from(fraction) * if nanoseconds { 1 } else { 1000 };
+        Ok(Some(CapturedPacket {
+            timestamp: Duration::from_secs(secs.into())
+                + Duration::from_nanos(nanos),
+            linktype,
+            data,
+        }))
+    }
+
+    fn next_pcapng_packet(&mut self) -> io::Result<Option<CapturedPacket>> {
+        loop {
+            let mut header = [0u8; 8];
+            if !self.read_or_eof(&mut header)? {
+                return Ok(None);
+            }
+            let kind = self.u32(&header[0..4]);
+            if kind == PCAPNG_SECTION_HEADER {
+                self.read_section_header_body(&header[4..8])?;
+                continue;
+            }
+            let length = self.u32(&header[4..8]) as usize;
+            if !(12..=MAX_BLOCK_LEN).contains(&length) || length & 3 != 0 {
+                return Err(invalid("invalid pcapng block length"));
+            }
+            // The body is followed by a copy of the block length
+            let mut body = vec![0u8; length - 8];
+            self.reader.read_exact(&mut body)?;
+            body.truncate(length - 12);
+            match kind {
+                PCAPNG_INTERFACE_DESCRIPTION => self.read_interface(&body)?,
+                PCAPNG_ENHANCED_PACKET => {
+                    if body.len() < 20 {
+                        return Err(invalid("truncated enhanced packet block"));
+                    }
+                    let interface = self.interface(self.u32(&body[0..4]))?;
+                    let units = u64::from(self.u32(&body[4..8])) << 32
+                        | u64::from(self.u32(&body[8..12]));
+                    let length = self.u32(&body[12..16]) as usize;
+                    let data = body
+                        .get(20..20 + length)
+                        .ok_or_else(|| invalid("truncated packet data"))?;
+                    return Ok(Some(CapturedPacket {
+                        timestamp: timestamp(units, interface.resolution),
+                        linktype: interface.linktype,
+                        data: data.to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET => {
+                    let interface = self.interface(0)?;
+                    let length = match body.get(0..4) {
+                        Some(length) => self.u32(length) as usize,
+                        None => return Err(invalid("truncated simple packet")),
+                    };
+                    let end = (4 + length).min(body.len());
+                    return Ok(Some(CapturedPacket {
+                        timestamp: Duration::ZERO,
+                        linktype: interface.linktype,
+                        data: body[4..end].to_vec(),
+                    }));
+                }
+                // Statistics, name resolution, custom blocks...
+                _ => (),
+            }
+        }
+    }
+
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut length = [0u8; 4];
+        self.reader.read_exact(&mut length)?;
+        self.read_section_header_body(&length)
+    }
+
+    /// Read a section header block after its type and its length, which is
+    /// decoded once the byte order is known
+    fn read_section_header_body(&mut self, length: &[u8]) -> io::Result<()> {
+        let mut magic = [0u8; 4];
+        self.reader.read_exact(&mut magic)?;
+        self.big_endian = match u32::from_le_bytes(magic) {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            _ if u32::from_be_bytes(magic) == PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ => return Err(invalid("invalid pcapng byte order magic")),
+        };
+        let length = self.u32(length) as usize;
+        if !(28..=MAX_BLOCK_LEN).contains(&length) || length & 3 != 0 {
+            return Err(invalid("invalid pcapng section header length"));
+        }
+        let mut rest = vec![0u8; length - 12];
+        self.reader.read_exact(&mut rest)?;
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    fn read_interface(&mut self, body: &[u8]) -> io::Result<()> {
+        if body.len() < 8 {
+            return Err(invalid("truncated interface description block"));
+        }
+        let mut interface = Interface {
+            linktype: self.u16(&body[0..2]),
+            resolution: 1_000_000,
+        };
+        let mut options = &body[8..];
+        while options.len() >= 4 {
+            let code = self.u16(&options[0..2]);
+            let length = self.u16(&options[2..4]) as usize;
+            let value = options
+                .get(4..4 + length)
+                .ok_or_else(|| invalid("truncated pcapng option"))?;
+            match code {
+                PCAPNG_OPTION_END => break,
+                PCAPNG_OPTION_IF_TSRESOL if length == 1 => {
+                    let exponent = u32::from(value[0] & 0x7f);
+                    let base: u64 = if value[0] & 0x80 == 0 { 10 } else { 2 };
+                    interface.resolution =
+                        base.checked_pow(exponent).ok_or_else(|| {
+                            invalid("invalid timestamp resolution")
+                        })?;
+                }
+                _ => (),
+            }
+            let padded = (4 + length + 3) & !3;
+            options = options.get(padded..).unwrap_or_default();
+        }
+        self.interfaces.push(interface);
+        Ok(())
+    }
+
+    fn interface(&self, index: u32) -> io::Result<Interface> {
+        self.interfaces
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| invalid("packet of an undescribed interface"))
+    }
+
+    /// Fill `buf`, or return `false` if the file ends before its first byte
+    fn read_or_eof(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let mut read = 0;
+        while read < buf.len() {
+            match self.reader.read(&mut buf[read..]) {
+                Ok(0) if read == 0 => return Ok(false),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<CapturedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet().transpose()
+    }
+}
+
+/// Writer of a pcapng file in the format of the captures of a `nlmon`
+/// device, which can be opened in Wireshark
+///
+/// The file holds a single interface with the `LINKTYPE_NETLINK` link type
+/// and a nanosecond timestamp resolution.
+#[derive(Debug)]
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Write the section header and the interface description to `writer`
+    pub fn new(writer: W) -> io::Result<Self> {
+        let mut capture = Self { writer };
+        let mut section = PCAPNG_BYTE_ORDER_MAGIC.to_ne_bytes().to_vec();
+        section.extend(1u16.to_ne_bytes());
+        section.extend(0u16.to_ne_bytes());
+        // Unknown section length
+        section.extend(u64::MAX.to_ne_bytes());
+        capture.write_block(PCAPNG_SECTION_HEADER, &section)?;
+
+        let mut interface = LINKTYPE_NETLINK.to_ne_bytes().to_vec();
+        interface.extend(0u16.to_ne_bytes());
+        interface.extend(0u32.to_ne_bytes());
+        interface.extend(PCAPNG_OPTION_IF_TSRESOL.to_ne_bytes());
+        interface.extend(1u16.to_ne_bytes());
+        interface.extend([9, 0, 0, 0]);
+        interface.extend(PCAPNG_OPTION_END.to_ne_bytes());
+        interface.extend(0u16.to_ne_bytes());
+        capture.write_block(PCAPNG_INTERFACE_DESCRIPTION, &interface)?;
+        capture.writer.flush()?;
+        Ok(capture)
+    }
+
+    /// Write a datagram of generic netlink messages, captured at
+    /// `timestamp` (time since the Unix epoch)
+    pub fn write_datagram(
+        &mut self,
+        timestamp: Duration,
+        direction: Direction,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let pkttype = match direction {
+            Direction::Sent => PACKET_USER,
+            Direction::Received => PACKET_KERNEL,
+        };
+        let mut packet = Vec::with_capacity(COOKED_HEADER_LEN + data.len());
+        packet.extend(pkttype.to_be_bytes());
+        packet.extend(ARPHRD_NETLINK.to_be_bytes());
+        packet.extend([0u8; 10]);
+        packet.extend(NETLINK_GENERIC.to_be_bytes());
+        packet.extend(data);
+
+        let units = u64::try_from(timestamp.as_nanos()).unwrap_or(u64::MAX);
+        let mut block = 0u32.to_ne_bytes().to_vec();
+        block.extend(((units >> 32) as u32).to_ne_bytes());
+        block.extend((units as u32).to_ne_bytes());
+        block.extend((packet.len() as u32).to_ne_bytes());
+        block.extend((packet.len() as u32).to_ne_bytes());
+        block.extend(packet);
+        self.write_block(PCAPNG_ENHANCED_PACKET, &block)?;
+        self.writer.flush()
+    }
+
+    /// Write `message`, captured now
+    pub fn write_message<F>(
+        &mut self,
+        direction: Direction,
+        message: &NetlinkMessage<GenlMessage<F>>,
+    ) -> io::Result<()>
+    where
+        F: GenlFamily + Emitable + Debug,
+    {
+        let mut buf = vec![0u8; message.buffer_len()];
+        message.serialize(&mut buf);
+        self.write_datagram(now(), direction, &buf)
+    }
+
+    /// Return the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_block(&mut self, kind: u32, body: &[u8]) -> io::Result<()> {
+        let padding = (4 - body.len() % 4) % 4;
+        let length = (body.len() + padding + 12) as u32;
+        self.writer.write_all(&kind.to_ne_bytes())?;
+        self.writer.write_all(&length.to_ne_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&[0u8; 3][..padding])?;
+        self.writer.write_all(&length.to_ne_bytes())
+    }
+}
+
+/// Transport writing the datagrams sent and received through another
+/// transport to a pcapng file
+///
+/// Wrap the transport of a client into a tap to inspect its traffic in
+/// Wireshark:
+///
+/// ```no_run
+/// # #[cfg(feature = "socket")]
+/// # fn main() -> std::io::Result<()> {
+/// use netlink_packet_generic::{capture::PcapngTap, GenlClient};
+/// use std::fs::File;
+///
+/// let socket = GenlClient::connect()?.into_transport();
+/// let tap = PcapngTap::new(socket, File::create("genl.pcapng")?)?;
+/// let mut client = GenlClient::new(tap);
+/// client.get_family("nlctrl")?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "socket"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug)]
+pub struct PcapngTap<T, W: Write> {
+    transport: T,
+    writer: PcapngWriter<W>,
+}
+
+impl<T: GenlTransport, W: Write> PcapngTap<T, W> {
+    /// Capture the traffic of `transport` to `writer`
+    pub fn new(transport: T, writer: W) -> io::Result<Self> {
+        Ok(Self {
+            transport,
+            writer: PcapngWriter::new(writer)?,
+        })
+    }
+
+    /// Return a mutable reference to the tapped transport
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Stop capturing, and return the transport and the writer
+    pub fn into_parts(self) -> (T, W) {
+        (self.transport, self.writer.into_inner())
+    }
+}
+
+impl<T: GenlTransport, W: Write> GenlTransport for PcapngTap<T, W> {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let sent = self.transport.send(buf)?;
+        self.writer
+            .write_datagram(now(), Direction::Sent, &buf[..sent])?;
+        Ok(sent)
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let buf = self.transport.recv()?;
+        self.writer
+            .write_datagram(now(), Direction::Received, &buf)?;
+        Ok(buf)
+    }
+
+    fn add_membership(&mut self, group: u32) -> io::Result<()> {
+        self.transport.add_membership(group)
+    }
+
+    fn drop_membership(&mut self, group: u32) -> io::Result<()> {
+        self.transport.drop_membership(group)
+    }
+}
+
+/// Payload of a dissected generic netlink message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DissectedPayload {
+    /// Message of the controller
+    Ctrl(GenlCtrl),
+    /// Message of another family
+    Family(GenlRaw),
+    /// End of a dump
+    Done(DoneMessage),
+    /// Error or acknowledgement
+    Error(ErrorMessage),
+    /// Other netlink control message
+    Other(Vec<u8>),
+}
+
+/// Generic netlink message of a capture
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DissectedMessage {
+    /// Time since the Unix epoch
+    pub timestamp: Duration,
+    /// Whether the message was sent by a user space socket (`Sent`) or by
+    /// the kernel (`Received`)
+    pub direction: Option<Direction>,
+    /// Netlink header of the message
+    pub header: NetlinkHeader,
+    /// Name of the family, if its id was seen in a controller message
+    /// earlier in the capture
+    ///
+    /// The errors and the ends of dumps are labelled with the family of the
+    /// request they answer.
+    pub family: Option<String>,
+    /// Decoded payload of the message
+    pub payload: DissectedPayload,
+}
+
+/// Decoder of the generic netlink messages of captured packets
+///
+/// The dissector learns the family names from the controller messages
+/// carrying both a family id and a family name, e.g. the replies to
+/// `CTRL_CMD_GETFAMILY` and the `CTRL_CMD_NEWFAMILY` notifications. The
+/// packets must therefore be dissected in the capture order.
+#[derive(Clone, Debug, Default)]
+pub struct GenlDissector {
+    families: FamilyNames,
+    /// Family id of the unanswered requests, by port id and sequence
+    /// number
+    requests: Vec<((u32, u32), u16)>,
+}
+
+impl GenlDissector {
+    /// Create a dissector only knowing the controller
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the name of the family with the id `id`, if it was learnt
+    pub fn family_name(&self, id: u16) -> Option<&str> {
+        self.families.get(id)
+    }
+
+    /// Decode the generic netlink messages of `packet`
+    ///
+    /// The packets of other link types and of other netlink protocols yield
+    /// no message.
+    pub fn dissect(
+        &mut self,
+        packet: &CapturedPacket,
+    ) -> io::Result<Vec<DissectedMessage>> {
+        if packet.linktype != LINKTYPE_NETLINK
+            || packet.data.len() < COOKED_HEADER_LEN
+        {
+            return Ok(Vec::new());
+        }
+        let cooked = &packet.data[..COOKED_HEADER_LEN];
+        let protocol = u16::from_be_bytes([cooked[14], cooked[15]]);
+        if protocol != NETLINK_GENERIC {
+            return Ok(Vec::new());
+        }
+        let direction = match u16::from_be_bytes([cooked[0], cooked[1]]) {
+            PACKET_USER => Some(Direction::Sent),
+            PACKET_KERNEL => Some(Direction::Received),
+            _ => None,
+        };
+        split_frames(&packet.data[COOKED_HEADER_LEN..])?
+            .iter()
+            .map(|frame| self.dissect_frame(frame, packet.timestamp, direction))
+            .collect()
+    }
+
+    fn dissect_frame(
+        &mut self,
+        frame: &[u8],
+        timestamp: Duration,
+        direction: Option<Direction>,
+    ) -> io::Result<DissectedMessage> {
+        let buffer = NetlinkBuffer::new(frame);
+        let message_type = buffer.message_type();
+        let sequence_number = buffer.sequence_number();
+        let (header, payload) = if message_type == GENL_ID_CTRL {
+            let message =
+                NetlinkMessage::<GenlMessage<GenlCtrl>>::deserialize(frame)
+                    .map_err(invalid_data)?;
+            let payload = match message.payload {
+                NetlinkPayload::InnerMessage(msg) => {
+                    self.families.learn(&msg.payload.nlas);
+                    DissectedPayload::Ctrl(msg.payload)
+                }
+                _ => DissectedPayload::Other(frame.to_vec()),
+            };
+            (message.header, payload)
+        } else {
+            let message =
+                NetlinkMessage::<GenlMessage<GenlRaw>>::deserialize(frame)
+                    .map_err(invalid_data)?;
+            let payload = match message.payload {
+                NetlinkPayload::InnerMessage(msg) => {
+                    DissectedPayload::Family(msg.payload)
+                }
+                NetlinkPayload::Done(done) => DissectedPayload::Done(done),
+                NetlinkPayload::Error(error) => DissectedPayload::Error(error),
+                _ => DissectedPayload::Other(frame.to_vec()),
+            };
+            (message.header, payload)
+        };
+
+        let key = (header.port_number, sequence_number);
+        let family_id = if message_type >= NLMSG_MIN_TYPE {
+            if direction == Some(Direction::Sent) && sequence_number != 0 {
+                self.requests.retain(|(k, _)| *k != key);
+                if self.requests.len() == MAX_PENDING_REQUESTS {
+                    self.requests.remove(0);
+                }
+                self.requests.push((key, message_type));
+            }
+            Some(message_type)
+        } else {
+            // The requests usually leave the port id to 0, while the kernel
+            // sets it to the one of the requesting socket in its answers
+            let position =
+                self.requests.iter().position(|(k, _)| *k == key).or_else(
+                    || {
+                        self.requests
+                            .iter()
+                            .position(|(k, _)| *k == (0, sequence_number))
+                    },
+                );
+            let terminal = matches!(
+                payload,
+                DissectedPayload::Done(_) | DissectedPayload::Error(_)
+            );
+            position.map(|i| {
+                if terminal {
+                    self.requests.remove(i).1
+                } else {
+                    self.requests[i].1
+                }
+            })
+        };
+        Ok(DissectedMessage {
+            timestamp,
+            direction,
+            header,
+            family: family_id
+                .and_then(|id| self.family_name(id))
+                .map(str::to_owned),
+            payload,
+        })
+    }
+}
+
+/// Convert a number of timestamp units to a duration
+fn timestamp(units: u64, resolution: u64) -> Duration {
+    let nanos =
+        u128::from(units) * 1_000_000_000 / u128::from(resolution.max(1));
+    Duration::new(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+fn invalid(description: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, description.to_owned())
+}
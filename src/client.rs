@@ -0,0 +1,301 @@
+// SPDX-License-Identifier: MIT
+
+//! Blocking request/response client for generic netlink families
+use crate::{
+    batch::NetlinkFrames,
+    ctrl::{
+        nlas::GenlCtrlAttrs, FamilyInfo, FamilyPolicy, GenlCtrl, GenlCtrlCmd,
+        OpPolicy,
+    },
+    header::GenlHeader,
+    message::GenlMessage,
+    traits::GenlFamily,
+    transport::GenlTransport,
+};
+use netlink_packet_core::{
+    DecodeError, Emitable, NetlinkBuffer, NetlinkMessage, NetlinkPayload,
+    ParseableParametrized, NLM_F_ACK, NLM_F_DUMP, NLM_F_REQUEST,
+};
+use std::{collections::VecDeque, convert::TryFrom, fmt::Debug, io};
+
+/// A blocking generic netlink client
+///
+/// The client numbers the requests it sends, and collects the replies of a
+/// request by its sequence number. Every other message received in the
+/// meantime (e.g. multicast notifications) is kept and handed out later by
+/// [`recv_frame()`](Self::recv_frame).
+#[derive(Debug)]
+pub struct GenlClient<T> {
+    transport: T,
+    sequence_number: u32,
+    pending: VecDeque<Vec<u8>>,
+}
+
+#[cfg(feature = "socket")]
+impl GenlClient<netlink_sys::Socket> {
+    /// Open a `NETLINK_GENERIC` socket connected to the kernel
+    pub fn connect() -> io::Result<Self> {
+        use netlink_sys::{protocols::NETLINK_GENERIC, Socket, SocketAddr};
+
+        let mut socket = Socket::new(NETLINK_GENERIC)?;
+        socket.bind_auto()?;
+        socket.connect(&SocketAddr::new(0, 0))?;
+        Ok(Self::new(socket))
+    }
+}
+
+impl<T> GenlClient<T> {
+    /// Build a client on top of `transport`
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            sequence_number: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Return a reference to the underlying transport
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Return a mutable reference to the underlying transport
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Consume the client and return the underlying transport
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+}
+
+impl<T: GenlTransport> GenlClient<T> {
+    /// Send `message` with the given netlink header `flags`
+    ///
+    /// Return the sequence number assigned to the message.
+    pub fn send<F>(
+        &mut self,
+        message: GenlMessage<F>,
+        flags: u16,
+    ) -> io::Result<u32>
+    where
+        F: GenlFamily + Emitable + Debug,
+    {
+        // Sequence number 0 is used by the kernel for notifications
+        self.sequence_number = self.sequence_number.wrapping_add(1).max(1);
+
+        let mut nlmsg = NetlinkMessage::from(message);
+        nlmsg.header.flags = flags;
+        nlmsg.header.sequence_number = self.sequence_number;
+        nlmsg.finalize();
+
+        let mut buf = vec![0u8; nlmsg.buffer_len()];
+        nlmsg.serialize(&mut buf);
+        self.transport.send(&buf)?;
+        Ok(self.sequence_number)
+    }
+
+    /// Send `message` as a request and collect its replies
+    ///
+    /// If `flags` contains `NLM_F_DUMP`, the replies are collected until the
+    /// end of the dump. Otherwise an acknowledgement is requested and the
+    /// replies are collected until it is received. A netlink error reported
+    /// by the peer is returned as an [`io::Error`].
+    pub fn request<F>(
+        &mut self,
+        message: GenlMessage<F>,
+        flags: u16,
+    ) -> io::Result<Vec<GenlMessage<F>>>
+    where
+        F: GenlFamily
+            + Emitable
+            + ParseableParametrized<[u8], GenlHeader>
+            + Debug,
+    {
+        let flags = if flags & NLM_F_DUMP == NLM_F_DUMP {
+            flags | NLM_F_REQUEST
+        } else {
+            flags | NLM_F_REQUEST | NLM_F_ACK
+        };
+        let sequence_number = self.send(message, flags)?;
+
+        // The replies are read until the end of the dump even after an error,
+        // so that they are not mistaken for notifications afterwards.
+        let mut replies = Ok(Vec::new());
+        let mut finished = false;
+        while !finished {
+            let buf = self.transport.recv()?;
+            for frame in split_frames(&buf)? {
+                if finished || frame_sequence_number(&frame) != sequence_number
+                {
+                    self.pending.push_back(frame);
+                    continue;
+                }
+                let payload = match parse_frame::<F>(&frame) {
+                    Ok(message) => message.payload,
+                    Err(e) => {
+                        replies = replies.and(Err(e));
+                        continue;
+                    }
+                };
+                match payload {
+                    NetlinkPayload::InnerMessage(msg) => {
+                        if let Ok(replies) = &mut replies {
+                            replies.push(msg);
+                        }
+                    }
+                    NetlinkPayload::Done(msg) if msg.code < 0 => {
+                        return Err(io::Error::from_raw_os_error(-msg.code))
+                    }
+                    NetlinkPayload::Done(_) => finished = true,
+                    NetlinkPayload::Error(err) if err.code.is_some() => {
+                        return Err(err.to_io())
+                    }
+                    NetlinkPayload::Error(_) => finished = true,
+                    _ => (),
+                }
+            }
+        }
+        replies
+    }
+
+    /// Receive the next netlink message which is not a reply to a request
+    /// made by this client
+    ///
+    /// The returned buffer holds exactly one netlink message.
+    pub fn recv_frame(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Ok(frame);
+            }
+            let buf = self.transport.recv()?;
+            self.pending.extend(split_frames(&buf)?);
+        }
+    }
+
+    /// Receive and parse the next netlink message which is not a reply to a
+    /// request made by this client
+    pub fn recv<F>(&mut self) -> io::Result<NetlinkMessage<GenlMessage<F>>>
+    where
+        F: ParseableParametrized<[u8], GenlHeader> + Debug,
+    {
+        let frame = self.recv_frame()?;
+        parse_frame(&frame)
+    }
+
+    /// Query the controller for the family called `name`
+    pub fn get_family(&mut self, name: &str) -> io::Result<FamilyInfo> {
+        let message = GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetFamily,
+            nlas: vec![GenlCtrlAttrs::FamilyName(name.to_owned())],
+        });
+        let replies = self.request(message, 0)?;
+        let reply = replies.first().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no reply for family {name}"),
+            )
+        })?;
+        FamilyInfo::try_from(&reply.payload.nlas[..]).map_err(invalid_data)
+    }
+
+    /// Query the controller for all the registered families
+    pub fn list_families(&mut self) -> io::Result<Vec<FamilyInfo>> {
+        let message = GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetFamily,
+            nlas: vec![],
+        });
+        self.request(message, NLM_F_DUMP)?
+            .iter()
+            .map(|reply| FamilyInfo::try_from(&reply.payload.nlas[..]))
+            .collect::<Result<_, _>>()
+            .map_err(invalid_data)
+    }
+
+    /// Query the controller for the policy of the family called `name`
+    ///
+    /// Dumping the policy of a whole family requires Linux 5.10 or later.
+    pub fn get_policy(&mut self, name: &str) -> io::Result<FamilyPolicy> {
+        let message = GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetPolicy,
+            nlas: vec![GenlCtrlAttrs::FamilyName(name.to_owned())],
+        });
+        let mut policy = FamilyPolicy::default();
+        for reply in self.request(message, NLM_F_DUMP)? {
+            policy.add(&reply.payload.nlas).map_err(invalid_data)?;
+        }
+        Ok(policy)
+    }
+
+    /// Query the controller for the policy of command `cmd` of the family
+    /// called `name`
+    ///
+    /// The reply only describes the policies of `cmd`, under `cmd` whatever
+    /// the command the kernel reports them for, as the kernels report the
+    /// commands which only support `dump` requests as command 0.
+    pub fn get_op_policy(
+        &mut self,
+        name: &str,
+        cmd: u8,
+    ) -> io::Result<FamilyPolicy> {
+        let message = GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetPolicy,
+            nlas: vec![
+                GenlCtrlAttrs::FamilyName(name.to_owned()),
+                GenlCtrlAttrs::Op(u32::from(cmd)),
+            ],
+        });
+        let mut policy = FamilyPolicy::default();
+        for reply in self.request(message, NLM_F_DUMP)? {
+            policy.add(&reply.payload.nlas).map_err(invalid_data)?;
+        }
+        let op = policy.ops.values().fold(OpPolicy::default(), |op, other| {
+            OpPolicy {
+                do_policy: op.do_policy.or(other.do_policy),
+                dump_policy: op.dump_policy.or(other.dump_policy),
+            }
+        });
+        policy.ops.clear();
+        policy.ops.insert(cmd, op);
+        Ok(policy)
+    }
+
+    /// Join the multicast group `group`
+    pub fn add_membership(&mut self, group: u32) -> io::Result<()> {
+        self.transport.add_membership(group)
+    }
+
+    /// Leave the multicast group `group`
+    pub fn drop_membership(&mut self, group: u32) -> io::Result<()> {
+        self.transport.drop_membership(group)
+    }
+}
+
+pub(crate) fn invalid_data(err: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+pub(crate) fn parse_frame<F>(
+    frame: &[u8],
+) -> io::Result<NetlinkMessage<GenlMessage<F>>>
+where
+    F: ParseableParametrized<[u8], GenlHeader> + Debug,
+{
+    NetlinkMessage::deserialize(frame).map_err(invalid_data)
+}
+
+/// Return the `message_type` field of a netlink message
+pub(crate) fn frame_message_type(frame: &[u8]) -> u16 {
+    NetlinkBuffer::new(frame).message_type()
+}
+
+fn frame_sequence_number(frame: &[u8]) -> u32 {
+    NetlinkBuffer::new(frame).sequence_number()
+}
+
+pub(crate) fn split_frames(buf: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+    NetlinkFrames::new(buf)
+        .map(|frame| frame.map(<[u8]>::to_vec).map_err(invalid_data))
+        .collect()
+}
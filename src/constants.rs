@@ -4,6 +4,12 @@
 pub const GENL_ID_CTRL: u16 = 16;
 pub const GENL_HDRLEN: usize = 4;
 
+pub const GENL_ADMIN_PERM: u32 = 0x01;
+pub const GENL_CMD_CAP_DO: u32 = 0x02;
+pub const GENL_CMD_CAP_DUMP: u32 = 0x04;
+pub const GENL_CMD_CAP_HASPOL: u32 = 0x08;
+pub const GENL_UNS_ADMIN_PERM: u32 = 0x10;
+
 pub const CTRL_CMD_UNSPEC: u8 = 0;
 pub const CTRL_CMD_NEWFAMILY: u8 = 1;
 pub const CTRL_CMD_DELFAMILY: u8 = 2;
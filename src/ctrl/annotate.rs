@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MIT
+
+use super::GenlCtrl;
+use crate::{
+    constants::*,
+    hexdump::{Annotate, NlaFormat, NlaFormat::*, NlaSpec},
+};
+
+impl Annotate for GenlCtrl {
+    fn command_name(cmd: u8) -> Option<&'static str> {
+        Some(match cmd {
+            CTRL_CMD_UNSPEC => "CTRL_CMD_UNSPEC",
+            CTRL_CMD_NEWFAMILY => "CTRL_CMD_NEWFAMILY",
+            CTRL_CMD_DELFAMILY => "CTRL_CMD_DELFAMILY",
+            CTRL_CMD_GETFAMILY => "CTRL_CMD_GETFAMILY",
+            CTRL_CMD_NEWOPS => "CTRL_CMD_NEWOPS",
+            CTRL_CMD_DELOPS => "CTRL_CMD_DELOPS",
+            CTRL_CMD_GETOPS => "CTRL_CMD_GETOPS",
+            CTRL_CMD_NEWMCAST_GRP => "CTRL_CMD_NEWMCAST_GRP",
+            CTRL_CMD_DELMCAST_GRP => "CTRL_CMD_DELMCAST_GRP",
+            CTRL_CMD_GETMCAST_GRP => "CTRL_CMD_GETMCAST_GRP",
+            CTRL_CMD_GETPOLICY => "CTRL_CMD_GETPOLICY",
+            _ => return None,
+        })
+    }
+
+    fn attribute(path: &[u16], kind: u16) -> Option<NlaSpec> {
+        let (name, format) = match (path, kind) {
+            ([], CTRL_ATTR_FAMILY_ID) => ("CTRL_ATTR_FAMILY_ID", U16),
+            ([], CTRL_ATTR_FAMILY_NAME) => ("CTRL_ATTR_FAMILY_NAME", String),
+            ([], CTRL_ATTR_VERSION) => ("CTRL_ATTR_VERSION", U32),
+            ([], CTRL_ATTR_HDRSIZE) => ("CTRL_ATTR_HDRSIZE", U32),
+            ([], CTRL_ATTR_MAXATTR) => ("CTRL_ATTR_MAXATTR", U32),
+            ([], CTRL_ATTR_OPS) => ("CTRL_ATTR_OPS", Nested),
+            ([], CTRL_ATTR_MCAST_GROUPS) => ("CTRL_ATTR_MCAST_GROUPS", Nested),
+            ([], CTRL_ATTR_POLICY) => ("CTRL_ATTR_POLICY", Nested),
+            ([], CTRL_ATTR_OP_POLICY) => ("CTRL_ATTR_OP_POLICY", Nested),
+            ([], CTRL_ATTR_OP) => ("CTRL_ATTR_OP", U32),
+
+            ([CTRL_ATTR_OPS], _) => ("op", Nested),
+            ([CTRL_ATTR_OPS, _], CTRL_ATTR_OP_ID) => ("CTRL_ATTR_OP_ID", U32),
+            ([CTRL_ATTR_OPS, _], CTRL_ATTR_OP_FLAGS) => {
+                ("CTRL_ATTR_OP_FLAGS", U32)
+            }
+
+            ([CTRL_ATTR_MCAST_GROUPS], _) => ("mcast group", Nested),
+            ([CTRL_ATTR_MCAST_GROUPS, _], CTRL_ATTR_MCAST_GRP_NAME) => {
+                ("CTRL_ATTR_MCAST_GRP_NAME", String)
+            }
+            ([CTRL_ATTR_MCAST_GROUPS, _], CTRL_ATTR_MCAST_GRP_ID) => {
+                ("CTRL_ATTR_MCAST_GRP_ID", U32)
+            }
+
+            ([CTRL_ATTR_OP_POLICY], _) => ("command", Nested),
+            ([CTRL_ATTR_OP_POLICY, _], CTRL_ATTR_POLICY_DO) => {
+                ("CTRL_ATTR_POLICY_DO", U32)
+            }
+            ([CTRL_ATTR_OP_POLICY, _], CTRL_ATTR_POLICY_DUMP) => {
+                ("CTRL_ATTR_POLICY_DUMP", U32)
+            }
+
+            ([CTRL_ATTR_POLICY], _) => ("policy", Nested),
+            ([CTRL_ATTR_POLICY, _], _) => ("attribute", Nested),
+            ([CTRL_ATTR_POLICY, _, _], kind) => policy_type_attr(kind)?,
+
+            _ => return None,
+        };
+        Some(NlaSpec::new(name, format))
+    }
+}
+
+fn policy_type_attr(kind: u16) -> Option<(&'static str, NlaFormat)> {
+    Some(match kind {
+        NL_POLICY_TYPE_ATTR_TYPE => ("NL_POLICY_TYPE_ATTR_TYPE", U32),
+        NL_POLICY_TYPE_ATTR_MIN_VALUE_S => {
+            ("NL_POLICY_TYPE_ATTR_MIN_VALUE_S", S64)
+        }
+        NL_POLICY_TYPE_ATTR_MAX_VALUE_S => {
+            ("NL_POLICY_TYPE_ATTR_MAX_VALUE_S", S64)
+        }
+        NL_POLICY_TYPE_ATTR_MIN_VALUE_U => {
+            ("NL_POLICY_TYPE_ATTR_MIN_VALUE_U", U64)
+        }
+        NL_POLICY_TYPE_ATTR_MAX_VALUE_U => {
+            ("NL_POLICY_TYPE_ATTR_MAX_VALUE_U", U64)
+        }
+        NL_POLICY_TYPE_ATTR_MIN_LENGTH => {
+            ("NL_POLICY_TYPE_ATTR_MIN_LENGTH", U32)
+        }
+        NL_POLICY_TYPE_ATTR_MAX_LENGTH => {
+            ("NL_POLICY_TYPE_ATTR_MAX_LENGTH", U32)
+        }
+        NL_POLICY_TYPE_ATTR_POLICY_IDX => {
+            ("NL_POLICY_TYPE_ATTR_POLICY_IDX", U32)
+        }
+        NL_POLICY_TYPE_ATTR_POLICY_MAXTYPE => {
+            ("NL_POLICY_TYPE_ATTR_POLICY_MAXTYPE", U32)
+        }
+        NL_POLICY_TYPE_ATTR_BITFIELD32_MASK => {
+            ("NL_POLICY_TYPE_ATTR_BITFIELD32_MASK", U32)
+        }
+        NL_POLICY_TYPE_ATTR_PAD => ("NL_POLICY_TYPE_ATTR_PAD", Binary),
+        NL_POLICY_TYPE_ATTR_MASK => ("NL_POLICY_TYPE_ATTR_MASK", U64),
+        _ => return None,
+    })
+}
@@ -0,0 +1,410 @@
+// SPDX-License-Identifier: MIT
+
+//! Comparison of the interface of a family between two kernels
+
+use super::{nlas::NlaType, AttributePolicy, FamilyInfo, FamilyPolicy};
+use crate::constants::{
+    GENL_ADMIN_PERM, GENL_CMD_CAP_DO, GENL_CMD_CAP_DUMP, GENL_UNS_ADMIN_PERM,
+};
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{convert::TryFrom, fmt};
+
+/// Family information and policy, as dumped from a kernel
+///
+/// With the `serde` feature, snapshots can be saved on a machine and
+/// compared later to the snapshot of another kernel with
+/// [`diff()`](Self::diff).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FamilySnapshot {
+    /// Commands and multicast groups of the family
+    pub info: FamilyInfo,
+    /// Attribute policies of the commands
+    pub policy: FamilyPolicy,
+}
+
+/// Kind of request of a command
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RequestKind {
+    /// Requests acting on a single object, without `NLM_F_DUMP`
+    Do,
+    /// Requests with `NLM_F_DUMP`, listing objects
+    Dump,
+}
+
+/// Lower and upper limits of a value or a length, unbounded if `None`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bounds<T> {
+    /// Lowest allowed value, inclusive
+    pub min: Option<T>,
+    /// Highest allowed value, inclusive
+    pub max: Option<T>,
+}
+
+/// A change of the interface of a family
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PolicyChange {
+    /// Command affected by the change
+    pub cmd: u32,
+    /// Requests affected by the change, `None` for the command itself
+    pub request: Option<RequestKind>,
+    /// Types of the attribute and of its parents, empty for the command
+    pub path: Vec<u16>,
+    /// What changed
+    pub kind: ChangeKind,
+}
+
+/// Kind of change of the interface of a family
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ChangeKind {
+    /// The command was added
+    CommandAdded,
+    /// The command was removed
+    CommandRemoved,
+    /// The flags of the command changed, e.g. the supported requests or the
+    /// required permission
+    FlagsChanged { old: u32, new: u32 },
+    /// The attribute was added to the policy of the requests
+    AttributeAdded,
+    /// The attribute was removed from the policy of the requests
+    AttributeRemoved,
+    /// The type of the attribute changed
+    TypeChanged { old: NlaType, new: NlaType },
+    /// The range of the values of an integer attribute changed
+    ValuesChanged {
+        old: Bounds<i128>,
+        new: Bounds<i128>,
+    },
+    /// The range of the length of a string or binary attribute changed
+    LengthChanged { old: Bounds<u32>, new: Bounds<u32> },
+    /// The mask of the allowed bits of the attribute changed, `None` if
+    /// any bit is allowed
+    MaskChanged { old: Option<u64>, new: Option<u64> },
+}
+
+impl FamilySnapshot {
+    /// Return the changes from `self` to `new`
+    ///
+    /// The attributes are identified by the command and the request using
+    /// them, and by their path of attribute types, as the indexes of the
+    /// policies depend on the kernel.
+    pub fn diff(&self, new: &FamilySnapshot) -> Vec<PolicyChange> {
+        let mut changes = Vec::new();
+        for old_op in &self.info.ops {
+            let Some(new_op) = new.info.op(old_op.id) else {
+                changes.push(PolicyChange::command(
+                    old_op.id,
+                    ChangeKind::CommandRemoved,
+                ));
+                continue;
+            };
+            if old_op.flags != new_op.flags {
+                changes.push(PolicyChange::command(
+                    old_op.id,
+                    ChangeKind::FlagsChanged {
+                        old: old_op.flags,
+                        new: new_op.flags,
+                    },
+                ));
+            }
+            for request in [RequestKind::Do, RequestKind::Dump] {
+                let mut diff = PolicyDiff {
+                    old: &self.policy,
+                    new: &new.policy,
+                    cmd: old_op.id,
+                    request,
+                    parents: Vec::new(),
+                    changes: &mut changes,
+                };
+                diff.policies(
+                    &[],
+                    self.policy_index(old_op.id, request),
+                    new.policy_index(old_op.id, request),
+                );
+            }
+        }
+        for new_op in &new.info.ops {
+            if self.info.op(new_op.id).is_none() {
+                changes.push(PolicyChange::command(
+                    new_op.id,
+                    ChangeKind::CommandAdded,
+                ));
+            }
+        }
+        changes
+    }
+
+    fn policy_index(&self, cmd: u32, request: RequestKind) -> Option<u32> {
+        let op = self.policy.op(u8::try_from(cmd).ok()?)?;
+        match request {
+            RequestKind::Do => op.do_policy,
+            RequestKind::Dump => op.dump_policy,
+        }
+    }
+}
+
+impl PolicyChange {
+    fn command(cmd: u32, kind: ChangeKind) -> Self {
+        Self {
+            cmd,
+            request: None,
+            path: Vec::new(),
+            kind,
+        }
+    }
+
+    /// Return whether the change may break users of the family, e.g. a
+    /// removed command or attribute, or a narrowed range
+    pub fn is_breaking(&self) -> bool {
+        match &self.kind {
+            ChangeKind::CommandAdded | ChangeKind::AttributeAdded => false,
+            ChangeKind::CommandRemoved
+            | ChangeKind::AttributeRemoved
+            | ChangeKind::TypeChanged { .. } => true,
+            ChangeKind::FlagsChanged { old, new } => {
+                let lost = old & !new & (GENL_CMD_CAP_DO | GENL_CMD_CAP_DUMP);
+                lost != 0 || permission(*new) > permission(*old)
+            }
+            ChangeKind::ValuesChanged { old, new } => !old.is_within(new),
+            ChangeKind::LengthChanged { old, new } => !old.is_within(new),
+            ChangeKind::MaskChanged { old, new } => match (old, new) {
+                (Some(old), Some(new)) => old & !new != 0,
+                (None, Some(_)) => true,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl<T: PartialOrd + Copy> Bounds<T> {
+    /// Return whether all the values within `self` are within `other`
+    pub fn is_within(&self, other: &Bounds<T>) -> bool {
+        let min = match (self.min, other.min) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(min), Some(other)) => min >= other,
+        };
+        let max = match (self.max, other.max) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(max), Some(other)) => max <= other,
+        };
+        min && max
+    }
+}
+
+struct PolicyDiff<'a> {
+    old: &'a FamilyPolicy,
+    new: &'a FamilyPolicy,
+    cmd: u32,
+    request: RequestKind,
+    /// Pairs of policies compared by the parents of the current attribute,
+    /// not compared again as policies can be recursive
+    parents: Vec<(Option<u32>, Option<u32>)>,
+    changes: &'a mut Vec<PolicyChange>,
+}
+
+impl PolicyDiff<'_> {
+    fn push(&mut self, path: Vec<u16>, kind: ChangeKind) {
+        self.changes.push(PolicyChange {
+            cmd: self.cmd,
+            request: Some(self.request),
+            path,
+            kind,
+        });
+    }
+
+    fn policies(&mut self, path: &[u16], old: Option<u32>, new: Option<u32>) {
+        if self.parents.contains(&(old, new)) {
+            return;
+        }
+        self.parents.push((old, new));
+        let empty = Default::default();
+        let old_attrs = old
+            .and_then(|index| self.old.policies.get(&index))
+            .unwrap_or(&empty);
+        let new_attrs = new
+            .and_then(|index| self.new.policies.get(&index))
+            .unwrap_or(&empty);
+        let kinds: BTreeSet<u16> =
+            old_attrs.keys().chain(new_attrs.keys()).copied().collect();
+        for kind in kinds {
+            let mut path = path.to_vec();
+            path.push(kind);
+            match (old_attrs.get(&kind), new_attrs.get(&kind)) {
+                (Some(old), Some(new)) => self.attributes(path, old, new),
+                (Some(_), None) => {
+                    self.push(path, ChangeKind::AttributeRemoved)
+                }
+                (None, Some(_)) => self.push(path, ChangeKind::AttributeAdded),
+                (None, None) => (),
+            }
+        }
+        self.parents.pop();
+    }
+
+    fn attributes(
+        &mut self,
+        path: Vec<u16>,
+        old: &AttributePolicy,
+        new: &AttributePolicy,
+    ) {
+        if old.nla_type != new.nla_type {
+            self.push(
+                path,
+                ChangeKind::TypeChanged {
+                    old: old.nla_type,
+                    new: new.nla_type,
+                },
+            );
+            return;
+        }
+        let (old_values, new_values) = (values(old), values(new));
+        if old_values != new_values {
+            self.push(
+                path.clone(),
+                ChangeKind::ValuesChanged {
+                    old: old_values,
+                    new: new_values,
+                },
+            );
+        }
+        let (old_length, new_length) = (length(old), length(new));
+        if old_length != new_length {
+            self.push(
+                path.clone(),
+                ChangeKind::LengthChanged {
+                    old: old_length,
+                    new: new_length,
+                },
+            );
+        }
+        let (old_mask, new_mask) = (mask(old), mask(new));
+        if old_mask != new_mask {
+            self.push(
+                path.clone(),
+                ChangeKind::MaskChanged {
+                    old: old_mask,
+                    new: new_mask,
+                },
+            );
+        }
+        if old.policy_idx.is_some() || new.policy_idx.is_some() {
+            self.policies(&path, old.policy_idx, new.policy_idx);
+        }
+    }
+}
+
+/// Rank the permission required by a command, from the least to the most
+/// restrictive
+fn permission(flags: u32) -> u8 {
+    if flags & GENL_ADMIN_PERM != 0 {
+        2
+    } else if flags & GENL_UNS_ADMIN_PERM != 0 {
+        1
+    } else {
+        0
+    }
+}
+
+fn values(policy: &AttributePolicy) -> Bounds<i128> {
+    Bounds {
+        min: policy
+            .min_value_signed
+            .map(i128::from)
+            .or(policy.min_value_unsigned.map(i128::from)),
+        max: policy
+            .max_value_signed
+            .map(i128::from)
+            .or(policy.max_value_unsigned.map(i128::from)),
+    }
+}
+
+fn length(policy: &AttributePolicy) -> Bounds<u32> {
+    Bounds {
+        min: policy.min_length,
+        max: policy.max_length,
+    }
+}
+
+fn mask(policy: &AttributePolicy) -> Option<u64> {
+    policy.mask.or(policy.bitfield32_mask.map(u64::from))
+}
+
+impl<T: fmt::Display> fmt::Display for Bounds<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.min, &self.max) {
+            (Some(min), Some(max)) => write!(f, "{min}..={max}"),
+            (Some(min), None) => write!(f, "{min}.."),
+            (None, Some(max)) => write!(f, "..={max}"),
+            (None, None) => write!(f, ".."),
+        }
+    }
+}
+
+impl fmt::Display for PolicyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "command {}", self.cmd)?;
+        match self.request {
+            Some(RequestKind::Do) => write!(f, " do")?,
+            Some(RequestKind::Dump) => write!(f, " dump")?,
+            None => (),
+        }
+        let mut separator = " attribute ";
+        for kind in &self.path {
+            write!(f, "{separator}{kind}")?;
+            separator = "/";
+        }
+        write!(f, ": ")?;
+        match &self.kind {
+            ChangeKind::CommandAdded => write!(f, "added")?,
+            ChangeKind::CommandRemoved => write!(f, "removed")?,
+            ChangeKind::FlagsChanged { old, new } => {
+                write!(f, "flags 0x{old:x} -> 0x{new:x}")?
+            }
+            ChangeKind::AttributeAdded => write!(f, "added")?,
+            ChangeKind::AttributeRemoved => write!(f, "removed")?,
+            ChangeKind::TypeChanged { old, new } => {
+                write!(f, "type {old} -> {new}")?
+            }
+            ChangeKind::ValuesChanged { old, new } => {
+                write!(f, "values {old} -> {new}")?
+            }
+            ChangeKind::LengthChanged { old, new } => {
+                write!(f, "length {old} -> {new}")?
+            }
+            ChangeKind::MaskChanged { old, new } => {
+                write!(f, "mask {} -> {}", Mask(*old), Mask(*new))?
+            }
+        }
+        if self.is_breaking() {
+            write!(f, " (breaking)")?;
+        } else {
+            write!(f, " (compatible)")?;
+        }
+        Ok(())
+    }
+}
+
+struct Mask(Option<u64>);
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(mask) => write!(f, "0x{mask:x}"),
+            None => write!(f, "none"),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// SPDX-License-Identifier: MIT
+
+use super::{nlas::NlaType, AttributePolicy, FamilyPolicy};
+use crate::visit::{visit_nlas, NlaVisitor};
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Write, mem::size_of};
+use netlink_packet_core::{DecodeError, NLA_F_NET_BYTEORDER, NLA_TYPE_MASK};
+
+impl FamilyPolicy {
+    /// Return the index of the policy of the attributes of command `cmd`
+    ///
+    /// This is the policy of its `do` requests, or for a command without
+    /// policy, e.g. a notification, the policy shared by all the commands of
+    /// a family having a single policy.
+    pub fn message_policy(&self, cmd: u8) -> Option<u32> {
+        if let Some(index) = self.op(cmd).and_then(|op| op.do_policy) {
+            return Some(index);
+        }
+        let mut indexes = self.ops.values().filter_map(|op| op.do_policy);
+        let index = indexes.next()?;
+        indexes.all(|other| other == index).then_some(index)
+    }
+
+    /// Format the attributes of `buf` as text, one attribute per line and
+    /// nested attributes indented
+    ///
+    /// The attributes are decoded according to the policy `index`, the
+    /// integers flagged with `NLA_F_NET_BYTEORDER` being big-endian. The
+    /// attributes without policy are formatted as bytes, unless they have
+    /// the `NLA_F_NESTED` flag set.
+    pub fn format_nlas(
+        &self,
+        index: Option<u32>,
+        buf: &[u8],
+    ) -> Result<String, DecodeError> {
+        let mut printer = Printer {
+            policy: self,
+            scopes: Vec::new(),
+            root: Scope::Policy(index),
+            out: String::new(),
+        };
+        visit_nlas(buf, &mut printer)?;
+        Ok(printer.out)
+    }
+}
+
+/// How the attributes at some nesting level are decoded
+#[derive(Clone, Copy)]
+enum Scope {
+    /// Attributes of a policy, if known
+    Policy(Option<u32>),
+    /// Elements of a nested array, whose attributes follow a policy
+    Array(Option<u32>),
+}
+
+struct Printer<'a> {
+    policy: &'a FamilyPolicy,
+    scopes: Vec<Scope>,
+    root: Scope,
+    out: String,
+}
+
+impl Printer<'_> {
+    fn scope(&self) -> Scope {
+        self.scopes.last().copied().unwrap_or(self.root)
+    }
+
+    fn attribute_policy(&self, kind: u16) -> Option<&AttributePolicy> {
+        match self.scope() {
+            Scope::Policy(Some(index)) => self.policy.attribute(index, kind),
+            _ => None,
+        }
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.scopes.len() {
+            self.out.push_str("  ");
+        }
+    }
+}
+
+impl NlaVisitor for Printer<'_> {
+    fn is_nested(&mut self, kind: u16, flagged: bool) -> bool {
+        if let Scope::Array(_) = self.scope() {
+            return true;
+        }
+        match self.attribute_policy(kind) {
+            Some(policy) => matches!(
+                policy.nla_type,
+                NlaType::Nested | NlaType::NestedArray
+            ),
+            None => flagged,
+        }
+    }
+
+    fn enter_nest(&mut self, kind: u16) {
+        self.indent();
+        let scope = match self.scope() {
+            Scope::Array(index) => {
+                let _ = writeln!(self.out, "[{kind}]");
+                Scope::Policy(index)
+            }
+            Scope::Policy(_) => {
+                let policy = self.attribute_policy(kind).copied();
+                let _ = writeln!(self.out, "{kind}: nested");
+                match policy {
+                    Some(policy) if policy.nla_type == NlaType::NestedArray => {
+                        Scope::Array(policy.policy_idx)
+                    }
+                    Some(policy) => Scope::Policy(policy.policy_idx),
+                    None => Scope::Policy(None),
+                }
+            }
+        };
+        self.scopes.push(scope);
+    }
+
+    fn leave_nest(&mut self, _kind: u16) {
+        self.scopes.pop();
+    }
+
+    fn raw_attribute(&mut self, raw_type: u16, value: &[u8]) {
+        let kind = raw_type & NLA_TYPE_MASK;
+        let big_endian = raw_type & NLA_F_NET_BYTEORDER != 0;
+        let nla_type = self.attribute_policy(kind).map(|p| p.nla_type);
+        self.indent();
+        let _ = write!(self.out, "{kind}: ");
+        if let Some(nla_type) = nla_type {
+            if write_value(&mut self.out, nla_type, value, big_endian).is_some()
+            {
+                self.out.push('\n');
+                return;
+            }
+        }
+        for byte in value {
+            let _ = write!(self.out, "{byte:02x}");
+        }
+        self.out.push('\n');
+    }
+}
+
+/// Write `value` decoded as `nla_type`, or nothing if it does not have the
+/// size of the type
+///
+/// Integers are in host byte order, or big-endian when `big_endian` is set.
+fn write_value(
+    out: &mut String,
+    nla_type: NlaType,
+    value: &[u8],
+    big_endian: bool,
+) -> Option<()> {
+    fn int<const N: usize>(value: &[u8]) -> Option<[u8; N]> {
+        value.try_into().ok()
+    }
+    // Value of the integers, in host byte order
+    let mut swapped = [0u8; 8];
+    let host = if big_endian
+        && cfg!(target_endian = "little")
+        && value.len() <= swapped.len()
+    {
+        let swapped = &mut swapped[..value.len()];
+        swapped.copy_from_slice(value);
+        swapped.reverse();
+        swapped
+    } else {
+        value
+    };
+    let _ = match nla_type {
+        NlaType::Flag if value.is_empty() => write!(out, "flag"),
+        NlaType::U8 => write!(out, "u8 {}", u8::from_ne_bytes(int(host)?)),
+        NlaType::U16 => write!(out, "u16 {}", u16::from_ne_bytes(int(host)?)),
+        NlaType::U32 => write!(out, "u32 {}", u32::from_ne_bytes(int(host)?)),
+        NlaType::U64 => write!(out, "u64 {}", u64::from_ne_bytes(int(host)?)),
+        NlaType::S8 => write!(out, "s8 {}", i8::from_ne_bytes(int(host)?)),
+        NlaType::S16 => write!(out, "s16 {}", i16::from_ne_bytes(int(host)?)),
+        NlaType::S32 => write!(out, "s32 {}", i32::from_ne_bytes(int(host)?)),
+        NlaType::S64 => write!(out, "s64 {}", i64::from_ne_bytes(int(host)?)),
+        NlaType::String | NlaType::NulString => {
+            let value = value.strip_suffix(&[0]).unwrap_or(value);
+            write!(out, "{:?}", String::from_utf8_lossy(value))
+        }
+        NlaType::Bitfield32 if value.len() == 2 * size_of::<u32>() => {
+            let bits = u32::from_ne_bytes(int(&value[..4])?);
+            let selector = u32::from_ne_bytes(int(&value[4..])?);
+            write!(out, "bitfield32 0x{bits:08x}/0x{selector:08x}")
+        }
+        _ => return None,
+    };
+    Some(())
+}
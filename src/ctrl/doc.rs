@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: MIT
+
+//! Reference documentation of a family, rendered as Markdown
+
+use super::{AttributePolicy, FamilyInfo, FamilyOp, FamilyPolicy};
+use crate::constants::{GENL_ADMIN_PERM, GENL_UNS_ADMIN_PERM};
+use core::{convert::TryFrom, fmt};
+
+/// Markdown reference of a family, listing its commands and the attributes
+/// accepted by each of them
+///
+/// The family is described by its information and its policy, as dumped
+/// from the controller, e.g. with
+/// [`GenlClient::get_family()`](crate::GenlClient::get_family) and
+/// [`GenlClient::get_policy()`](crate::GenlClient::get_policy).
+#[derive(Clone, Copy, Debug)]
+pub struct FamilyDoc<'a> {
+    info: &'a FamilyInfo,
+    policy: &'a FamilyPolicy,
+}
+
+impl<'a> FamilyDoc<'a> {
+    /// Describe the family `info`, whose attributes follow `policy`
+    pub fn new(info: &'a FamilyInfo, policy: &'a FamilyPolicy) -> Self {
+        Self { info, policy }
+    }
+
+    fn command(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        op: &FamilyOp,
+    ) -> fmt::Result {
+        writeln!(f, "### Command {}", op.id)?;
+        writeln!(f)?;
+        let requests = match (op.can_do(), op.can_dump()) {
+            (true, true) => "do, dump",
+            (true, false) => "do",
+            (false, true) => "dump",
+            (false, false) => "none",
+        };
+        writeln!(f, "- Requests: {requests}")?;
+        let permission = if op.flags & GENL_ADMIN_PERM != 0 {
+            "`CAP_NET_ADMIN`"
+        } else if op.flags & GENL_UNS_ADMIN_PERM != 0 {
+            "`CAP_NET_ADMIN` in the user namespace of the network namespace"
+        } else {
+            "none"
+        };
+        writeln!(f, "- Permission: {permission}")?;
+        let policy = u8::try_from(op.id).ok().and_then(|id| self.policy.op(id));
+        if let Some(index) = policy.and_then(|policy| policy.do_policy) {
+            writeln!(f, "- Do attributes: [policy {index}](#policy-{index})")?;
+        }
+        if let Some(index) = policy.and_then(|policy| policy.dump_policy) {
+            writeln!(
+                f,
+                "- Dump attributes: [policy {index}](#policy-{index})"
+            )?;
+        }
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for FamilyDoc<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = self.info;
+        writeln!(f, "# Family `{}`", info.name)?;
+        writeln!(f)?;
+        writeln!(f, "| Id | Version | Header size | Max attribute |")?;
+        writeln!(f, "|----|---------|-------------|---------------|")?;
+        writeln!(
+            f,
+            "| 0x{:04x} | {} | {} | {} |",
+            info.id, info.version, info.hdrsize, info.maxattr
+        )?;
+        writeln!(f)?;
+
+        if !info.mcast_groups.is_empty() {
+            writeln!(f, "## Multicast groups")?;
+            writeln!(f)?;
+            writeln!(f, "| Name | Id |")?;
+            writeln!(f, "|------|----|")?;
+            for grp in &info.mcast_groups {
+                writeln!(f, "| `{}` | {} |", grp.name, grp.id)?;
+            }
+            writeln!(f)?;
+        }
+
+        if !info.ops.is_empty() {
+            writeln!(f, "## Commands")?;
+            writeln!(f)?;
+            for op in &info.ops {
+                self.command(f, op)?;
+            }
+        }
+
+        if !self.policy.policies.is_empty() {
+            writeln!(f, "## Policies")?;
+            writeln!(f)?;
+        }
+        for (index, attributes) in &self.policy.policies {
+            writeln!(f, "### Policy {index}")?;
+            writeln!(f)?;
+            writeln!(f, "| Attribute | Type | Values | Length | Nested |")?;
+            writeln!(f, "|-----------|------|--------|--------|--------|")?;
+            for (kind, policy) in attributes {
+                writeln!(f, "| {kind} | {} |", Row(policy))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Cells of the row of an attribute, after its type
+struct Row<'a>(&'a AttributePolicy);
+
+impl fmt::Display for Row<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let policy = self.0;
+        write!(f, "{} | ", policy.nla_type)?;
+        write_range(f, policy.min_value_signed, policy.max_value_signed)?;
+        write_range(f, policy.min_value_unsigned, policy.max_value_unsigned)?;
+        if let Some(mask) = policy.bitfield32_mask {
+            write!(f, "mask 0x{mask:08x}")?;
+        }
+        if let Some(mask) = policy.mask {
+            write!(f, "mask 0x{mask:x}")?;
+        }
+        write!(f, " | ")?;
+        write_range(f, policy.min_length, policy.max_length)?;
+        write!(f, " | ")?;
+        match (policy.policy_idx, policy.policy_max_type) {
+            (Some(index), Some(max)) => {
+                write!(f, "[policy {index}](#policy-{index}), max type {max}")?
+            }
+            (Some(index), None) => {
+                write!(f, "[policy {index}](#policy-{index})")?
+            }
+            (None, Some(max)) => write!(f, "max type {max}")?,
+            (None, None) => (),
+        }
+        Ok(())
+    }
+}
+
+fn write_range<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    min: Option<T>,
+    max: Option<T>,
+) -> fmt::Result {
+    match (min, max) {
+        (Some(min), Some(max)) => write!(f, "{min}..={max}"),
+        (Some(min), None) => write!(f, ">= {min}"),
+        (None, Some(max)) => write!(f, "<= {max}"),
+        (None, None) => Ok(()),
+    }
+}
@@ -0,0 +1,278 @@
+// SPDX-License-Identifier: MIT
+
+//! Condensed view of a family registered in the controller
+
+use super::nlas::{GenlCtrlAttrs, McastGrpAttrs, OpAttrs};
+#[cfg(feature = "std")]
+use crate::constants::GENL_ID_CTRL;
+use crate::{
+    constants::{
+        GENL_ADMIN_PERM, GENL_CMD_CAP_DO, GENL_CMD_CAP_DUMP,
+        GENL_CMD_CAP_HASPOL, GENL_UNS_ADMIN_PERM,
+    },
+    NlaArray,
+};
+use alloc::{string::String, vec, vec::Vec};
+use core::{convert::TryFrom, fmt};
+use netlink_packet_core::DecodeError;
+
+/// Information of a generic netlink family, as reported by the controller in
+/// `CTRL_CMD_NEWFAMILY` messages
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FamilyInfo {
+    /// Family ID assigned by the kernel
+    pub id: u16,
+    /// Registered family name
+    pub name: String,
+    /// Family version
+    pub version: u32,
+    /// Length of the family specific header
+    pub hdrsize: u32,
+    /// Maximum attribute number of the family
+    pub maxattr: u32,
+    /// Commands supported by the family
+    pub ops: Vec<FamilyOp>,
+    /// Multicast groups of the family
+    pub mcast_groups: Vec<FamilyMcastGroup>,
+}
+
+/// A command supported by a family
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FamilyOp {
+    /// Command id
+    pub id: u32,
+    /// Command flags (`GENL_ADMIN_PERM`, `GENL_CMD_CAP_DO`, ...)
+    pub flags: u32,
+}
+
+/// A multicast group of a family
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FamilyMcastGroup {
+    /// Group name
+    pub name: String,
+    /// Group id, which is used to join the group
+    pub id: u32,
+}
+
+impl FamilyInfo {
+    /// Return the id of the multicast group called `name`
+    pub fn mcast_group_id(&self, name: &str) -> Option<u32> {
+        self.mcast_groups
+            .iter()
+            .find(|grp| grp.name == name)
+            .map(|grp| grp.id)
+    }
+
+    /// Return the command `id` if the family supports it
+    pub fn op(&self, id: u32) -> Option<&FamilyOp> {
+        self.ops.iter().find(|op| op.id == id)
+    }
+}
+
+impl fmt::Display for FamilyInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} (id 0x{:04x}, version {}, header size {}, max attribute {})",
+            self.name, self.id, self.version, self.hdrsize, self.maxattr
+        )?;
+        if !self.ops.is_empty() {
+            writeln!(f, "  commands:")?;
+            for op in &self.ops {
+                writeln!(f, "    {op}")?;
+            }
+        }
+        if !self.mcast_groups.is_empty() {
+            writeln!(f, "  multicast groups:")?;
+            for grp in &self.mcast_groups {
+                writeln!(f, "    {}: {}", grp.id, grp.name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FamilyOp {
+    /// Return whether the command accepts `do` requests
+    pub fn can_do(&self) -> bool {
+        self.flags & GENL_CMD_CAP_DO != 0
+    }
+
+    /// Return whether the command accepts `dump` requests
+    pub fn can_dump(&self) -> bool {
+        self.flags & GENL_CMD_CAP_DUMP != 0
+    }
+
+    /// Return whether the command validates its attributes with a policy
+    pub fn has_policy(&self) -> bool {
+        self.flags & GENL_CMD_CAP_HASPOL != 0
+    }
+}
+
+impl fmt::Display for FamilyOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const FLAGS: [(u32, &str); 5] = [
+            (GENL_CMD_CAP_DO, "do"),
+            (GENL_CMD_CAP_DUMP, "dump"),
+            (GENL_CMD_CAP_HASPOL, "policy"),
+            (GENL_ADMIN_PERM, "admin"),
+            (GENL_UNS_ADMIN_PERM, "netns-admin"),
+        ];
+        write!(f, "{}:", self.id)?;
+        let mut separator = " ";
+        for (flag, name) in FLAGS {
+            if self.flags & flag != 0 {
+                write!(f, "{separator}{name}")?;
+                separator = ", ";
+            }
+        }
+        let unknown = FLAGS
+            .iter()
+            .fold(self.flags, |rest, (flag, _)| rest & !flag);
+        if unknown != 0 {
+            write!(f, "{separator}0x{unknown:x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&[GenlCtrlAttrs]> for FamilyInfo {
+    type Error = DecodeError;
+
+    fn try_from(nlas: &[GenlCtrlAttrs]) -> Result<Self, Self::Error> {
+        let mut id = None;
+        let mut name = None;
+        let mut info = FamilyInfo::default();
+        for nla in nlas {
+            match nla {
+                GenlCtrlAttrs::FamilyId(v) => id = Some(*v),
+                GenlCtrlAttrs::FamilyName(v) => name = Some(v.clone()),
+                GenlCtrlAttrs::Version(v) => info.version = *v,
+                GenlCtrlAttrs::HdrSize(v) => info.hdrsize = *v,
+                GenlCtrlAttrs::MaxAttr(v) => info.maxattr = *v,
+                GenlCtrlAttrs::Ops(ops) => {
+                    info.ops = ops
+                        .iter()
+                        .map(|op| FamilyOp::from(&op.nlas[..]))
+                        .collect()
+                }
+                GenlCtrlAttrs::McastGroups(groups) => {
+                    info.mcast_groups = groups
+                        .iter()
+                        .map(|grp| FamilyMcastGroup::from(&grp.nlas[..]))
+                        .collect()
+                }
+                _ => (),
+            }
+        }
+        info.id = id.ok_or("missing CTRL_ATTR_FAMILY_ID")?;
+        info.name = name.ok_or("missing CTRL_ATTR_FAMILY_NAME")?;
+        Ok(info)
+    }
+}
+
+impl From<&FamilyInfo> for Vec<GenlCtrlAttrs> {
+    fn from(info: &FamilyInfo) -> Self {
+        let mut nlas = vec![
+            GenlCtrlAttrs::FamilyName(info.name.clone()),
+            GenlCtrlAttrs::FamilyId(info.id),
+            GenlCtrlAttrs::Version(info.version),
+            GenlCtrlAttrs::HdrSize(info.hdrsize),
+            GenlCtrlAttrs::MaxAttr(info.maxattr),
+        ];
+        if !info.ops.is_empty() {
+            nlas.push(GenlCtrlAttrs::Ops(NlaArray::new(
+                info.ops.iter().map(Vec::from),
+            )));
+        }
+        if !info.mcast_groups.is_empty() {
+            nlas.push(GenlCtrlAttrs::McastGroups(NlaArray::new(
+                info.mcast_groups.iter().map(Vec::from),
+            )));
+        }
+        nlas
+    }
+}
+
+impl From<&[OpAttrs]> for FamilyOp {
+    fn from(nlas: &[OpAttrs]) -> Self {
+        let mut op = FamilyOp::default();
+        for nla in nlas {
+            match nla {
+                OpAttrs::Id(v) => op.id = *v,
+                OpAttrs::Flags(v) => op.flags = *v,
+            }
+        }
+        op
+    }
+}
+
+impl From<&FamilyOp> for Vec<OpAttrs> {
+    fn from(op: &FamilyOp) -> Self {
+        vec![OpAttrs::Id(op.id), OpAttrs::Flags(op.flags)]
+    }
+}
+
+impl From<&[McastGrpAttrs]> for FamilyMcastGroup {
+    fn from(nlas: &[McastGrpAttrs]) -> Self {
+        let mut grp = FamilyMcastGroup::default();
+        for nla in nlas {
+            match nla {
+                McastGrpAttrs::Name(v) => grp.name = v.clone(),
+                McastGrpAttrs::Id(v) => grp.id = *v,
+            }
+        }
+        grp
+    }
+}
+
+impl From<&FamilyMcastGroup> for Vec<McastGrpAttrs> {
+    fn from(grp: &FamilyMcastGroup) -> Self {
+        vec![
+            McastGrpAttrs::Id(grp.id),
+            McastGrpAttrs::Name(grp.name.clone()),
+        ]
+    }
+}
+
+/// Family names by id, learnt from the controller messages
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FamilyNames(Vec<(u16, String)>);
+
+#[cfg(feature = "std")]
+impl Default for FamilyNames {
+    fn default() -> Self {
+        Self(vec![(GENL_ID_CTRL, "nlctrl".to_owned())])
+    }
+}
+
+#[cfg(feature = "std")]
+impl FamilyNames {
+    /// Remember the family described by a controller message carrying both
+    /// its id and its name
+    pub(crate) fn learn(&mut self, nlas: &[GenlCtrlAttrs]) {
+        let id = nlas.iter().find_map(|nla| match nla {
+            GenlCtrlAttrs::FamilyId(id) => Some(*id),
+            _ => None,
+        });
+        let name = nlas.iter().find_map(|nla| match nla {
+            GenlCtrlAttrs::FamilyName(name) => Some(name),
+            _ => None,
+        });
+        if let (Some(id), Some(name)) = (id, name) {
+            self.0.retain(|(known, _)| *known != id);
+            self.0.push((id, name.clone()));
+        }
+    }
+
+    pub(crate) fn get(&self, id: u16) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(known, _)| *known == id)
+            .map(|(_, name)| name.as_str())
+    }
+}
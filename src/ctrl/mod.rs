@@ -6,18 +6,48 @@
 //! It also serves as an example for creating a generic family.
 
 use self::nlas::*;
-use crate::{constants::*, traits::*, GenlHeader};
+use crate::{
+    canonical::{canonicalize_nlas, AttributeSet, Canonicalize},
+    constants::*,
+    traits::*,
+    GenlHeader,
+};
+use alloc::{format, vec::Vec};
+use core::convert::{TryFrom, TryInto};
 use netlink_packet_core::{
     DecodeError, Emitable, ErrorContext, NlasIterator, Parseable,
     ParseableParametrized,
 };
-use std::convert::{TryFrom, TryInto};
+
+mod annotate;
+mod decode;
+
+mod compat;
+pub use self::compat::*;
+
+mod doc;
+pub use self::doc::*;
+
+mod family;
+pub use self::family::*;
+
+mod policy;
+pub use self::policy::*;
+
+mod spec;
+pub use self::spec::*;
 
 /// Netlink attributes for this family
 pub mod nlas;
 
 /// Command code definition of Netlink controller (nlctrl) family
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum GenlCtrlCmd {
     /// Notify from event
     NewFamily,
@@ -86,6 +116,8 @@ impl TryFrom<u8> for GenlCtrlCmd {
 
 /// Payload of generic netlink controller
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GenlCtrl {
     /// Command code of this message
     pub cmd: GenlCtrlCmd,
@@ -111,6 +143,20 @@ impl GenlFamily for GenlCtrl {
     }
 }
 
+impl Canonicalize for GenlCtrl {
+    fn canonicalize(&mut self) {
+        canonicalize_nlas(&mut self.nlas);
+    }
+}
+
+impl AttributeSet for GenlCtrl {
+    type Nla = GenlCtrlAttrs;
+
+    fn nlas(&self) -> &[GenlCtrlAttrs] {
+        &self.nlas
+    }
+}
+
 impl Emitable for GenlCtrl {
     fn emit(&self, buffer: &mut [u8]) {
         self.nlas.as_slice().emit(buffer)
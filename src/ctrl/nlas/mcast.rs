@@ -1,64 +1,27 @@
 // SPDX-License-Identifier: MIT
 
-use crate::constants::*;
+use crate::{canonical::Canonicalize, constants::*};
+use alloc::{format, string::String};
+use core::mem::size_of_val;
 use netlink_packet_core::{
-    emit_u32, parse_string, parse_u32, DecodeError, Emitable, ErrorContext,
-    Nla, NlaBuffer, Parseable,
+    emit_u32, parse_string, parse_u32, DecodeError, ErrorContext, Nla,
+    NlaBuffer, Parseable,
 };
-use std::{mem::size_of_val, ops::Deref};
-
-pub(crate) struct McastGroupList(Vec<McastGroup>);
-
-impl Deref for McastGroupList {
-    type Target = Vec<McastGroup>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl From<&Vec<Vec<McastGrpAttrs>>> for McastGroupList {
-    fn from(groups: &Vec<Vec<McastGrpAttrs>>) -> Self {
-        Self(
-            groups
-                .iter()
-                .cloned()
-                .enumerate()
-                .map(|(index, nlas)| McastGroup {
-                    index: index as u16,
-                    nlas,
-                })
-                .collect(),
-        )
-    }
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct McastGroup {
-    pub index: u16,
-    pub nlas: Vec<McastGrpAttrs>,
-}
-
-impl Nla for McastGroup {
-    fn value_len(&self) -> usize {
-        self.nlas.as_slice().buffer_len()
-    }
-
-    fn kind(&self) -> u16 {
-        self.index + 1
-    }
-
-    fn emit_value(&self, buffer: &mut [u8]) {
-        self.nlas.as_slice().emit(buffer);
-    }
-}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum McastGrpAttrs {
     Name(String),
     Id(u32),
 }
 
+impl Canonicalize for McastGrpAttrs {}
+
 impl Nla for McastGrpAttrs {
     fn value_len(&self) -> usize {
         use McastGrpAttrs::*;
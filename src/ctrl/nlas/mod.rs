@@ -1,11 +1,12 @@
 // SPDX-License-Identifier: MIT
 
-use crate::constants::*;
+use crate::{canonical::Canonicalize, constants::*, NlaArray};
+use alloc::{format, string::String};
+use core::mem::size_of_val;
 use netlink_packet_core::{
     emit_u16, emit_u32, parse_string, parse_u16, parse_u32, DecodeError,
-    Emitable, ErrorContext, Nla, NlaBuffer, NlasIterator, Parseable,
+    Emitable, ErrorContext, Nla, NlaBuffer, Parseable,
 };
-use std::mem::size_of_val;
 
 mod mcast;
 mod oppolicy;
@@ -18,19 +19,38 @@ pub use ops::*;
 pub use policy::*;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum GenlCtrlAttrs {
     FamilyId(u16),
     FamilyName(String),
     Version(u32),
     HdrSize(u32),
     MaxAttr(u32),
-    Ops(Vec<Vec<OpAttrs>>),
-    McastGroups(Vec<Vec<McastGrpAttrs>>),
+    Ops(NlaArray<OpAttrs>),
+    McastGroups(NlaArray<McastGrpAttrs>),
     Policy(PolicyAttr),
     OpPolicy(OppolicyAttr),
     Op(u32),
 }
 
+impl Canonicalize for GenlCtrlAttrs {
+    fn canonicalize(&mut self) {
+        use GenlCtrlAttrs::*;
+        match self {
+            Ops(ops) => ops.canonicalize(),
+            McastGroups(groups) => groups.canonicalize(),
+            Policy(policy) => policy.canonicalize(),
+            OpPolicy(policy) => policy.canonicalize(),
+            _ => (),
+        }
+    }
+}
+
 impl Nla for GenlCtrlAttrs {
     fn value_len(&self) -> usize {
         use GenlCtrlAttrs::*;
@@ -40,10 +60,8 @@ impl Nla for GenlCtrlAttrs {
             Version(v) => size_of_val(v),
             HdrSize(v) => size_of_val(v),
             MaxAttr(v) => size_of_val(v),
-            Ops(nlas) => OpList::from(nlas).as_slice().buffer_len(),
-            McastGroups(nlas) => {
-                McastGroupList::from(nlas).as_slice().buffer_len()
-            }
+            Ops(array) => array.buffer_len(),
+            McastGroups(array) => array.buffer_len(),
             Policy(nla) => nla.buffer_len(),
             OpPolicy(nla) => nla.buffer_len(),
             Op(v) => size_of_val(v),
@@ -77,17 +95,17 @@ impl Nla for GenlCtrlAttrs {
             Version(v) => emit_u32(buffer, *v).unwrap(),
             HdrSize(v) => emit_u32(buffer, *v).unwrap(),
             MaxAttr(v) => emit_u32(buffer, *v).unwrap(),
-            Ops(nlas) => {
-                OpList::from(nlas).as_slice().emit(buffer);
-            }
-            McastGroups(nlas) => {
-                McastGroupList::from(nlas).as_slice().emit(buffer);
-            }
-            Policy(nla) => nla.emit_value(buffer),
-            OpPolicy(nla) => nla.emit_value(buffer),
+            Ops(array) => array.emit(buffer),
+            McastGroups(array) => array.emit(buffer),
+            Policy(nla) => nla.emit(buffer),
+            OpPolicy(nla) => nla.emit(buffer),
             Op(v) => emit_u32(buffer, *v).unwrap(),
         }
     }
+
+    fn is_nested(&self) -> bool {
+        matches!(self, GenlCtrlAttrs::Policy(_) | GenlCtrlAttrs::OpPolicy(_))
+    }
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>>
@@ -116,44 +134,22 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>>
                 parse_u32(payload)
                     .context("invalid CTRL_ATTR_MAXATTR value")?,
             ),
-            CTRL_ATTR_OPS => {
-                let ops = NlasIterator::new(payload)
-                    .map(|nlas| {
-                        nlas.and_then(|nlas| {
-                            NlasIterator::new(nlas.value())
-                                .map(|nla| {
-                                    nla.and_then(|nla| OpAttrs::parse(&nla))
-                                })
-                                .collect::<Result<Vec<_>, _>>()
-                        })
-                    })
-                    .collect::<Result<Vec<Vec<_>>, _>>()
-                    .context("failed to parse CTRL_ATTR_OPS")?;
-                Self::Ops(ops)
-            }
-            CTRL_ATTR_MCAST_GROUPS => {
-                let groups = NlasIterator::new(payload)
-                    .map(|nlas| {
-                        nlas.and_then(|nlas| {
-                            NlasIterator::new(nlas.value())
-                                .map(|nla| {
-                                    nla.and_then(|nla| {
-                                        McastGrpAttrs::parse(&nla)
-                                    })
-                                })
-                                .collect::<Result<Vec<_>, _>>()
-                        })
-                    })
-                    .collect::<Result<Vec<Vec<_>>, _>>()
-                    .context("failed to parse CTRL_ATTR_MCAST_GROUPS")?;
-                Self::McastGroups(groups)
-            }
+            CTRL_ATTR_OPS => Self::Ops(
+                NlaArray::parse(buf)
+                    .context("failed to parse CTRL_ATTR_OPS")?,
+            ),
+            CTRL_ATTR_MCAST_GROUPS => Self::McastGroups(
+                NlaArray::parse(buf)
+                    .context("failed to parse CTRL_ATTR_MCAST_GROUPS")?,
+            ),
             CTRL_ATTR_POLICY => Self::Policy(
-                PolicyAttr::parse(&NlaBuffer::new(payload))
+                NlaBuffer::new_checked(payload)
+                    .and_then(|nla| PolicyAttr::parse(&nla))
                     .context("failed to parse CTRL_ATTR_POLICY")?,
             ),
             CTRL_ATTR_OP_POLICY => Self::OpPolicy(
-                OppolicyAttr::parse(&NlaBuffer::new(payload))
+                NlaBuffer::new_checked(payload)
+                    .and_then(|nla| OppolicyAttr::parse(&nla))
                     .context("failed to parse CTRL_ATTR_OP_POLICY")?,
             ),
             CTRL_ATTR_OP => Self::Op(parse_u32(payload)?),
@@ -170,6 +166,8 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{array::NlaArrayItem, ctrl::GenlCtrl, HexDump};
+    use netlink_packet_core::NlasIterator;
 
     #[test]
     fn mcast_groups_parse() {
@@ -189,16 +187,16 @@ mod tests {
             .expect("Failed to create NlaBuffer");
         let result_attr = GenlCtrlAttrs::parse(&nla_buffer)
             .expect("Failed to parse encoded McastGroups");
-        let expected_attr = GenlCtrlAttrs::McastGroups(vec![vec![
+        let expected_attr = GenlCtrlAttrs::McastGroups(NlaArray::new([vec![
             McastGrpAttrs::Id(1),
             McastGrpAttrs::Name("test".to_string()),
-        ]]);
+        ]]));
         assert_eq!(expected_attr, result_attr);
     }
 
     #[test]
     fn mcast_groups_emit() {
-        let mcast_attr = GenlCtrlAttrs::McastGroups(vec![
+        let mcast_attr = GenlCtrlAttrs::McastGroups(NlaArray::new([
             vec![
                 McastGrpAttrs::Id(7),
                 McastGrpAttrs::Name("group1".to_string()),
@@ -207,7 +205,7 @@ mod tests {
                 McastGrpAttrs::Id(8),
                 McastGrpAttrs::Name("group2".to_string()),
             ],
-        ]);
+        ]));
         let expected_bytes: [u8; 52] = [
             52, 0, // Netlink header length
             7, 0, // Netlink header kind (Mcast groups)
@@ -233,7 +231,13 @@ mod tests {
         let mut buf = vec![0u8; 100];
         mcast_attr.emit(&mut buf);
 
-        assert_eq!(&expected_bytes[..], &buf[..expected_bytes.len()]);
+        let buf = &buf[..expected_bytes.len()];
+        assert_eq!(
+            &expected_bytes[..],
+            buf,
+            "\n{}",
+            HexDump::<GenlCtrl>::attributes(buf)
+        );
     }
 
     #[test]
@@ -254,17 +258,26 @@ mod tests {
             .expect("Failed to create NlaBuffer");
         let result_attr = GenlCtrlAttrs::parse(&nla_buffer)
             .expect("Failed to parse encoded McastGroups");
-        let expected_attr =
-            GenlCtrlAttrs::Ops(vec![vec![OpAttrs::Id(1), OpAttrs::Flags(123)]]);
+        // The index of the op is kept even though it is not 1-based
+        let expected_attr = GenlCtrlAttrs::Ops(NlaArray {
+            items: vec![NlaArrayItem {
+                index: 0,
+                nlas: vec![OpAttrs::Id(1), OpAttrs::Flags(123)],
+            }],
+        });
         assert_eq!(expected_attr, result_attr);
+
+        let mut buf = vec![0u8; result_attr.buffer_len()];
+        result_attr.emit(&mut buf);
+        assert_eq!(&ops_bytes[..], &buf[..]);
     }
 
     #[test]
     fn ops_emit() {
-        let ops = GenlCtrlAttrs::Ops(vec![
+        let ops = GenlCtrlAttrs::Ops(NlaArray::new([
             vec![OpAttrs::Id(1), OpAttrs::Flags(11)],
             vec![OpAttrs::Id(3), OpAttrs::Flags(33)],
-        ]);
+        ]));
         let expected_bytes: [u8; 44] = [
             44, 0, // Netlink header length
             6, 0, // Netlink header kind (Ops)
@@ -290,4 +303,141 @@ mod tests {
 
         assert_eq!(&expected_bytes[..], &buf[..expected_bytes.len()]);
     }
+
+    #[test]
+    fn policy_parse_truncated() {
+        let policy_bytes: [u8; 8] = [
+            8, 0, // Netlink header length
+            8, 0x80, // Netlink header kind (nested Policy)
+            12, 0, // Policy nested NLA length, beyond the buffer
+            1, 0, // Policy index
+        ];
+        let nla_buffer = NlaBuffer::new_checked(&policy_bytes[..])
+            .expect("Failed to create NlaBuffer");
+        assert!(GenlCtrlAttrs::parse(&nla_buffer).is_err());
+    }
+
+    fn emit_parse(attr: &GenlCtrlAttrs, expected_bytes: &[u8]) {
+        let mut buf = vec![0u8; attr.buffer_len()];
+        attr.emit(&mut buf);
+        assert_eq!(
+            expected_bytes,
+            &buf[..],
+            "\n{}",
+            HexDump::<GenlCtrl>::attributes(&buf)
+        );
+
+        let nla_buffer = NlaBuffer::new_checked(&buf[..])
+            .expect("Failed to create NlaBuffer");
+        assert_eq!(attr, &GenlCtrlAttrs::parse(&nla_buffer).unwrap());
+    }
+
+    #[test]
+    fn policy_emit_nested() {
+        let policy = GenlCtrlAttrs::Policy(PolicyAttr {
+            index: 1,
+            attr_policy: AttributePolicyAttr {
+                index: 2,
+                policies: vec![NlPolicyTypeAttrs::Type(NlaType::U32)],
+            },
+        });
+        let expected_bytes: [u8; 20] = [
+            20, 0, // Netlink header length
+            8, 0x80, // Netlink header kind (nested Policy)
+            16, 0, // Policy nested NLA length
+            1, 0x80, // Policy index, nested
+            12, 0, // Attribute policy nested NLA length
+            2, 0x80, // Attribute type, nested
+            8, 0, // Type length
+            1, 0, // Type kind
+            4, 0, 0, 0, // Type (NL_ATTR_TYPE_U32)
+        ];
+        emit_parse(&policy, &expected_bytes);
+    }
+
+    #[test]
+    fn op_policy_emit_nested() {
+        let op_policy = GenlCtrlAttrs::OpPolicy(OppolicyAttr {
+            cmd: 3,
+            policy_idx: vec![OppolicyIndexAttr::Do(0)],
+        });
+        let expected_bytes: [u8; 16] = [
+            16, 0, // Netlink header length
+            9, 0x80, // Netlink header kind (nested OpPolicy)
+            12, 0, // OpPolicy nested NLA length
+            3, 0x80, // Command, nested
+            8, 0, // Do length
+            1, 0, // Do kind
+            0, 0, 0, 0, // Do policy index
+        ];
+        emit_parse(&op_policy, &expected_bytes);
+    }
+
+    #[test]
+    fn policy_type_emit() {
+        let policy_type = NlPolicyTypeAttrs::Type(NlaType::NulString);
+        let expected_bytes: [u8; 8] = [
+            8, 0, // Type length
+            1, 0, // Type kind
+            12, 0, 0, 0, // Type (NL_ATTR_TYPE_NUL_STRING), as a u32
+        ];
+        let mut buf = vec![0u8; policy_type.buffer_len()];
+        policy_type.emit(&mut buf);
+        assert_eq!(&expected_bytes[..], &buf[..]);
+
+        let nla_buffer = NlaBuffer::new_checked(&buf[..])
+            .expect("Failed to create NlaBuffer");
+        assert_eq!(policy_type, NlPolicyTypeAttrs::parse(&nla_buffer).unwrap());
+    }
+
+    #[test]
+    fn policy_unsigned_bounds_emit() {
+        let policies = vec![
+            NlPolicyTypeAttrs::MinValueUnsigned(1),
+            NlPolicyTypeAttrs::MaxValueUnsigned(10),
+        ];
+        let expected_bytes: [u8; 24] = [
+            12, 0, // Minimum length
+            4, 0, // Minimum kind (NL_POLICY_TYPE_ATTR_MIN_VALUE_U)
+            1, 0, 0, 0, 0, 0, 0, 0, // Minimum
+            12, 0, // Maximum length
+            5, 0, // Maximum kind (NL_POLICY_TYPE_ATTR_MAX_VALUE_U)
+            10, 0, 0, 0, 0, 0, 0, 0, // Maximum
+        ];
+        let mut buf = vec![0u8; policies.as_slice().buffer_len()];
+        policies.as_slice().emit(&mut buf);
+        assert_eq!(&expected_bytes[..], &buf[..]);
+
+        let parsed = NlasIterator::new(&buf[..])
+            .map(|nla| NlPolicyTypeAttrs::parse(&nla.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(policies, parsed);
+    }
+
+    #[test]
+    fn family_mcast_group_emit() {
+        use crate::ctrl::FamilyMcastGroup;
+
+        let group = FamilyMcastGroup {
+            name: "test".to_owned(),
+            id: 1,
+        };
+        let mcast_attr =
+            GenlCtrlAttrs::McastGroups(NlaArray::new([Vec::from(&group)]));
+        // Like the kernel, the id comes before the name
+        let expected_bytes: [u8; 28] = [
+            28, 0, // Netlink header length
+            7, 0, // Netlink header kind (Mcast groups)
+            24, 0, // Mcast group nested NLA length
+            1, 0, // Mcast group kind
+            8, 0, // Id length
+            2, 0, // Id kind
+            1, 0, 0, 0, // Id
+            9, 0, // Name length
+            1, 0, // Name kind
+            b't', b'e', b's', b't', 0, // Name
+            0, 0, 0, // padding
+        ];
+        emit_parse(&mcast_attr, &expected_bytes);
+    }
 }
@@ -1,18 +1,30 @@
 // SPDX-License-Identifier: MIT
 
-use crate::constants::*;
+use crate::{
+    canonical::{canonicalize_nlas, Canonicalize},
+    constants::*,
+};
+use alloc::{format, vec::Vec};
+use core::mem::size_of_val;
 use netlink_packet_core::{
     emit_u32, parse_u32, DecodeError, Emitable, ErrorContext, Nla, NlaBuffer,
     NlasIterator, Parseable,
 };
-use std::mem::size_of_val;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OppolicyAttr {
     pub cmd: u8,
     pub policy_idx: Vec<OppolicyIndexAttr>,
 }
 
+impl Canonicalize for OppolicyAttr {
+    fn canonicalize(&mut self) {
+        canonicalize_nlas(&mut self.policy_idx);
+    }
+}
+
 impl Nla for OppolicyAttr {
     fn value_len(&self) -> usize {
         self.policy_idx.as_slice().buffer_len()
@@ -47,11 +59,19 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>> for OppolicyAttr {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum OppolicyIndexAttr {
     Do(u32),
     Dump(u32),
 }
 
+impl Canonicalize for OppolicyIndexAttr {}
+
 impl Nla for OppolicyIndexAttr {
     fn value_len(&self) -> usize {
         use OppolicyIndexAttr::*;
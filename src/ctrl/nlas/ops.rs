@@ -1,63 +1,26 @@
 // SPDX-License-Identifier: MIT
 
-use crate::constants::*;
+use crate::{canonical::Canonicalize, constants::*};
+use alloc::format;
+use core::mem::size_of_val;
 use netlink_packet_core::{
-    emit_u32, parse_u32, DecodeError, Emitable, ErrorContext, Nla, NlaBuffer,
-    Parseable,
+    emit_u32, parse_u32, DecodeError, ErrorContext, Nla, NlaBuffer, Parseable,
 };
-use std::{mem::size_of_val, ops::Deref};
-
-pub struct OpList(Vec<Op>);
-
-impl Deref for OpList {
-    type Target = Vec<Op>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl From<&Vec<Vec<OpAttrs>>> for OpList {
-    fn from(ops: &Vec<Vec<OpAttrs>>) -> Self {
-        Self(
-            ops.iter()
-                .cloned()
-                .enumerate()
-                .map(|(index, nlas)| Op {
-                    index: index as u16,
-                    nlas,
-                })
-                .collect(),
-        )
-    }
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Op {
-    pub index: u16,
-    pub nlas: Vec<OpAttrs>,
-}
-
-impl Nla for Op {
-    fn value_len(&self) -> usize {
-        self.nlas.as_slice().buffer_len()
-    }
-
-    fn kind(&self) -> u16 {
-        self.index + 1
-    }
-
-    fn emit_value(&self, buffer: &mut [u8]) {
-        self.nlas.as_slice().emit(buffer);
-    }
-}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum OpAttrs {
     Id(u32),
     Flags(u32),
 }
 
+impl Canonicalize for OpAttrs {}
+
 impl Nla for OpAttrs {
     fn value_len(&self) -> usize {
         use OpAttrs::*;
@@ -1,20 +1,37 @@
 // SPDX-License-Identifier: MIT
 
-use crate::constants::*;
+use crate::{
+    canonical::{canonicalize_nlas, Canonicalize},
+    constants::*,
+};
+use alloc::{format, vec::Vec};
+use core::{
+    convert::TryFrom,
+    fmt,
+    mem::{size_of, size_of_val},
+};
 use netlink_packet_core::{
     emit_i64, emit_u32, emit_u64, parse_i64, parse_u32, parse_u64, DecodeError,
     Emitable, ErrorContext, Nla, NlaBuffer, NlasIterator, Parseable,
 };
-use std::{convert::TryFrom, mem::size_of_val};
 
 // PolicyAttr
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PolicyAttr {
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::array::arbitrary_nla_kind))]
     pub index: u16,
     pub attr_policy: AttributePolicyAttr,
 }
 
+impl Canonicalize for PolicyAttr {
+    fn canonicalize(&mut self) {
+        self.attr_policy.canonicalize();
+    }
+}
+
 impl Nla for PolicyAttr {
     fn value_len(&self) -> usize {
         self.attr_policy.buffer_len()
@@ -39,7 +56,8 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>> for PolicyAttr {
 
         Ok(Self {
             index: buf.kind(),
-            attr_policy: AttributePolicyAttr::parse(&NlaBuffer::new(payload))
+            attr_policy: NlaBuffer::new_checked(payload)
+                .and_then(|nla| AttributePolicyAttr::parse(&nla))
                 .context("failed to parse PolicyAttr")?,
         })
     }
@@ -48,11 +66,20 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>> for PolicyAttr {
 // AttributePolicyAttr
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AttributePolicyAttr {
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::array::arbitrary_nla_kind))]
     pub index: u16,
     pub policies: Vec<NlPolicyTypeAttrs>,
 }
 
+impl Canonicalize for AttributePolicyAttr {
+    fn canonicalize(&mut self) {
+        canonicalize_nlas(&mut self.policies);
+    }
+}
+
 impl Nla for AttributePolicyAttr {
     fn value_len(&self) -> usize {
         self.policies.as_slice().buffer_len()
@@ -91,6 +118,12 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>>
 // PolicyTypeAttrs
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum NlPolicyTypeAttrs {
     Type(NlaType),
     MinValueSigned(i64),
@@ -105,11 +138,13 @@ pub enum NlPolicyTypeAttrs {
     Mask(u64),
 }
 
+impl Canonicalize for NlPolicyTypeAttrs {}
+
 impl Nla for NlPolicyTypeAttrs {
     fn value_len(&self) -> usize {
         use NlPolicyTypeAttrs::*;
         match self {
-            Type(v) => size_of_val(v),
+            Type(_) => size_of::<u32>(),
             MinValueSigned(v) => size_of_val(v),
             MaxValueSigned(v) => size_of_val(v),
             MaxValueUnsigned(v) => size_of_val(v),
@@ -129,8 +164,8 @@ impl Nla for NlPolicyTypeAttrs {
             Type(_) => NL_POLICY_TYPE_ATTR_TYPE,
             MinValueSigned(_) => NL_POLICY_TYPE_ATTR_MIN_VALUE_S,
             MaxValueSigned(_) => NL_POLICY_TYPE_ATTR_MAX_VALUE_S,
-            MaxValueUnsigned(_) => NL_POLICY_TYPE_ATTR_MIN_VALUE_U,
-            MinValueUnsigned(_) => NL_POLICY_TYPE_ATTR_MAX_VALUE_U,
+            MaxValueUnsigned(_) => NL_POLICY_TYPE_ATTR_MAX_VALUE_U,
+            MinValueUnsigned(_) => NL_POLICY_TYPE_ATTR_MIN_VALUE_U,
             MinLength(_) => NL_POLICY_TYPE_ATTR_MIN_LENGTH,
             MaxLength(_) => NL_POLICY_TYPE_ATTR_MAX_LENGTH,
             PolicyIdx(_) => NL_POLICY_TYPE_ATTR_POLICY_IDX,
@@ -221,6 +256,12 @@ impl<'a, T: AsRef<[u8]> + ?Sized> Parseable<NlaBuffer<&'a T>>
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum NlaType {
     Flag,
     U8,
@@ -239,6 +280,28 @@ pub enum NlaType {
     Bitfield32,
 }
 
+impl fmt::Display for NlaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NlaType::Flag => "flag",
+            NlaType::U8 => "u8",
+            NlaType::U16 => "u16",
+            NlaType::U32 => "u32",
+            NlaType::U64 => "u64",
+            NlaType::S8 => "s8",
+            NlaType::S16 => "s16",
+            NlaType::S32 => "s32",
+            NlaType::S64 => "s64",
+            NlaType::Binary => "binary",
+            NlaType::String => "string",
+            NlaType::NulString => "nul-string",
+            NlaType::Nested => "nested",
+            NlaType::NestedArray => "nested-array",
+            NlaType::Bitfield32 => "bitfield32",
+        })
+    }
+}
+
 impl From<NlaType> for u32 {
     fn from(nlatype: NlaType) -> u32 {
         match nlatype {
@@ -0,0 +1,299 @@
+// SPDX-License-Identifier: MIT
+
+//! Resolved view of the policy of a family, as dumped by the controller
+
+use super::nlas::{
+    AttributePolicyAttr, GenlCtrlAttrs, NlPolicyTypeAttrs, NlaType,
+    OppolicyAttr, OppolicyIndexAttr, PolicyAttr,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{convert::TryFrom, fmt};
+use netlink_packet_core::DecodeError;
+
+/// Attribute policies of a family, as reported by the controller in the
+/// replies to `CTRL_CMD_GETPOLICY`
+///
+/// The kernel describes each command by the indexes of the policies
+/// validating its `do` and `dump` requests, and each policy by the policies
+/// of its attributes. Nested attributes refer to the policy of their own
+/// attributes by index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FamilyPolicy {
+    /// Policies of the commands, by command id
+    pub ops: BTreeMap<u8, OpPolicy>,
+    /// Policies of the attributes, by policy index and attribute type
+    pub policies: BTreeMap<u32, BTreeMap<u16, AttributePolicy>>,
+}
+
+/// Policy indexes of a command
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpPolicy {
+    /// Index of the policy of the `do` requests
+    pub do_policy: Option<u32>,
+    /// Index of the policy of the `dump` requests
+    pub dump_policy: Option<u32>,
+}
+
+/// Policy of an attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributePolicy {
+    /// Type of the attribute
+    pub nla_type: NlaType,
+    /// Minimum value of a signed integer
+    pub min_value_signed: Option<i64>,
+    /// Maximum value of a signed integer
+    pub max_value_signed: Option<i64>,
+    /// Minimum value of an unsigned integer
+    pub min_value_unsigned: Option<u64>,
+    /// Maximum value of an unsigned integer
+    pub max_value_unsigned: Option<u64>,
+    /// Minimum length of a string or binary attribute
+    pub min_length: Option<u32>,
+    /// Maximum length of a string or binary attribute
+    pub max_length: Option<u32>,
+    /// Index of the policy of the nested attributes
+    pub policy_idx: Option<u32>,
+    /// Maximum attribute type of the nested attributes
+    pub policy_max_type: Option<u32>,
+    /// Valid bits of a bitfield32 attribute
+    pub bitfield32_mask: Option<u32>,
+    /// Valid bits of an unsigned integer
+    pub mask: Option<u64>,
+}
+
+impl FamilyPolicy {
+    /// Add the policies described by a `CTRL_CMD_GETPOLICY` reply
+    pub fn add(&mut self, nlas: &[GenlCtrlAttrs]) -> Result<(), DecodeError> {
+        for nla in nlas {
+            match nla {
+                GenlCtrlAttrs::OpPolicy(attr) => {
+                    let op = self.ops.entry(attr.cmd).or_default();
+                    for idx in &attr.policy_idx {
+                        match idx {
+                            OppolicyIndexAttr::Do(v) => op.do_policy = Some(*v),
+                            OppolicyIndexAttr::Dump(v) => {
+                                op.dump_policy = Some(*v)
+                            }
+                        }
+                    }
+                }
+                GenlCtrlAttrs::Policy(attr) => {
+                    let policy = AttributePolicy::try_from(
+                        &attr.attr_policy.policies[..],
+                    )?;
+                    self.policies
+                        .entry(u32::from(attr.index))
+                        .or_default()
+                        .insert(attr.attr_policy.index, policy);
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the policy indexes of command `cmd`
+    pub fn op(&self, cmd: u8) -> Option<&OpPolicy> {
+        self.ops.get(&cmd)
+    }
+
+    /// Return the policy of attribute `kind` in policy `index`
+    pub fn attribute(&self, index: u32, kind: u16) -> Option<&AttributePolicy> {
+        self.policies.get(&index)?.get(&kind)
+    }
+}
+
+impl AttributePolicy {
+    /// Create the policy of an attribute of type `nla_type` without any
+    /// constraint
+    pub fn new(nla_type: NlaType) -> Self {
+        Self {
+            nla_type,
+            min_value_signed: None,
+            max_value_signed: None,
+            min_value_unsigned: None,
+            max_value_unsigned: None,
+            min_length: None,
+            max_length: None,
+            policy_idx: None,
+            policy_max_type: None,
+            bitfield32_mask: None,
+            mask: None,
+        }
+    }
+}
+
+impl From<&OpPolicy> for Vec<OppolicyIndexAttr> {
+    fn from(op: &OpPolicy) -> Self {
+        op.do_policy
+            .map(OppolicyIndexAttr::Do)
+            .into_iter()
+            .chain(op.dump_policy.map(OppolicyIndexAttr::Dump))
+            .collect()
+    }
+}
+
+impl From<&FamilyPolicy> for Vec<OppolicyAttr> {
+    fn from(policy: &FamilyPolicy) -> Self {
+        policy
+            .ops
+            .iter()
+            .map(|(cmd, op)| OppolicyAttr {
+                cmd: *cmd,
+                policy_idx: op.into(),
+            })
+            .collect()
+    }
+}
+
+/// The policies whose index does not fit in the 16 bits of an attribute
+/// type cannot be encoded and are skipped
+impl From<&FamilyPolicy> for Vec<PolicyAttr> {
+    fn from(policy: &FamilyPolicy) -> Self {
+        policy
+            .policies
+            .iter()
+            .filter_map(|(index, attributes)| {
+                Some((u16::try_from(*index).ok()?, attributes))
+            })
+            .flat_map(|(index, attributes)| {
+                attributes.iter().map(move |(kind, attr)| PolicyAttr {
+                    index,
+                    attr_policy: AttributePolicyAttr {
+                        index: *kind,
+                        policies: attr.into(),
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<&[NlPolicyTypeAttrs]> for AttributePolicy {
+    type Error = DecodeError;
+
+    fn try_from(nlas: &[NlPolicyTypeAttrs]) -> Result<Self, Self::Error> {
+        let nla_type = nlas
+            .iter()
+            .find_map(|nla| match nla {
+                NlPolicyTypeAttrs::Type(v) => Some(*v),
+                _ => None,
+            })
+            .ok_or("missing NL_POLICY_TYPE_ATTR_TYPE")?;
+        let mut policy = AttributePolicy::new(nla_type);
+        for nla in nlas {
+            match nla {
+                NlPolicyTypeAttrs::Type(_) => (),
+                NlPolicyTypeAttrs::MinValueSigned(v) => {
+                    policy.min_value_signed = Some(*v)
+                }
+                NlPolicyTypeAttrs::MaxValueSigned(v) => {
+                    policy.max_value_signed = Some(*v)
+                }
+                NlPolicyTypeAttrs::MinValueUnsigned(v) => {
+                    policy.min_value_unsigned = Some(*v)
+                }
+                NlPolicyTypeAttrs::MaxValueUnsigned(v) => {
+                    policy.max_value_unsigned = Some(*v)
+                }
+                NlPolicyTypeAttrs::MinLength(v) => policy.min_length = Some(*v),
+                NlPolicyTypeAttrs::MaxLength(v) => policy.max_length = Some(*v),
+                NlPolicyTypeAttrs::PolicyIdx(v) => policy.policy_idx = Some(*v),
+                NlPolicyTypeAttrs::PolicyMaxType(v) => {
+                    policy.policy_max_type = Some(*v)
+                }
+                NlPolicyTypeAttrs::Bitfield32Mask(v) => {
+                    policy.bitfield32_mask = Some(*v)
+                }
+                NlPolicyTypeAttrs::Mask(v) => policy.mask = Some(*v),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+impl From<&AttributePolicy> for Vec<NlPolicyTypeAttrs> {
+    fn from(policy: &AttributePolicy) -> Self {
+        use NlPolicyTypeAttrs::*;
+        [
+            Some(Type(policy.nla_type)),
+            policy.min_value_signed.map(MinValueSigned),
+            policy.max_value_signed.map(MaxValueSigned),
+            policy.min_value_unsigned.map(MinValueUnsigned),
+            policy.max_value_unsigned.map(MaxValueUnsigned),
+            policy.min_length.map(MinLength),
+            policy.max_length.map(MaxLength),
+            policy.policy_idx.map(PolicyIdx),
+            policy.policy_max_type.map(PolicyMaxType),
+            policy.bitfield32_mask.map(Bitfield32Mask),
+            policy.mask.map(Mask),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+impl fmt::Display for FamilyPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (cmd, op) in &self.ops {
+            write!(f, "command {cmd}:")?;
+            match (op.do_policy, op.dump_policy) {
+                (Some(index), Some(dump)) => {
+                    write!(f, " do policy {index}, dump policy {dump}")?
+                }
+                (Some(index), None) => write!(f, " do policy {index}")?,
+                (None, Some(index)) => write!(f, " dump policy {index}")?,
+                (None, None) => (),
+            }
+            writeln!(f)?;
+        }
+        for (index, attributes) in &self.policies {
+            writeln!(f, "policy {index}:")?;
+            for (kind, policy) in attributes {
+                writeln!(f, "  attribute {kind}: {policy}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AttributePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.nla_type)?;
+        match (self.min_value_signed, self.max_value_signed) {
+            (Some(min), Some(max)) => write!(f, ", range {min}..={max}")?,
+            (Some(min), None) => write!(f, ", min {min}")?,
+            (None, Some(max)) => write!(f, ", max {max}")?,
+            (None, None) => (),
+        }
+        match (self.min_value_unsigned, self.max_value_unsigned) {
+            (Some(min), Some(max)) => write!(f, ", range {min}..={max}")?,
+            (Some(min), None) => write!(f, ", min {min}")?,
+            (None, Some(max)) => write!(f, ", max {max}")?,
+            (None, None) => (),
+        }
+        if let Some(len) = self.min_length {
+            write!(f, ", min length {len}")?;
+        }
+        if let Some(len) = self.max_length {
+            write!(f, ", max length {len}")?;
+        }
+        if let Some(index) = self.policy_idx {
+            write!(f, ", policy {index}")?;
+        }
+        if let Some(max) = self.policy_max_type {
+            write!(f, ", max type {max}")?;
+        }
+        if let Some(mask) = self.bitfield32_mask {
+            write!(f, ", mask 0x{mask:08x}")?;
+        }
+        if let Some(mask) = self.mask {
+            write!(f, ", mask 0x{mask:x}")?;
+        }
+        Ok(())
+    }
+}
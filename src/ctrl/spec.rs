@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: MIT
+
+//! Skeleton of the YAML netlink specification of a family
+
+use super::{nlas::NlaType, AttributePolicy, FamilyInfo, FamilyPolicy};
+use crate::constants::{GENL_ADMIN_PERM, GENL_UNS_ADMIN_PERM};
+use alloc::vec::Vec;
+use core::{convert::TryFrom, fmt};
+
+/// Skeleton of the YAML specification of a family, following the
+/// `genetlink` protocol schema of the kernel
+/// (`Documentation/netlink/genetlink.yaml`)
+///
+/// Each policy becomes an attribute set called `policy-<index>`, each
+/// attribute `attr-<type>` and each command `op-<id>`, as the kernel does not
+/// report these names. The types and the checks of the attributes, and the
+/// attributes accepted by each command, are taken from the policy. A command
+/// whose `do` and `dump` requests follow different policies has no
+/// attribute set, which the schema allows only once per command, and names
+/// both policies in its documentation instead of listing the attributes of
+/// its requests.
+#[derive(Clone, Copy, Debug)]
+pub struct FamilySpec<'a> {
+    info: &'a FamilyInfo,
+    policy: &'a FamilyPolicy,
+}
+
+impl<'a> FamilySpec<'a> {
+    /// Describe the family `info`, whose attributes follow `policy`
+    pub fn new(info: &'a FamilyInfo, policy: &'a FamilyPolicy) -> Self {
+        Self { info, policy }
+    }
+
+    /// Write the request `kind` of a command with the attribute set `set`,
+    /// whose attributes follow the policy `index`
+    fn request(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        kind: &str,
+        index: Option<u32>,
+        set: Option<u32>,
+    ) -> fmt::Result {
+        writeln!(f, "      {kind}:")?;
+        // The attributes of a request must be named in the attribute set of
+        // the command
+        if let Some(index) = index.filter(|index| Some(*index) != set) {
+            writeln!(f, "        # The attributes follow policy-{index}")?;
+            return writeln!(f, "        request: {{}}");
+        }
+        writeln!(f, "        request:")?;
+        let attributes: Vec<u16> = index
+            .and_then(|index| self.policy.policies.get(&index))
+            .map(|attributes| attributes.keys().copied().collect())
+            .unwrap_or_default();
+        if attributes.is_empty() {
+            return writeln!(f, "          attributes: []");
+        }
+        writeln!(f, "          attributes:")?;
+        for kind in attributes {
+            writeln!(f, "            - attr-{kind}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FamilySpec<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = self.info;
+        writeln!(f, "name: {}", info.name)?;
+        writeln!(f, "protocol: genetlink")?;
+        writeln!(f, "doc: >-")?;
+        writeln!(
+            f,
+            "  Skeleton generated from the policy of version {} of the family.",
+            info.version
+        )?;
+
+        if !self.policy.policies.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "attribute-sets:")?;
+        }
+        for (index, attributes) in &self.policy.policies {
+            writeln!(f, "  -")?;
+            writeln!(f, "    name: policy-{index}")?;
+            writeln!(f, "    attributes:")?;
+            for (kind, policy) in attributes {
+                writeln!(f, "      -")?;
+                writeln!(f, "        name: attr-{kind}")?;
+                writeln!(f, "        value: {kind}")?;
+                write_attribute(f, policy)?;
+            }
+        }
+
+        if !info.ops.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "operations:")?;
+            writeln!(f, "  list:")?;
+        }
+        for op in &info.ops {
+            let policy = u8::try_from(op.id)
+                .ok()
+                .and_then(|id| self.policy.op(id))
+                .copied()
+                .unwrap_or_default();
+            writeln!(f, "    -")?;
+            writeln!(f, "      name: op-{}", op.id)?;
+            writeln!(f, "      value: {}", op.id)?;
+            // The schema has a single attribute set per command, the
+            // attributes of its requests being named in it
+            let do_policy = policy.do_policy.filter(|_| op.can_do());
+            let dump_policy = policy.dump_policy.filter(|_| op.can_dump());
+            let set = match (do_policy, dump_policy) {
+                (Some(do_index), Some(dump_index))
+                    if do_index != dump_index =>
+                {
+                    writeln!(
+                        f,
+                        "      doc: The do requests follow policy-{do_index} \
+                         and the dump requests policy-{dump_index}."
+                    )?;
+                    None
+                }
+                (Some(index), _) | (None, Some(index)) => {
+                    writeln!(f, "      attribute-set: policy-{index}")?;
+                    Some(index)
+                }
+                (None, None) => None,
+            };
+            if op.flags & GENL_ADMIN_PERM != 0 {
+                writeln!(f, "      flags: [admin-perm]")?;
+            } else if op.flags & GENL_UNS_ADMIN_PERM != 0 {
+                writeln!(f, "      flags: [uns-admin-perm]")?;
+            }
+            if op.can_do() {
+                self.request(f, "do", do_policy, set)?;
+            }
+            if op.can_dump() {
+                self.request(f, "dump", dump_policy, set)?;
+            }
+        }
+
+        if !info.mcast_groups.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "mcast-groups:")?;
+            writeln!(f, "  list:")?;
+        }
+        for grp in &info.mcast_groups {
+            writeln!(f, "    -")?;
+            writeln!(f, "      name: {}", grp.name)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_attribute(
+    f: &mut fmt::Formatter<'_>,
+    policy: &AttributePolicy,
+) -> fmt::Result {
+    let nla_type = match policy.nla_type {
+        NlaType::Flag => "flag",
+        NlaType::U8 => "u8",
+        NlaType::U16 => "u16",
+        NlaType::U32 => "u32",
+        NlaType::U64 => "u64",
+        NlaType::S8 => "s8",
+        NlaType::S16 => "s16",
+        NlaType::S32 => "s32",
+        NlaType::S64 => "s64",
+        NlaType::Binary => "binary",
+        NlaType::String | NlaType::NulString => "string",
+        NlaType::Nested => "nest",
+        NlaType::NestedArray => "indexed-array",
+        NlaType::Bitfield32 => "bitfield32",
+    };
+    writeln!(f, "        type: {nla_type}")?;
+    if policy.nla_type == NlaType::NestedArray {
+        writeln!(f, "        sub-type: nest")?;
+    }
+    if let Some(index) = policy.policy_idx {
+        writeln!(f, "        nested-attributes: policy-{index}")?;
+    }
+
+    // The kernel reports the range of the type for the integers without
+    // range check
+    let (type_min, type_max) = match policy.nla_type {
+        NlaType::U8 => (0, i128::from(u8::MAX)),
+        NlaType::U16 => (0, i128::from(u16::MAX)),
+        NlaType::U32 => (0, i128::from(u32::MAX)),
+        NlaType::U64 => (0, i128::from(u64::MAX)),
+        NlaType::S8 => (i128::from(i8::MIN), i128::from(i8::MAX)),
+        NlaType::S16 => (i128::from(i16::MIN), i128::from(i16::MAX)),
+        NlaType::S32 => (i128::from(i32::MIN), i128::from(i32::MAX)),
+        NlaType::S64 => (i128::from(i64::MIN), i128::from(i64::MAX)),
+        _ => (0, 0),
+    };
+    let min = policy
+        .min_value_signed
+        .map(i128::from)
+        .or(policy.min_value_unsigned.map(i128::from))
+        .filter(|min| *min != type_min);
+    let max = policy
+        .max_value_signed
+        .map(i128::from)
+        .or(policy.max_value_unsigned.map(i128::from))
+        .filter(|max| *max != type_max);
+    let (min_len, max_len) = (policy.min_length, policy.max_length);
+    if min.is_none() && max.is_none() && min_len.is_none() && max_len.is_none()
+    {
+        return Ok(());
+    }
+    writeln!(f, "        checks:")?;
+    if let Some(min) = min {
+        writeln!(f, "          min: {min}")?;
+    }
+    if let Some(max) = max {
+        writeln!(f, "          max: {max}")?;
+    }
+    match (min_len, max_len) {
+        (Some(min), Some(max)) if min == max => {
+            writeln!(f, "          exact-len: {min}")?
+        }
+        _ => {
+            if let Some(len) = min_len {
+                writeln!(f, "          min-len: {len}")?;
+            }
+            if let Some(len) = max_len {
+                writeln!(f, "          max-len: {len}")?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MIT
+
+//! Linux error numbers carried in netlink messages
+
+pub(crate) const ENOENT: i32 = 2;
+pub(crate) const EINVAL: i32 = 22;
+
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+pub(crate) const ENOBUFS: i32 = 132;
+#[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+pub(crate) const ENOBUFS: i32 = 55;
+#[cfg(not(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+)))]
+pub(crate) const ENOBUFS: i32 = 105;
+
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+pub(crate) const EOPNOTSUPP: i32 = 122;
+#[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+pub(crate) const EOPNOTSUPP: i32 = 45;
+#[cfg(not(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+)))]
+pub(crate) const EOPNOTSUPP: i32 = 95;
+
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+pub(crate) const ENODATA: i32 = 96;
+#[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+pub(crate) const ENODATA: i32 = 111;
+#[cfg(not(any(
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc",
+    target_arch = "sparc64"
+)))]
+pub(crate) const ENODATA: i32 = 61;
@@ -0,0 +1,564 @@
+// SPDX-License-Identifier: MIT
+
+//! Generation of requests following the policy of a family
+//!
+//! [`RequestGenerator`] builds the requests of a family from its information
+//! and its policy, as dumped from the kernel, to test the peers and the mock
+//! servers of the family with realistic inputs. The requests are either
+//! valid, or break exactly one rule of the policy: a value out of its range
+//! or mask, a length out of its limits, a missing `NLA_F_NESTED` flag, an
+//! attribute which is not in the policy, ...
+//!
+//! The attributes not in the policy are reported as invalid, as the kernel
+//! rejects them when validating strictly, which is the default for the
+//! recent families.
+//!
+//! [`boundary_requests()`](RequestGenerator::boundary_requests) returns a
+//! fixed set of requests at the limits of the policy, e.g. to seed a fuzzing
+//! corpus. With the `arbitrary` feature,
+//! [`arbitrary_request()`](RequestGenerator::arbitrary_request) generates a
+//! random request from unstructured bytes, which can back a proptest strategy
+//! or a fuzz target:
+//!
+//! ```ignore
+//! let strategy = vec(any::<u8>(), 0..1024).prop_map(move |data| {
+//!     let generator = RequestGenerator::new(&snapshot);
+//!     generator.arbitrary_request(&mut Unstructured::new(&data), false)
+//! });
+//! ```
+
+use crate::{
+    ctrl::{nlas::NlaType, AttributePolicy, FamilySnapshot, RequestKind},
+    message::GenlMessage,
+    raw::GenlRaw,
+};
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+use netlink_packet_core::{
+    NetlinkMessage, NLA_F_NESTED, NLA_TYPE_MASK, NLM_F_DUMP, NLM_F_REQUEST,
+};
+
+/// Largest payload generated for an attribute, leaving room for the headers
+/// of the nests in the `u16` length of an attribute
+const MAX_PAYLOAD: usize = 0x8000;
+
+/// Longest string or binary payload generated randomly when the policy does
+/// not limit it
+#[cfg(feature = "arbitrary")]
+const DEFAULT_MAX_LENGTH: usize = 64;
+
+/// Generator of the requests of a family, following its policy
+#[derive(Clone, Copy, Debug)]
+pub struct RequestGenerator<'a> {
+    snapshot: &'a FamilySnapshot,
+    max_depth: usize,
+}
+
+/// Request built by a [`RequestGenerator`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedRequest {
+    /// Kind of the request, which is sent with `NLM_F_DUMP` for dumps
+    pub request: RequestKind,
+    /// The request
+    pub message: GenlMessage<GenlRaw>,
+    /// Whether the request follows the policy of the family
+    pub valid: bool,
+}
+
+impl<'a> RequestGenerator<'a> {
+    /// Create a generator of the requests of the family described by
+    /// `snapshot`
+    pub fn new(snapshot: &'a FamilySnapshot) -> Self {
+        Self {
+            snapshot,
+            max_depth: 4,
+        }
+    }
+
+    /// Set the maximum nesting depth of the attributes, 4 by default
+    pub fn max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+
+    /// Return the requests accepted by the family, with the index of their
+    /// policy
+    fn requests(&self) -> Vec<(u8, RequestKind, Option<u32>)> {
+        let mut requests = Vec::new();
+        for op in &self.snapshot.info.ops {
+            let Ok(cmd) = u8::try_from(op.id) else {
+                continue;
+            };
+            let policy = self.snapshot.policy.op(cmd);
+            if op.can_do() {
+                let index = policy.and_then(|policy| policy.do_policy);
+                requests.push((cmd, RequestKind::Do, index));
+            }
+            if op.can_dump() {
+                let index = policy.and_then(|policy| policy.dump_policy);
+                requests.push((cmd, RequestKind::Dump, index));
+            }
+        }
+        requests
+    }
+
+    fn request(
+        &self,
+        cmd: u8,
+        request: RequestKind,
+        nlas: &[u8],
+        valid: bool,
+    ) -> GeneratedRequest {
+        let info = &self.snapshot.info;
+        let mut payload = vec![0; info.hdrsize as usize];
+        payload.extend_from_slice(nlas);
+        let mut message = GenlMessage::from_payload(GenlRaw {
+            cmd,
+            // The controller reports the version as a `u32`, but the generic
+            // netlink header only has a byte for it
+            version: u8::try_from(info.version).unwrap_or(u8::MAX),
+            payload,
+        });
+        message.set_resolved_family_id(info.id);
+        GeneratedRequest {
+            request,
+            message,
+            valid,
+        }
+    }
+
+    /// Return the requests at the limits of the policy
+    ///
+    /// For each request of each command, this is the request without
+    /// attributes, and for each attribute of its policy, followed through
+    /// the nested policies, the requests holding only this attribute with
+    /// the values at the limits of the policy, and just beyond them.
+    pub fn boundary_requests(&self) -> Vec<GeneratedRequest> {
+        let mut requests = Vec::new();
+        let maxattr = Some(self.snapshot.info.maxattr);
+        for (cmd, request, index) in self.requests() {
+            requests.push(self.request(cmd, request, &[], true));
+            for (nla, valid) in self.edges(index, maxattr, 0) {
+                requests.push(self.request(cmd, request, &nla, valid));
+            }
+        }
+        requests
+    }
+
+    /// Return the attributes at the limits of policy `index`, whose largest
+    /// attribute type is `max_type`
+    fn edges(
+        &self,
+        index: Option<u32>,
+        max_type: Option<u32>,
+        depth: usize,
+    ) -> Vec<(Vec<u8>, bool)> {
+        let Some(attributes) =
+            index.and_then(|index| self.snapshot.policy.policies.get(&index))
+        else {
+            return Vec::new();
+        };
+        let mut edges = Vec::new();
+        for (kind, policy) in attributes {
+            let kind = *kind;
+            let nested = kind | NLA_F_NESTED;
+            match policy.nla_type {
+                NlaType::Nested | NlaType::NestedArray => {
+                    edges.push((nla(nested, &[]), true));
+                    edges.push((nla(kind, &[]), false));
+                    // Truncated attribute header
+                    edges.push((nla(nested, &[0; 3]), false));
+                    if depth + 1 >= self.max_depth {
+                        continue;
+                    }
+                    let array = policy.nla_type == NlaType::NestedArray;
+                    for (inner, valid) in self.edges(
+                        policy.policy_idx,
+                        policy.policy_max_type,
+                        depth + 1,
+                    ) {
+                        let inner = if array {
+                            nla(1 | NLA_F_NESTED, &inner)
+                        } else {
+                            inner
+                        };
+                        edges.push((nla(nested, &inner), valid));
+                    }
+                }
+                _ => edges.extend(
+                    leaf_edges(policy)
+                        .into_iter()
+                        .map(|(value, valid)| (nla(kind, &value), valid)),
+                ),
+            }
+        }
+        if let Some(kind) = unknown_kind(max_type, attributes.keys().copied()) {
+            edges.push((nla(kind, &[]), false));
+        }
+        edges
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl RequestGenerator<'_> {
+    /// Generate a random request of the family from `u`, which is valid
+    /// unless `violate` is set
+    ///
+    /// The valid requests hold a random subset of the attributes of their
+    /// policy. The invalid requests also break exactly one rule of the
+    /// policy.
+    pub fn arbitrary_request(
+        &self,
+        u: &mut arbitrary::Unstructured,
+        violate: bool,
+    ) -> arbitrary::Result<GeneratedRequest> {
+        // The kernel does not validate the requests without policy
+        let requests: Vec<_> = self
+            .requests()
+            .into_iter()
+            .filter(|(_, _, index)| !violate || index.is_some())
+            .collect();
+        let (cmd, request, index) = *u.choose(&requests)?;
+        let maxattr = Some(self.snapshot.info.maxattr);
+        let nlas = self.arbitrary_nlas(u, index, maxattr, 0, violate)?;
+        Ok(self.request(cmd, request, &nlas, !violate))
+    }
+
+    fn arbitrary_nlas(
+        &self,
+        u: &mut arbitrary::Unstructured,
+        index: Option<u32>,
+        max_type: Option<u32>,
+        depth: usize,
+        violate: bool,
+    ) -> arbitrary::Result<Vec<u8>> {
+        let attributes: Vec<(u16, &AttributePolicy)> = index
+            .and_then(|index| self.snapshot.policy.policies.get(&index))
+            .map(|attributes| {
+                attributes.iter().map(|(kind, p)| (*kind, p)).collect()
+            })
+            .unwrap_or_default();
+        // The attribute breaking the policy, or the attribute which is not
+        // in the policy if it is `attributes.len()`
+        let violating = if violate {
+            let candidates: Vec<usize> = attributes
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, policy))| can_violate(policy))
+                .map(|(i, _)| i)
+                .chain(Some(attributes.len()))
+                .collect();
+            Some(*u.choose(&candidates)?)
+        } else {
+            None
+        };
+        let mut buf = Vec::new();
+        for (i, (kind, policy)) in attributes.iter().enumerate() {
+            let violate = violating == Some(i);
+            if violate || u.arbitrary()? {
+                let nla =
+                    self.arbitrary_attribute(u, *kind, policy, depth, violate)?;
+                buf.extend_from_slice(&nla);
+            }
+        }
+        if violating == Some(attributes.len()) {
+            // The kind is valid as long as the policy is not full
+            let kinds = attributes.iter().map(|(kind, _)| *kind);
+            let kind = unknown_kind(max_type, kinds).unwrap_or(0);
+            buf.extend_from_slice(&nla(kind, &[]));
+        }
+        Ok(buf)
+    }
+
+    fn arbitrary_attribute(
+        &self,
+        u: &mut arbitrary::Unstructured,
+        kind: u16,
+        policy: &AttributePolicy,
+        depth: usize,
+        violate: bool,
+    ) -> arbitrary::Result<Vec<u8>> {
+        let nested = kind | NLA_F_NESTED;
+        let deeper = depth + 1 < self.max_depth;
+        match policy.nla_type {
+            NlaType::Nested | NlaType::NestedArray => {
+                let index = policy.policy_idx;
+                if violate && (!deeper || index.is_none() || u.arbitrary()?) {
+                    return Ok(if u.arbitrary()? {
+                        nla(kind, &[])
+                    } else {
+                        nla(nested, &[0; 3])
+                    });
+                }
+                if !deeper {
+                    return Ok(nla(nested, &[]));
+                }
+                let max_type = policy.policy_max_type;
+                if policy.nla_type == NlaType::Nested {
+                    let inner = self.arbitrary_nlas(
+                        u,
+                        index,
+                        max_type,
+                        depth + 1,
+                        violate,
+                    )?;
+                    return Ok(nla(nested, &inner));
+                }
+                let count = u.int_in_range(usize::from(violate)..=3)?;
+                let mut elements = Vec::new();
+                for i in 0..count {
+                    let violate = violate && i == 0;
+                    let inner = self.arbitrary_nlas(
+                        u,
+                        index,
+                        max_type,
+                        depth + 1,
+                        violate,
+                    )?;
+                    elements.extend_from_slice(&nla(
+                        (i as u16 + 1) | NLA_F_NESTED,
+                        &inner,
+                    ));
+                }
+                Ok(nla(nested, &elements))
+            }
+            _ if violate => {
+                let invalid: Vec<Vec<u8>> = leaf_edges(policy)
+                    .into_iter()
+                    .filter(|(_, valid)| !valid)
+                    .map(|(value, _)| value)
+                    .collect();
+                Ok(nla(kind, u.choose(&invalid)?))
+            }
+            _ => Ok(nla(kind, &arbitrary_value(u, policy)?)),
+        }
+    }
+}
+
+/// Return a random value of an attribute following its policy
+#[cfg(feature = "arbitrary")]
+fn arbitrary_value(
+    u: &mut arbitrary::Unstructured,
+    policy: &AttributePolicy,
+) -> arbitrary::Result<Vec<u8>> {
+    let length = |u: &mut arbitrary::Unstructured| {
+        let min = policy.min_length.unwrap_or(0) as usize;
+        let max = policy
+            .max_length
+            .map_or(DEFAULT_MAX_LENGTH, |max| max as usize)
+            .min(min.max(DEFAULT_MAX_LENGTH));
+        u.int_in_range(min..=max.max(min))
+    };
+    let string = |u: &mut arbitrary::Unstructured, len| {
+        (0..len)
+            .map(|_| Ok(b'a' + u.int_in_range(0..=25)?))
+            .collect::<arbitrary::Result<Vec<u8>>>()
+    };
+    Ok(match policy.nla_type {
+        NlaType::String => {
+            let len = length(u)?;
+            string(u, len)?
+        }
+        NlaType::NulString => {
+            // The limit does not count the trailing nul
+            let len = length(u)?;
+            let mut value = string(u, len)?;
+            value.push(0);
+            value
+        }
+        NlaType::Binary => {
+            let len = length(u)?;
+            u.bytes(len)?.to_vec()
+        }
+        NlaType::Bitfield32 => {
+            let mask = policy.bitfield32_mask.unwrap_or(u32::MAX);
+            let value = u.arbitrary::<u32>()? & mask;
+            let selector = u.arbitrary::<u32>()? & mask;
+            [value.to_ne_bytes(), selector.to_ne_bytes()].concat()
+        }
+        NlaType::Flag => Vec::new(),
+        nla_type => {
+            let (min, max) = value_range(policy);
+            let mut value = u.int_in_range(min..=max)?;
+            if let Some(mask) = policy.mask {
+                value &= i128::from(mask);
+            }
+            encode(nla_type, value)
+        }
+    })
+}
+
+/// Return whether an attribute can break its policy
+#[cfg(feature = "arbitrary")]
+fn can_violate(policy: &AttributePolicy) -> bool {
+    leaf_edges(policy).iter().any(|(_, valid)| !valid)
+        || matches!(policy.nla_type, NlaType::Nested | NlaType::NestedArray)
+}
+
+/// Return the values at the limits of the policy of an attribute which is
+/// not nested, and whether they are valid
+fn leaf_edges(policy: &AttributePolicy) -> Vec<(Vec<u8>, bool)> {
+    let mut edges = Vec::new();
+    let min_length = policy.min_length.unwrap_or(0) as usize;
+    let max_length = policy.max_length.map(|max| max as usize);
+    match policy.nla_type {
+        NlaType::Flag => {
+            edges.push((vec![], true));
+            edges.push((vec![0], false));
+        }
+        NlaType::String | NlaType::NulString => {
+            let nul = policy.nla_type == NlaType::NulString;
+            let string = |len: usize| {
+                let mut value = vec![b'a'; len];
+                if nul {
+                    value.push(0);
+                }
+                value
+            };
+            edges.push((string(min_length), true));
+            if min_length > 0 {
+                edges.push((string(min_length - 1), false));
+            }
+            if let Some(max) = max_length {
+                edges.push((string(max), true));
+                edges.push((string(max + 1), false));
+            }
+            if nul {
+                edges.push((vec![b'a'], false));
+            }
+        }
+        NlaType::Binary => {
+            edges.push((vec![0; min_length], true));
+            if min_length > 0 {
+                edges.push((vec![0; min_length - 1], false));
+            }
+            if let Some(max) = max_length {
+                edges.push((vec![0; max], true));
+                edges.push((vec![0; max + 1], false));
+            }
+        }
+        NlaType::Bitfield32 => {
+            let mask = policy.bitfield32_mask.unwrap_or(u32::MAX);
+            let value = [mask.to_ne_bytes(), mask.to_ne_bytes()].concat();
+            edges.push((value, true));
+            if mask != u32::MAX {
+                let value = (!mask).to_ne_bytes();
+                edges.push(([value, value].concat(), false));
+            }
+            edges.push((vec![0; 4], false));
+        }
+        NlaType::Nested | NlaType::NestedArray => (),
+        nla_type => {
+            let (type_min, type_max) = type_range(nla_type);
+            let (min, max) = value_range(policy);
+            edges.push((encode(nla_type, min), true));
+            if max != min {
+                edges.push((encode(nla_type, max), true));
+            }
+            if min > type_min {
+                edges.push((encode(nla_type, min - 1), false));
+            }
+            if max < type_max {
+                edges.push((encode(nla_type, max + 1), false));
+            }
+            if let Some(mask) = policy.mask {
+                let outside = !i128::from(mask) & type_max;
+                if outside != 0 {
+                    edges.push((encode(nla_type, outside), false));
+                }
+            }
+            // Too short for the type
+            let width = encode(nla_type, 0).len();
+            edges.push((vec![0; width - 1], false));
+        }
+    }
+    edges.retain(|(value, _)| value.len() <= MAX_PAYLOAD);
+    edges
+}
+
+/// Return the range of the values of an integer type
+fn type_range(nla_type: NlaType) -> (i128, i128) {
+    match nla_type {
+        NlaType::U8 => (0, i128::from(u8::MAX)),
+        NlaType::U16 => (0, i128::from(u16::MAX)),
+        NlaType::U32 => (0, i128::from(u32::MAX)),
+        NlaType::U64 => (0, i128::from(u64::MAX)),
+        NlaType::S8 => (i128::from(i8::MIN), i128::from(i8::MAX)),
+        NlaType::S16 => (i128::from(i16::MIN), i128::from(i16::MAX)),
+        NlaType::S32 => (i128::from(i32::MIN), i128::from(i32::MAX)),
+        NlaType::S64 => (i128::from(i64::MIN), i128::from(i64::MAX)),
+        _ => (0, 0),
+    }
+}
+
+/// Return the range of the valid values of an integer attribute
+fn value_range(policy: &AttributePolicy) -> (i128, i128) {
+    let (type_min, type_max) = type_range(policy.nla_type);
+    let min = policy
+        .min_value_signed
+        .map(i128::from)
+        .or(policy.min_value_unsigned.map(i128::from))
+        .unwrap_or(type_min)
+        .max(type_min);
+    let max = policy
+        .max_value_signed
+        .map(i128::from)
+        .or(policy.max_value_unsigned.map(i128::from))
+        .unwrap_or(type_max)
+        .min(type_max);
+    (min, max.max(min))
+}
+
+/// Encode `value` as an integer of type `nla_type`, in host byte order
+fn encode(nla_type: NlaType, value: i128) -> Vec<u8> {
+    match nla_type {
+        NlaType::U8 => (value as u8).to_ne_bytes().to_vec(),
+        NlaType::U16 => (value as u16).to_ne_bytes().to_vec(),
+        NlaType::U32 => (value as u32).to_ne_bytes().to_vec(),
+        NlaType::U64 => (value as u64).to_ne_bytes().to_vec(),
+        NlaType::S8 => (value as i8).to_ne_bytes().to_vec(),
+        NlaType::S16 => (value as i16).to_ne_bytes().to_vec(),
+        NlaType::S32 => (value as i32).to_ne_bytes().to_vec(),
+        NlaType::S64 => (value as i64).to_ne_bytes().to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+/// Return the attribute type following the largest type `max_type` of a
+/// policy, if any is left
+///
+/// The kernel accepts the types up to `max_type` which are missing from the
+/// dumped policy, so the largest type of the policy is only used when
+/// `max_type` is not known.
+fn unknown_kind(
+    max_type: Option<u32>,
+    kinds: impl Iterator<Item = u16>,
+) -> Option<u16> {
+    let max = kinds.map(u32::from).chain(max_type).max().unwrap_or(0);
+    let kind = u16::try_from(max.checked_add(1)?).ok()?;
+    (kind <= NLA_TYPE_MASK).then_some(kind)
+}
+
+/// Build an attribute, padded to 4 bytes
+fn nla(kind: u16, value: &[u8]) -> Vec<u8> {
+    let len = 4 + value.len();
+    let mut buf = Vec::with_capacity((len + 3) & !3);
+    buf.extend_from_slice(&(len as u16).to_ne_bytes());
+    buf.extend_from_slice(&kind.to_ne_bytes());
+    buf.extend_from_slice(value);
+    buf.resize((len + 3) & !3, 0);
+    buf
+}
+
+impl GeneratedRequest {
+    /// Return the netlink message carrying the request, with `NLM_F_DUMP`
+    /// set for dumps
+    pub fn to_netlink(&self) -> NetlinkMessage<GenlMessage<GenlRaw>> {
+        let mut message = NetlinkMessage::from(self.message.clone());
+        message.header.flags = match self.request {
+            RequestKind::Do => NLM_F_REQUEST,
+            RequestKind::Dump => NLM_F_REQUEST | NLM_F_DUMP,
+        };
+        message.finalize();
+        message
+    }
+}
@@ -0,0 +1,521 @@
+// SPDX-License-Identifier: MIT
+
+//! Annotated hex dump of netlink messages
+//!
+//! [`HexDump`] formats a buffer holding one or more netlink messages, or a
+//! sequence of attributes, with one line per field: the fields of `nlmsghdr`
+//! and of the generic netlink header, the length, type and flags of each
+//! attribute, indented by nesting depth, and the alignment padding. When the
+//! family implements [`Annotate`], commands and attributes are named and the
+//! attribute values decoded:
+//!
+//! ```text
+//! 0000  20 00 00 00                                       nlmsghdr length 32
+//! 0004  10 00                                               type 16 (GENL_ID_CTRL)
+//! 0006  01 03                                               flags 0x0301
+//! 0008  01 00 00 00                                         sequence 1
+//! 000c  00 00 00 00                                         port 0
+//! 0010  03                                                genlmsghdr cmd 3 (CTRL_CMD_GETFAMILY)
+//! 0011  02                                                  version 2
+//! 0012  00 00                                               reserved
+//! 0014  0b 00 02 00                                       nla length 11 type 2 (CTRL_ATTR_FAMILY_NAME)
+//! 0018  6e 6c 63 74 72 6c 00                                "nlctrl"
+//! 001f  00                                                padding
+//! ```
+
+use crate::{message::GenlMessage, traits::GenlFamily};
+use alloc::{format, string::String, vec, vec::Vec};
+use core::{
+    fmt::{self, Debug, Display, Formatter},
+    marker::PhantomData,
+};
+use netlink_packet_core::{
+    Emitable, NetlinkBuffer, NetlinkMessage, NLA_F_NESTED, NLA_F_NET_BYTEORDER,
+    NLA_TYPE_MASK, NLMSG_ALIGNTO, NLMSG_DONE, NLMSG_ERROR, NLMSG_NOOP,
+    NLMSG_OVERRUN,
+};
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Maximum nesting depth of the dumped attributes, deeper attributes being
+/// dumped as values
+const MAX_DEPTH: usize = crate::visit::MAX_NESTING_DEPTH;
+
+/// Layout of the value of an attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NlaFormat {
+    /// Unsigned 8 bits integer
+    U8,
+    /// Unsigned 16 bits integer
+    U16,
+    /// Unsigned 32 bits integer
+    U32,
+    /// Unsigned 64 bits integer
+    U64,
+    /// Signed 64 bits integer
+    S64,
+    /// String, possibly NUL terminated
+    String,
+    /// Opaque bytes
+    Binary,
+    /// Nested attributes
+    Nested,
+}
+
+/// Name and layout of an attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NlaSpec {
+    /// Name of the attribute type
+    pub name: &'static str,
+    /// Layout of the value
+    pub format: NlaFormat,
+}
+
+impl NlaSpec {
+    /// Create the specification of attribute `name`
+    pub const fn new(name: &'static str, format: NlaFormat) -> Self {
+        Self { name, format }
+    }
+}
+
+/// Knowledge of a family used to annotate its messages
+///
+/// The default implementations know nothing about the family: attributes
+/// are only descended into when they have the `NLA_F_NESTED` flag set.
+pub trait Annotate {
+    /// Return the name of command `cmd`
+    fn command_name(_cmd: u8) -> Option<&'static str> {
+        None
+    }
+
+    /// Return the specification of the attribute of type `kind`, nested in
+    /// the attributes of types `path`
+    fn attribute(_path: &[u16], _kind: u16) -> Option<NlaSpec> {
+        None
+    }
+}
+
+/// Annotated hex dump of a buffer, formatted with [`Display`]
+///
+/// The attributes are annotated with the knowledge of family `F`, which may
+/// be [`GenlRaw`](crate::GenlRaw) to annotate nothing but the layout.
+pub struct HexDump<'a, F> {
+    buf: &'a [u8],
+    attributes: bool,
+    header_size: usize,
+    family: PhantomData<F>,
+}
+
+impl<'a, F> HexDump<'a, F> {
+    /// Dump the netlink messages of `buf`, back to back
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            attributes: false,
+            header_size: 0,
+            family: PhantomData,
+        }
+    }
+
+    /// Dump the sequence of attributes of `buf`
+    pub fn attributes(buf: &'a [u8]) -> Self {
+        Self {
+            attributes: true,
+            ..Self::new(buf)
+        }
+    }
+
+    /// Set the size of the family header following the generic netlink
+    /// header
+    pub fn header_size(self, header_size: usize) -> Self {
+        Self {
+            header_size,
+            ..self
+        }
+    }
+}
+
+impl<F: Annotate> Display for HexDump<'_, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut path = Vec::new();
+        if self.attributes {
+            return dump_nlas::<F>(f, 0, self.buf, 0, &mut path);
+        }
+        let mut offset = 0;
+        while offset < self.buf.len() {
+            let rest = &self.buf[offset..];
+            let Ok(packet) = NetlinkBuffer::new_checked(rest) else {
+                return line(
+                    f,
+                    offset,
+                    rest,
+                    0,
+                    format_args!("truncated message"),
+                );
+            };
+            let length = packet.length() as usize;
+            self.dump_message(f, offset, &rest[..length], &mut path)?;
+            let end = align(length).min(rest.len());
+            dump_padding(f, offset + length, &rest[length..end], 0)?;
+            offset += end;
+        }
+        Ok(())
+    }
+}
+
+impl<F: Annotate> HexDump<'_, F> {
+    fn dump_message(
+        &self,
+        f: &mut Formatter<'_>,
+        offset: usize,
+        buf: &[u8],
+        path: &mut Vec<u16>,
+    ) -> fmt::Result {
+        let packet = NetlinkBuffer::new(buf);
+        let message_type = packet.message_type();
+        line(
+            f,
+            offset,
+            &buf[..4],
+            0,
+            format_args!("nlmsghdr length {}", buf.len()),
+        )?;
+        match message_type_name(message_type) {
+            Some(name) => line(
+                f,
+                offset + 4,
+                &buf[4..6],
+                1,
+                format_args!("type {message_type} ({name})"),
+            )?,
+            None => line(
+                f,
+                offset + 4,
+                &buf[4..6],
+                1,
+                format_args!("type {message_type}"),
+            )?,
+        }
+        line(
+            f,
+            offset + 6,
+            &buf[6..8],
+            1,
+            format_args!("flags 0x{:04x}", packet.flags()),
+        )?;
+        line(
+            f,
+            offset + 8,
+            &buf[8..12],
+            1,
+            format_args!("sequence {}", packet.sequence_number()),
+        )?;
+        line(
+            f,
+            offset + 12,
+            &buf[12..16],
+            1,
+            format_args!("port {}", packet.port_number()),
+        )?;
+
+        let offset = offset + 16;
+        let payload = &buf[16..];
+        match message_type {
+            NLMSG_ERROR if payload.len() >= 4 => {
+                let code = i32::from_ne_bytes(payload[..4].try_into().unwrap());
+                line(
+                    f,
+                    offset,
+                    &payload[..4],
+                    0,
+                    format_args!("error {code}"),
+                )?;
+                line(f, offset + 4, &payload[4..], 0, format_args!("request"))
+            }
+            NLMSG_DONE if payload.len() >= 4 => {
+                let status =
+                    i32::from_ne_bytes(payload[..4].try_into().unwrap());
+                line(
+                    f,
+                    offset,
+                    &payload[..4],
+                    0,
+                    format_args!("status {status}"),
+                )?;
+                line(
+                    f,
+                    offset + 4,
+                    &payload[4..],
+                    0,
+                    format_args!("trailing bytes"),
+                )
+            }
+            NLMSG_ERROR | NLMSG_DONE | NLMSG_NOOP | NLMSG_OVERRUN => {
+                line(f, offset, payload, 0, format_args!("payload"))
+            }
+            _ => self.dump_genl(f, offset, payload, path),
+        }
+    }
+
+    fn dump_genl(
+        &self,
+        f: &mut Formatter<'_>,
+        offset: usize,
+        buf: &[u8],
+        path: &mut Vec<u16>,
+    ) -> fmt::Result {
+        if buf.len() < 4 {
+            return line(
+                f,
+                offset,
+                buf,
+                0,
+                format_args!("truncated genlmsghdr"),
+            );
+        }
+        let cmd = buf[0];
+        match F::command_name(cmd) {
+            Some(name) => line(
+                f,
+                offset,
+                &buf[..1],
+                0,
+                format_args!("genlmsghdr cmd {cmd} ({name})"),
+            )?,
+            None => line(
+                f,
+                offset,
+                &buf[..1],
+                0,
+                format_args!("genlmsghdr cmd {cmd}"),
+            )?,
+        }
+        line(
+            f,
+            offset + 1,
+            &buf[1..2],
+            1,
+            format_args!("version {}", buf[1]),
+        )?;
+        line(f, offset + 2, &buf[2..4], 1, format_args!("reserved"))?;
+
+        let (offset, buf) = (offset + 4, &buf[4..]);
+        if buf.len() < self.header_size {
+            return line(
+                f,
+                offset,
+                buf,
+                0,
+                format_args!("truncated family header"),
+            );
+        }
+        line(
+            f,
+            offset,
+            &buf[..self.header_size],
+            0,
+            format_args!("family header"),
+        )?;
+        dump_nlas::<F>(
+            f,
+            offset + self.header_size,
+            &buf[self.header_size..],
+            0,
+            path,
+        )
+    }
+}
+
+/// Format the attributes of `buf`, found at `offset`
+fn dump_nlas<F: Annotate>(
+    f: &mut Formatter<'_>,
+    offset: usize,
+    buf: &[u8],
+    depth: usize,
+    path: &mut Vec<u16>,
+) -> fmt::Result {
+    let mut pos = 0;
+    while pos < buf.len() {
+        let (offset, rest) = (offset + pos, &buf[pos..]);
+        if rest.len() < 4 {
+            return line(
+                f,
+                offset,
+                rest,
+                depth,
+                format_args!("truncated attribute"),
+            );
+        }
+        let length = usize::from(u16::from_ne_bytes([rest[0], rest[1]]));
+        if length < 4 || length > rest.len() {
+            return line(
+                f,
+                offset,
+                rest,
+                depth,
+                format_args!("malformed attribute of length {length}"),
+            );
+        }
+        let nla_type = u16::from_ne_bytes([rest[2], rest[3]]);
+        let kind = nla_type & NLA_TYPE_MASK;
+        let flagged = nla_type & NLA_F_NESTED != 0;
+        let net_byteorder = nla_type & NLA_F_NET_BYTEORDER != 0;
+        let spec = F::attribute(path, kind);
+        line(
+            f,
+            offset,
+            &rest[..4],
+            depth,
+            format_args!(
+                "nla length {length} type {kind}{}{}{}",
+                Name(spec),
+                if flagged { " nested" } else { "" },
+                if net_byteorder { " net-byteorder" } else { "" },
+            ),
+        )?;
+
+        let value = &rest[4..length];
+        let format = spec.map(|spec| spec.format);
+        let nested =
+            format.map_or(flagged, |format| format == NlaFormat::Nested);
+        if nested && depth < MAX_DEPTH {
+            path.push(kind);
+            dump_nlas::<F>(f, offset + 4, value, depth + 1, path)?;
+            path.pop();
+        } else {
+            let value_fmt = Value {
+                format,
+                net_byteorder,
+                value,
+            };
+            line(f, offset + 4, value, depth + 1, format_args!("{value_fmt}"))?;
+        }
+
+        let end = align(length).min(rest.len());
+        dump_padding(f, offset + length, &rest[length..end], depth)?;
+        pos += end;
+    }
+    Ok(())
+}
+
+fn dump_padding(
+    f: &mut Formatter<'_>,
+    offset: usize,
+    padding: &[u8],
+    depth: usize,
+) -> fmt::Result {
+    line(f, offset, padding, depth, format_args!("padding"))
+}
+
+/// Format `bytes`, found at `offset`, with their annotation indented by
+/// `depth` levels
+fn line(
+    f: &mut Formatter<'_>,
+    offset: usize,
+    bytes: &[u8],
+    depth: usize,
+    note: fmt::Arguments<'_>,
+) -> fmt::Result {
+    for (i, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        write!(f, "{:04x} ", offset + i * BYTES_PER_LINE)?;
+        for byte in chunk {
+            write!(f, " {byte:02x}")?;
+        }
+        if i == 0 {
+            let pad = (BYTES_PER_LINE - chunk.len()) * 3 + 2 * depth + 3;
+            write!(f, "{:pad$}{note}", "")?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+fn align(len: usize) -> usize {
+    let align = NLMSG_ALIGNTO as usize;
+    (len + align - 1) & !(align - 1)
+}
+
+fn message_type_name(message_type: u16) -> Option<&'static str> {
+    match message_type {
+        NLMSG_NOOP => Some("NLMSG_NOOP"),
+        NLMSG_ERROR => Some("NLMSG_ERROR"),
+        NLMSG_DONE => Some("NLMSG_DONE"),
+        NLMSG_OVERRUN => Some("NLMSG_OVERRUN"),
+        crate::constants::GENL_ID_CTRL => Some("GENL_ID_CTRL"),
+        _ => None,
+    }
+}
+
+/// Name of an attribute type, if known
+struct Name(Option<NlaSpec>);
+
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(spec) => write!(f, " ({})", spec.name),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Value of an attribute, decoded according to its format
+struct Value<'a> {
+    format: Option<NlaFormat>,
+    net_byteorder: bool,
+    value: &'a [u8],
+}
+
+impl Value<'_> {
+    /// Return the value in native byte order
+    fn bytes<const N: usize>(&self) -> Option<[u8; N]> {
+        let mut bytes: [u8; N] = self.value.try_into().ok()?;
+        if self.net_byteorder && cfg!(target_endian = "little") {
+            bytes.reverse();
+        }
+        Some(bytes)
+    }
+}
+
+impl Display for Value<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.format {
+            Some(NlaFormat::U8) => match self.bytes() {
+                Some(bytes) => write!(f, "{}", u8::from_ne_bytes(bytes)),
+                None => f.write_str("malformed u8"),
+            },
+            Some(NlaFormat::U16) => match self.bytes() {
+                Some(bytes) => write!(f, "{}", u16::from_ne_bytes(bytes)),
+                None => f.write_str("malformed u16"),
+            },
+            Some(NlaFormat::U32) => match self.bytes() {
+                Some(bytes) => write!(f, "{}", u32::from_ne_bytes(bytes)),
+                None => f.write_str("malformed u32"),
+            },
+            Some(NlaFormat::U64) => match self.bytes() {
+                Some(bytes) => write!(f, "{}", u64::from_ne_bytes(bytes)),
+                None => f.write_str("malformed u64"),
+            },
+            Some(NlaFormat::S64) => match self.bytes() {
+                Some(bytes) => write!(f, "{}", i64::from_ne_bytes(bytes)),
+                None => f.write_str("malformed s64"),
+            },
+            Some(NlaFormat::String) => {
+                let value = self.value.strip_suffix(&[0]).unwrap_or(self.value);
+                write!(f, "{:?}", String::from_utf8_lossy(value))
+            }
+            Some(NlaFormat::Binary | NlaFormat::Nested) | None => {
+                f.write_str("value")
+            }
+        }
+    }
+}
+
+/// Return the annotated hex dump of `message`
+pub fn dump_message<F>(message: &NetlinkMessage<GenlMessage<F>>) -> String
+where
+    F: Annotate + GenlFamily + Emitable + Debug,
+{
+    let mut buf = vec![0; message.buffer_len()];
+    message.serialize(&mut buf);
+    format!("{}", HexDump::<F>::new(&buf))
+}
@@ -66,22 +66,101 @@
 //!
 //! If you are implementing such a generic family, note that you should define
 //! the header data structure in your payload type and handle the serialization.
+//!
+//! ## `std` feature
+//! Without the default `std` feature, only the packet types (header, buffer,
+//! message, raw payload and the controller model) are built, and the code of
+//! this crate only uses `core` and `alloc`. The client, transports, listener,
+//! session recording, capture and testing helpers need `std::io` and are not
+//! available.
+//!
+//! This does not make the crate usable on targets without `std` yet:
+//! `netlink-packet-core` 0.8 uses `std` unconditionally, so the dependency
+//! still needs it.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 #[macro_use]
 extern crate netlink_packet_core;
 
+pub mod array;
+pub use self::array::{NlaArray, NlaArrayAttr};
+
+pub mod batch;
+pub use self::batch::{GenlBatch, GenlMessages};
+
 pub mod buffer;
 pub use self::buffer::GenlBuffer;
 
+pub mod canonical;
+pub use self::canonical::Canonicalize;
+
+#[cfg(feature = "std")]
+pub mod capabilities;
+#[cfg(feature = "std")]
+pub use self::capabilities::{Capabilities, NetnsId};
+
+#[cfg(feature = "std")]
+pub mod capture;
+#[cfg(feature = "std")]
+pub use self::capture::{
+    CaptureReader, GenlDissector, PcapngTap, PcapngWriter,
+};
+
+#[cfg(feature = "std")]
+pub mod client;
+#[cfg(feature = "std")]
+pub use self::client::GenlClient;
+
 pub mod constants;
 
 pub mod ctrl;
 
+#[cfg(feature = "std")]
+mod errno;
+
+pub mod generate;
+pub use self::generate::RequestGenerator;
+
 pub mod header;
 pub use self::header::GenlHeader;
 
+pub mod hexdump;
+pub use self::hexdump::HexDump;
+
+#[cfg(feature = "std")]
+pub mod listener;
+#[cfg(feature = "std")]
+pub use self::listener::{GenlListener, ListenerEvent};
+
 pub mod message;
 pub use self::message::GenlMessage;
 
+pub mod raw;
+pub use self::raw::GenlRaw;
+
+#[cfg(feature = "std")]
+pub mod session;
+#[cfg(feature = "std")]
+pub use self::session::{GenlRecorder, GenlReplay, GenlSession};
+
+#[cfg(feature = "std")]
+pub mod subscription;
+#[cfg(feature = "std")]
+pub use self::subscription::McastSubscription;
+
+#[cfg(feature = "std")]
+pub mod testing;
+
 pub mod traits;
 pub use self::traits::GenlFamily;
+
+#[cfg(feature = "std")]
+pub mod transport;
+#[cfg(feature = "std")]
+pub use self::transport::GenlTransport;
+
+pub mod visit;
+pub use self::visit::NlaVisitor;
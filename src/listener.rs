@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: MIT
+
+//! Multicast listener recovering from receive buffer overruns
+use crate::{
+    errno::ENOBUFS, header::GenlHeader, message::GenlMessage,
+    subscription::McastSubscription, traits::GenlFamily,
+    transport::GenlTransport,
+};
+use netlink_packet_core::{Emitable, ParseableParametrized, NLM_F_DUMP};
+use std::{fmt::Debug, io};
+
+/// Event produced by a [`GenlListener`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenerEvent<F> {
+    /// A notification of the family
+    Message(GenlMessage<F>),
+    /// The socket receive buffer overflowed (`ENOBUFS`), some notifications
+    /// were dropped by the kernel
+    EventsLost,
+    /// Replies of the resynchronization dump request, sent after some
+    /// notifications were lost
+    Resync(Vec<GenlMessage<F>>),
+}
+
+type ResyncRequest<F> = Box<dyn FnMut() -> GenlMessage<F>>;
+
+/// Listener of the multicast notifications of the family `F`
+///
+/// When the kernel reports that the socket receive buffer overflowed, the
+/// listener yields [`ListenerEvent::EventsLost`] and resolves its
+/// subscriptions again. If a resynchronization request was configured with
+/// [`set_resync()`](Self::set_resync), it is then sent as a dump and its
+/// replies are yielded as [`ListenerEvent::Resync`] before the following
+/// notifications.
+pub struct GenlListener<T, F> {
+    subscription: McastSubscription<T>,
+    resync: Option<ResyncRequest<F>>,
+    refresh_pending: bool,
+    resync_pending: bool,
+}
+
+impl<T, F> GenlListener<T, F>
+where
+    T: GenlTransport,
+    F: GenlFamily + Emitable + ParseableParametrized<[u8], GenlHeader> + Debug,
+{
+    /// Listen to the groups joined by `subscription`
+    pub fn new(subscription: McastSubscription<T>) -> Self {
+        Self {
+            subscription,
+            resync: None,
+            refresh_pending: false,
+            resync_pending: false,
+        }
+    }
+
+    /// Set the dump request sent to resynchronize the state after
+    /// notifications were lost
+    ///
+    /// The family id of the built message is filled by the listener.
+    pub fn set_resync<R>(&mut self, request: R)
+    where
+        R: FnMut() -> GenlMessage<F> + 'static,
+    {
+        self.resync = Some(Box::new(request));
+    }
+
+    /// Return a mutable reference to the underlying subscription
+    pub fn subscription_mut(&mut self) -> &mut McastSubscription<T> {
+        &mut self.subscription
+    }
+
+    /// Consume the listener and return the underlying subscription
+    pub fn into_subscription(self) -> McastSubscription<T> {
+        self.subscription
+    }
+
+    /// Wait for the next event
+    pub fn next_event(&mut self) -> io::Result<ListenerEvent<F>> {
+        if self.refresh_pending {
+            return self.overrun();
+        }
+        if self.resync_pending {
+            return self.resync();
+        }
+        match self.subscription.recv::<F>() {
+            Ok(msg) => Ok(ListenerEvent::Message(msg)),
+            Err(e) if e.raw_os_error() == Some(ENOBUFS) => self.overrun(),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn overrun(&mut self) -> io::Result<ListenerEvent<F>> {
+        self.resync_pending = self.resync.is_some();
+        // More notifications may be lost while the subscriptions are
+        // resolved again, which is reported as another event and retried
+        self.refresh_pending = match self.subscription.refresh() {
+            Ok(()) => false,
+            Err(e) if e.raw_os_error() == Some(ENOBUFS) => true,
+            Err(e) => return Err(e),
+        };
+        Ok(ListenerEvent::EventsLost)
+    }
+
+    fn resync(&mut self) -> io::Result<ListenerEvent<F>> {
+        let mut request = match self.resync.as_mut() {
+            Some(build) => build(),
+            None => {
+                self.resync_pending = false;
+                return self.next_event();
+            }
+        };
+        if let Some(id) = self.subscription.family_id(F::family_name()) {
+            request.set_resolved_family_id(id);
+        }
+        match self.subscription.client_mut().request(request, NLM_F_DUMP) {
+            Ok(replies) => {
+                self.resync_pending = false;
+                Ok(ListenerEvent::Resync(replies))
+            }
+            Err(e) if e.raw_os_error() == Some(ENOBUFS) => self.overrun(),
+            Err(e) => Err(e),
+        }
+    }
+}
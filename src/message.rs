@@ -2,12 +2,14 @@
 
 //! Message definition and method implementations
 
-use crate::{buffer::GenlBuffer, header::GenlHeader, traits::*};
+use crate::{
+    buffer::GenlBuffer, canonical::Canonicalize, header::GenlHeader, traits::*,
+};
+use core::fmt::Debug;
 use netlink_packet_core::{
     DecodeError, Emitable, NetlinkDeserializable, NetlinkHeader,
     NetlinkPayload, NetlinkSerializable, ParseableParametrized,
 };
-use std::fmt::Debug;
 
 #[cfg(doc)]
 use netlink_packet_core::NetlinkMessage;
@@ -19,6 +21,7 @@ use netlink_packet_core::NetlinkMessage;
 /// [`GenlFamily`], [`Emitable`], and [`ParseableParametrized<[u8],
 /// GenlHeader>`](ParseableParametrized).
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GenlMessage<F> {
     pub header: GenlHeader,
     pub payload: F,
@@ -129,6 +132,12 @@ where
     }
 }
 
+impl<F: Canonicalize> Canonicalize for GenlMessage<F> {
+    fn canonicalize(&mut self) {
+        self.payload.canonicalize();
+    }
+}
+
 impl<F> Emitable for GenlMessage<F>
 where
     F: GenlFamily + Emitable + Debug,
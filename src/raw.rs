@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: MIT
+
+//! Undecoded payload of any generic netlink family
+use crate::{header::GenlHeader, hexdump::Annotate, traits::GenlFamily};
+use alloc::{vec, vec::Vec};
+use netlink_packet_core::{
+    DecodeError, Emitable, Nla, NlasIterator, ParseableParametrized,
+    NLA_HEADER_SIZE,
+};
+
+/// Payload of a generic netlink message, kept as raw bytes
+///
+/// This type can carry the messages of any family, e.g. to forward or
+/// inspect them without knowing their attributes. As the family is not known
+/// statically, [`GenlFamily::family_name()`] returns an empty string, and the
+/// family id must be set with
+/// [`GenlMessage::set_resolved_family_id()`](crate::GenlMessage::set_resolved_family_id).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenlRaw {
+    /// Command code of this message
+    pub cmd: u8,
+    /// Family version of this message
+    pub version: u8,
+    /// Bytes following the generic netlink header, including the family
+    /// specific header if any
+    pub payload: Vec<u8>,
+}
+
+impl GenlRaw {
+    /// Build the payload from a list of netlink attributes
+    pub fn from_nlas<T: Nla>(cmd: u8, version: u8, nlas: &[T]) -> Self {
+        let mut payload = vec![0u8; nlas.buffer_len()];
+        nlas.emit(&mut payload);
+        Self {
+            cmd,
+            version,
+            payload,
+        }
+    }
+
+    /// Iterate over the netlink attributes of the payload
+    ///
+    /// The family must not use a family specific header.
+    pub fn nlas(&self) -> NlasIterator<&[u8]> {
+        NlasIterator::new(&self.payload[..])
+    }
+
+    /// Return whether the payload contains an attribute identical to `nla`
+    pub fn contains_nla<T: Nla>(&self, nla: &T) -> bool {
+        let mut expected = vec![0u8; nla.buffer_len()];
+        nla.emit(&mut expected);
+        let expected = &expected[..NLA_HEADER_SIZE + nla.value_len()];
+        self.nlas().flatten().any(|found| {
+            found.length() as usize == expected.len()
+                && found.into_inner().get(..expected.len()) == Some(expected)
+        })
+    }
+}
+
+impl<F: GenlFamily + Emitable> From<&F> for GenlRaw {
+    fn from(payload: &F) -> Self {
+        let mut buf = vec![0u8; payload.buffer_len()];
+        payload.emit(&mut buf);
+        Self {
+            cmd: payload.command(),
+            version: payload.version(),
+            payload: buf,
+        }
+    }
+}
+
+impl GenlFamily for GenlRaw {
+    fn family_name() -> &'static str {
+        ""
+    }
+
+    fn command(&self) -> u8 {
+        self.cmd
+    }
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+}
+
+impl Annotate for GenlRaw {}
+
+impl Emitable for GenlRaw {
+    fn buffer_len(&self) -> usize {
+        self.payload.len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) {
+        buffer[..self.payload.len()].copy_from_slice(&self.payload)
+    }
+}
+
+impl ParseableParametrized<[u8], GenlHeader> for GenlRaw {
+    fn parse_with_param(
+        buf: &[u8],
+        header: GenlHeader,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            cmd: header.cmd,
+            version: header.version,
+            payload: buf.to_vec(),
+        })
+    }
+}
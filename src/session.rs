@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: MIT
+
+//! Recording and replay of generic netlink sessions
+//!
+//! A [`GenlRecorder`] wraps the transport of a client and writes every
+//! datagram it sends and receives to a session file. A [`GenlReplay`] then
+//! serves the received datagrams of the session back to the same client
+//! code, checking that it sends the recorded requests, so a session captured
+//! on another machine can be used as a regression test.
+//!
+//! A session file is a text file starting with the line `# genl-session 1`,
+//! followed by one line per datagram:
+//!
+//! ```text
+//! > 1700000000.000000001 nlctrl 2400000010000500...
+//! < 1700000000.000120000 nlctrl 4c00000010000000...
+//! ```
+//!
+//! The fields are the direction (`>` for sent and `<` for received), the
+//! time since the Unix epoch, the name of the family of the first frame (`-`
+//! when not known) and the datagram in hexadecimal.
+use crate::{
+    batch::align,
+    client::{frame_message_type, parse_frame, split_frames},
+    constants::GENL_ID_CTRL,
+    ctrl::{FamilyNames, GenlCtrl},
+    transport::GenlTransport,
+};
+use netlink_packet_core::{NetlinkBuffer, NetlinkPayload};
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead, Write},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// First line of a session file
+const SESSION_HEADER: &str = "# genl-session 1";
+
+/// Direction of a recorded datagram
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Sent to the kernel
+    Sent,
+    /// Received from the kernel
+    Received,
+}
+
+/// Datagram of a recorded session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// Whether the datagram was sent or received
+    pub direction: Direction,
+    /// Time since the Unix epoch
+    pub timestamp: Duration,
+    /// Name of the family of the first netlink message of the datagram, if
+    /// it was resolved when recording
+    pub family: Option<String>,
+    /// Netlink messages of the datagram
+    pub data: Vec<u8>,
+}
+
+impl fmt::Display for RecordedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Sent => '>',
+            Direction::Received => '<',
+        };
+        write!(
+            f,
+            "{} {}.{:09} {} ",
+            direction,
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_nanos(),
+            self.family.as_deref().unwrap_or("-"),
+        )?;
+        for byte in &self.data {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RecordedFrame {
+    type Err = io::Error;
+
+    fn from_str(line: &str) -> io::Result<Self> {
+        let mut fields = line.split_ascii_whitespace();
+        let mut next = || fields.next().ok_or_else(|| invalid_line(line));
+        let direction = match next()? {
+            ">" => Direction::Sent,
+            "<" => Direction::Received,
+            _ => return Err(invalid_line(line)),
+        };
+        let (secs, nanos) =
+            next()?.split_once('.').ok_or_else(|| invalid_line(line))?;
+        let secs = secs.parse().map_err(|_| invalid_line(line))?;
+        let nanos = nanos.parse().map_err(|_| invalid_line(line))?;
+        if nanos >= 1_000_000_000 {
+            return Err(invalid_line(line));
+        }
+        let timestamp = Duration::new(secs, nanos);
+        let family = match next()? {
+            "-" => None,
+            name => Some(name.to_owned()),
+        };
+        let hex = next()?;
+        if !hex.is_ascii() || hex.len() % 2 != 0 {
+            return Err(invalid_line(line));
+        }
+        let data = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid_line(line))?;
+        Ok(Self {
+            direction,
+            timestamp,
+            family,
+            data,
+        })
+    }
+}
+
+/// Recorded generic netlink session
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenlSession {
+    /// Recorded datagrams, in order
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl GenlSession {
+    /// Read a session file
+    pub fn read_from<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        match lines.next() {
+            Some(Ok(line)) if line == SESSION_HEADER => (),
+            Some(Err(e)) => return Err(e),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not a generic netlink session file",
+                ))
+            }
+        }
+        let mut frames = Vec::new();
+        for line in lines {
+            let line = line?;
+            if !line.is_empty() && !line.starts_with('#') {
+                frames.push(line.parse()?);
+            }
+        }
+        Ok(Self { frames })
+    }
+
+    /// Write the session in the session file format
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{SESSION_HEADER}")?;
+        for frame in &self.frames {
+            writeln!(writer, "{frame}")?;
+        }
+        writer.flush()
+    }
+}
+
+/// Transport recording the datagrams sent and received through another
+/// transport into a session file
+///
+/// Each datagram is written to `writer` as soon as it goes through, so the
+/// session survives a crash of the recorded program. The family names are
+/// learnt from the controller replies and notifications going through the
+/// transport.
+#[derive(Debug)]
+pub struct GenlRecorder<T, W> {
+    transport: T,
+    writer: W,
+    families: FamilyNames,
+}
+
+impl<T: GenlTransport, W: Write> GenlRecorder<T, W> {
+    /// Record the traffic of `transport` to `writer`
+    pub fn new(transport: T, mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{SESSION_HEADER}")?;
+        writer.flush()?;
+        Ok(Self {
+            transport,
+            writer,
+            families: FamilyNames::default(),
+        })
+    }
+
+    /// Return a mutable reference to the recorded transport
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// Stop recording, and return the transport and the writer
+    pub fn into_parts(self) -> (T, W) {
+        (self.transport, self.writer)
+    }
+
+    fn record(&mut self, direction: Direction, data: &[u8]) -> io::Result<()> {
+        let frames = split_frames(data).unwrap_or_default();
+        for frame in &frames {
+            self.learn(frame);
+        }
+        let family = frames.first().and_then(|frame| {
+            self.families
+                .get(frame_message_type(frame))
+                .map(str::to_owned)
+        });
+        let frame = RecordedFrame {
+            direction,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            family,
+            data: data.to_vec(),
+        };
+        writeln!(self.writer, "{frame}")?;
+        self.writer.flush()
+    }
+
+    /// Learn the family id and name described by a controller message
+    fn learn(&mut self, frame: &[u8]) {
+        if frame_message_type(frame) != GENL_ID_CTRL {
+            return;
+        }
+        let nlas = match parse_frame::<GenlCtrl>(frame).map(|m| m.payload) {
+            Ok(NetlinkPayload::InnerMessage(msg)) => msg.payload.nlas,
+            _ => return,
+        };
+        self.families.learn(&nlas);
+    }
+}
+
+impl<T: GenlTransport, W: Write> GenlTransport for GenlRecorder<T, W> {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let sent = self.transport.send(buf)?;
+        self.record(Direction::Sent, &buf[..sent])?;
+        Ok(sent)
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let buf = self.transport.recv()?;
+        self.record(Direction::Received, &buf)?;
+        Ok(buf)
+    }
+
+    fn add_membership(&mut self, group: u32) -> io::Result<()> {
+        self.transport.add_membership(group)
+    }
+
+    fn drop_membership(&mut self, group: u32) -> io::Result<()> {
+        self.transport.drop_membership(group)
+    }
+}
+
+/// Transport serving a recorded session back
+///
+/// The sent datagrams must match the next recorded ones, except for their
+/// sequence numbers: the sequence numbers of the received datagrams are
+/// rewritten to the ones actually used by the client. When the next
+/// recorded datagram was sent, or when the session is over, receiving fails
+/// with [`io::ErrorKind::WouldBlock`]. Multicast memberships are accepted
+/// and ignored.
+#[derive(Clone, Debug, Default)]
+pub struct GenlReplay {
+    frames: VecDeque<RecordedFrame>,
+    sequence_numbers: Vec<(u32, u32)>,
+}
+
+impl GenlReplay {
+    /// Replay `session`
+    pub fn new(session: GenlSession) -> Self {
+        Self {
+            frames: session.frames.into(),
+            sequence_numbers: Vec::new(),
+        }
+    }
+
+    /// Return the datagrams which were not replayed yet
+    pub fn remaining(&self) -> impl Iterator<Item = &RecordedFrame> {
+        self.frames.iter()
+    }
+
+    /// Return whether the whole session was replayed
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl GenlTransport for GenlReplay {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let expected = match self.frames.front() {
+            Some(frame) if frame.direction == Direction::Sent => frame,
+            Some(frame) => {
+                return Err(mismatch(format!(
+                    "sent {} bytes while {} bytes were to be received",
+                    buf.len(),
+                    frame.data.len()
+                )))
+            }
+            None => return Err(mismatch("sent after the end of the session")),
+        };
+        let sent = split_frames(buf)?;
+        let recorded = split_frames(&expected.data)?;
+        if sent.len() != recorded.len() {
+            return Err(mismatch(format!(
+                "sent {} messages instead of {}",
+                sent.len(),
+                recorded.len()
+            )));
+        }
+        let mut sequence_numbers = Vec::new();
+        for (sent, recorded) in sent.into_iter().zip(recorded) {
+            let actual = NetlinkBuffer::new(&sent).sequence_number();
+            let original = NetlinkBuffer::new(&recorded).sequence_number();
+            if with_sequence_number(recorded, actual) != sent {
+                return Err(mismatch(format!(
+                    "message with sequence number {actual} differs from the \
+                     recorded message with sequence number {original}"
+                )));
+            }
+            sequence_numbers.push((original, actual));
+        }
+        self.frames.pop_front();
+        for (original, actual) in sequence_numbers {
+            self.sequence_numbers
+                .retain(|(known, _)| *known != original);
+            self.sequence_numbers.push((original, actual));
+        }
+        Ok(buf.len())
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        let frame = match self.frames.front() {
+            Some(frame) if frame.direction == Direction::Received => {
+                self.frames.pop_front().unwrap()
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "no datagram to receive in the recorded session",
+                ))
+            }
+        };
+        // The sequence numbers are rewritten in place, keeping the padding
+        // between the messages
+        let mut data = frame.data.clone();
+        let mut offset = 0;
+        for msg in split_frames(&frame.data)? {
+            // Notifications carry no sequence number
+            let original = NetlinkBuffer::new(&msg).sequence_number();
+            let actual = self
+                .sequence_numbers
+                .iter()
+                .find(|(known, _)| original != 0 && *known == original)
+                .map_or(original, |(_, actual)| *actual);
+            NetlinkBuffer::new(&mut data[offset..]).set_sequence_number(actual);
+            offset += align(msg.len());
+        }
+        Ok(data)
+    }
+
+    fn add_membership(&mut self, _group: u32) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn drop_membership(&mut self, _group: u32) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn with_sequence_number(mut frame: Vec<u8>, sequence_number: u32) -> Vec<u8> {
+    NetlinkBuffer::new(&mut frame).set_sequence_number(sequence_number);
+    frame
+}
+
+fn invalid_line(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid session line: {line}"),
+    )
+}
+
+fn mismatch<E: Into<String>>(description: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("replay mismatch: {}", description.into()),
+    )
+}
@@ -0,0 +1,303 @@
+// SPDX-License-Identifier: MIT
+
+//! Multicast group subscription by family and group name
+use crate::{
+    client::{frame_message_type, invalid_data, parse_frame, GenlClient},
+    constants::GENL_ID_CTRL,
+    ctrl::{nlas::GenlCtrlAttrs, FamilyInfo, GenlCtrl, GenlCtrlCmd},
+    header::GenlHeader,
+    message::GenlMessage,
+    traits::GenlFamily,
+    transport::GenlTransport,
+};
+use netlink_packet_core::{NetlinkPayload, ParseableParametrized};
+use std::{collections::BTreeSet, convert::TryFrom, fmt::Debug, io};
+
+/// Name of the multicast group where the controller sends its notifications
+const CTRL_NOTIFY_GROUP: &str = "notify";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Subscribed {
+    family_name: String,
+    group_name: String,
+    family_id: Option<u16>,
+    group_id: Option<u32>,
+}
+
+/// Multicast subscriptions keyed by `(family_name, group_name)`
+///
+/// Multicast group ids are allocated by the kernel when a family registers,
+/// so they change when the module providing the family is reloaded. This
+/// type joins the controller notification group, watches the
+/// `CTRL_CMD_NEWFAMILY`, `CTRL_CMD_NEWMCAST_GRP`, `CTRL_CMD_DELFAMILY` and
+/// `CTRL_CMD_DELMCAST_GRP` notifications, and joins the groups again with
+/// their new ids when they come back.
+///
+/// Controller notifications are consumed internally, unless the `notify`
+/// group of `nlctrl` was explicitly subscribed.
+#[derive(Debug)]
+pub struct McastSubscription<T> {
+    client: GenlClient<T>,
+    groups: Vec<Subscribed>,
+}
+
+impl<T: GenlTransport> McastSubscription<T> {
+    /// Build the subscription on top of `client`, and join the controller
+    /// notification group
+    pub fn new(mut client: GenlClient<T>) -> io::Result<Self> {
+        let ctrl = client.get_family(GenlCtrl::family_name())?;
+        let ctrl_group =
+            ctrl.mcast_group_id(CTRL_NOTIFY_GROUP).ok_or_else(|| {
+                not_found(GenlCtrl::family_name(), CTRL_NOTIFY_GROUP)
+            })?;
+        client.add_membership(ctrl_group)?;
+        Ok(Self {
+            client,
+            groups: Vec::new(),
+        })
+    }
+
+    /// Resolve and join the multicast group `group_name` of the family
+    /// `family_name`
+    pub fn subscribe(
+        &mut self,
+        family_name: &str,
+        group_name: &str,
+    ) -> io::Result<()> {
+        if self.find(family_name, group_name).is_some() {
+            return Ok(());
+        }
+        let family = self.client.get_family(family_name)?;
+        let group_id = family
+            .mcast_group_id(group_name)
+            .ok_or_else(|| not_found(family_name, group_name))?;
+        self.client.add_membership(group_id)?;
+        self.groups.push(Subscribed {
+            family_name: family_name.to_owned(),
+            group_name: group_name.to_owned(),
+            family_id: Some(family.id),
+            group_id: Some(group_id),
+        });
+        Ok(())
+    }
+
+    /// Leave the multicast group `group_name` of the family `family_name`
+    pub fn unsubscribe(
+        &mut self,
+        family_name: &str,
+        group_name: &str,
+    ) -> io::Result<()> {
+        if let Some(index) = self.find(family_name, group_name) {
+            let group = self.groups.remove(index);
+            // The controller notification group stays joined for the
+            // subscription itself, and the groups of a family which left the
+            // kernel are no longer joined
+            if group.family_name == GenlCtrl::family_name() {
+                return Ok(());
+            }
+            if let Some(group_id) = group.group_id {
+                self.client.drop_membership(group_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the current id of the family `family_name`, if one of its
+    /// groups is subscribed and the family is registered
+    pub fn family_id(&self, family_name: &str) -> Option<u16> {
+        self.groups
+            .iter()
+            .find(|grp| grp.family_name == family_name)
+            .and_then(|grp| grp.family_id)
+    }
+
+    /// Return the current id of a subscribed group, if its family is
+    /// registered
+    pub fn group_id(&self, family_name: &str, group_name: &str) -> Option<u32> {
+        self.find(family_name, group_name)
+            .and_then(|index| self.groups[index].group_id)
+    }
+
+    /// Return a mutable reference to the underlying client
+    pub fn client_mut(&mut self) -> &mut GenlClient<T> {
+        &mut self.client
+    }
+
+    /// Consume the subscription and return the underlying client
+    pub fn into_client(self) -> GenlClient<T> {
+        self.client
+    }
+
+    /// Receive the next netlink message of the subscribed groups
+    ///
+    /// The returned buffer holds exactly one netlink message.
+    pub fn recv_frame(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            let frame = self.client.recv_frame()?;
+            if frame_message_type(&frame) != GENL_ID_CTRL {
+                return Ok(frame);
+            }
+            self.handle_ctrl(&frame)?;
+            if self
+                .find(GenlCtrl::family_name(), CTRL_NOTIFY_GROUP)
+                .is_some()
+            {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Receive the next notification of the family `F`
+    ///
+    /// Messages of the other subscribed families are discarded. This fails
+    /// with [`io::ErrorKind::NotFound`] if no group of `F` is subscribed, as
+    /// no message of `F` could be received. While `F` is unregistered, e.g.
+    /// during the reload of its module, this waits for it to come back.
+    pub fn recv<F>(&mut self) -> io::Result<GenlMessage<F>>
+    where
+        F: GenlFamily + ParseableParametrized<[u8], GenlHeader> + Debug,
+    {
+        if !self
+            .groups
+            .iter()
+            .any(|grp| grp.family_name == F::family_name())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("family {} is not subscribed", F::family_name()),
+            ));
+        }
+        loop {
+            let frame = self.recv_frame()?;
+            if Some(frame_message_type(&frame))
+                != self.family_id(F::family_name())
+            {
+                continue;
+            }
+            if let NetlinkPayload::InnerMessage(msg) =
+                parse_frame::<F>(&frame)?.payload
+            {
+                return Ok(msg);
+            }
+        }
+    }
+
+    /// Resolve all the subscribed groups again and join them
+    ///
+    /// The controller notifications may be lost when the socket receive
+    /// buffer overflows, so this should be called to recover from it.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let names: BTreeSet<String> = self
+            .groups
+            .iter()
+            .map(|grp| grp.family_name.clone())
+            .collect();
+        for name in names {
+            match self.client.get_family(&name) {
+                Ok(family) => self.family_added(&family)?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    self.family_removed(&name)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn find(&self, family_name: &str, group_name: &str) -> Option<usize> {
+        self.groups.iter().position(|grp| {
+            grp.family_name == family_name && grp.group_name == group_name
+        })
+    }
+
+    fn handle_ctrl(&mut self, frame: &[u8]) -> io::Result<()> {
+        let msg = match parse_frame::<GenlCtrl>(frame)?.payload {
+            NetlinkPayload::InnerMessage(msg) => msg.payload,
+            _ => return Ok(()),
+        };
+        match msg.cmd {
+            GenlCtrlCmd::NewFamily => {
+                let family = FamilyInfo::try_from(&msg.nlas[..])
+                    .map_err(invalid_data)?;
+                self.family_added(&family)?;
+            }
+            GenlCtrlCmd::NewMcastGrp => {
+                let family = FamilyInfo::try_from(&msg.nlas[..])
+                    .map_err(invalid_data)?;
+                self.groups_added(&family)?;
+            }
+            GenlCtrlCmd::DelFamily => {
+                if let Some(name) = family_name(&msg.nlas) {
+                    self.family_removed(name);
+                }
+            }
+            GenlCtrlCmd::DelMcastGrp => {
+                let family = FamilyInfo::try_from(&msg.nlas[..])
+                    .map_err(invalid_data)?;
+                for grp in self
+                    .groups
+                    .iter_mut()
+                    .filter(|grp| grp.family_name == family.name)
+                {
+                    if family.mcast_group_id(&grp.group_name).is_some() {
+                        grp.group_id = None;
+                    }
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Update the subscriptions from the complete description of a family
+    fn family_added(&mut self, family: &FamilyInfo) -> io::Result<()> {
+        self.family_removed(&family.name);
+        self.groups_added(family)
+    }
+
+    /// Join the subscribed groups among the groups of `family`
+    fn groups_added(&mut self, family: &FamilyInfo) -> io::Result<()> {
+        for grp in self
+            .groups
+            .iter_mut()
+            .filter(|grp| grp.family_name == family.name)
+        {
+            grp.family_id = Some(family.id);
+            if let Some(id) = family.mcast_group_id(&grp.group_name) {
+                // Joining an already joined group is harmless, and covers
+                // missed deletion notifications
+                self.client.add_membership(id)?;
+                grp.group_id = Some(id);
+            }
+        }
+        Ok(())
+    }
+
+    fn family_removed(&mut self, family_name: &str) {
+        for grp in self
+            .groups
+            .iter_mut()
+            .filter(|grp| grp.family_name == family_name)
+        {
+            grp.family_id = None;
+            grp.group_id = None;
+        }
+    }
+}
+
+fn family_name(nlas: &[GenlCtrlAttrs]) -> Option<&str> {
+    nlas.iter().find_map(|nla| {
+        if let GenlCtrlAttrs::FamilyName(name) = nla {
+            Some(name.as_str())
+        } else {
+            None
+        }
+    })
+}
+
+fn not_found(family_name: &str, group_name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("family {family_name} has no multicast group {group_name}"),
+    )
+}
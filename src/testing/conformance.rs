@@ -0,0 +1,421 @@
+// SPDX-License-Identifier: MIT
+
+//! Conformance of family implementations with the policy of the kernel
+//!
+//! A family crate implements [`DeclareSchema`] for its payload, listing the
+//! attributes that each command puts in its requests, and checks this
+//! declaration from its test suite:
+//!
+//! - [`check_policy()`] against a policy built by the test, or dumped from a
+//!   [`FakeController`](super::FakeController), without kernel;
+//! - [`check_snapshot()`] against the snapshots saved with `genl-ctrl
+//!   snapshot FAMILY` on the supported kernels and committed with the tests,
+//!   which need the `serde` feature and `serde_json` to be loaded;
+//! - [`check_kernel()`] against the running kernel, through
+//!   [`GenlClient::connect()`](crate::GenlClient) with the `socket` feature,
+//!   usually in an ignored test as it needs the family in the kernel.
+//!
+//! [`check_request()`] then checks that the requests built by the crate
+//! follow the declaration. All the checks return the mismatches, so a
+//! conforming family asserts that they are empty:
+//!
+//! ```ignore
+//! #[test]
+//! fn linux_6_8() {
+//!     let snapshot: FamilySnapshot =
+//!         serde_json::from_str(include_str!("linux-6.8.json")).unwrap();
+//!     let mismatches = check_snapshot::<Family>(&snapshot);
+//!     assert_eq!(Vec::<Mismatch>::new(), mismatches);
+//! }
+//!
+//! #[test]
+//! fn get_request() {
+//!     let request = Family::get(1);
+//!     let mismatches = check_request(&request, RequestKind::Do);
+//!     assert_eq!(Vec::<Mismatch>::new(), mismatches);
+//! }
+//!
+//! #[test]
+//! #[ignore = "needs the family in the running kernel"]
+//! fn running_kernel() {
+//!     let mut client = GenlClient::connect().unwrap();
+//!     let mismatches = check_kernel::<Family, _>(&mut client).unwrap();
+//!     assert_eq!(Vec::<Mismatch>::new(), mismatches);
+//! }
+//! ```
+
+use crate::{
+    client::GenlClient,
+    constants::*,
+    ctrl::{
+        nlas::NlaType, FamilyPolicy, FamilySnapshot, GenlCtrl, RequestKind,
+    },
+    traits::GenlFamily,
+    transport::GenlTransport,
+};
+use netlink_packet_core::{Emitable, NlasIterator, NLA_TYPE_MASK};
+use std::{collections::BTreeMap, fmt, io};
+
+/// Attribute of a request, as declared by a family implementation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttributeSchema {
+    /// Attribute type
+    pub kind: u16,
+    /// Type of the payload of the attribute
+    pub nla_type: NlaType,
+    /// Nested attributes, or attributes of the elements of a nested array
+    pub nested: Vec<AttributeSchema>,
+}
+
+/// Attributes of the requests of a command, as declared by a family
+/// implementation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommandSchema {
+    /// Command id
+    pub cmd: u8,
+    /// Kind of the requests
+    pub request: RequestKind,
+    /// Attributes the implementation puts in the requests
+    pub attributes: Vec<AttributeSchema>,
+}
+
+/// Family implementation declaring the attributes of its requests, to check
+/// it against the policy of the kernel
+///
+/// See [`check_policy()`], [`check_snapshot()`] and [`check_kernel()`],
+/// which check the declaration only. Use [`check_request()`] on the requests
+/// built by the implementation to check that they follow the declaration.
+pub trait DeclareSchema: GenlFamily {
+    /// Return the attributes of the requests of each command
+    fn schema() -> Vec<CommandSchema>;
+}
+
+/// Difference between a family implementation and the policy of the kernel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Command id
+    pub cmd: u8,
+    /// Kind of the requests
+    pub request: RequestKind,
+    /// Types of the attribute and of its parents, empty for the command
+    pub path: Vec<u16>,
+    /// What differs
+    pub kind: MismatchKind,
+}
+
+/// Kind of difference between a family implementation and the policy of the
+/// kernel
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// The kernel does not report the policy of these requests
+    UnknownCommand,
+    /// The attribute is not in the policy of the kernel
+    UnknownAttribute,
+    /// The attribute has another type in the policy of the kernel
+    TypeMismatch { declared: NlaType, kernel: NlaType },
+    /// The requests are not declared
+    UndeclaredCommand,
+    /// The request holds an attribute which is not declared
+    UndeclaredAttribute,
+    /// The value of the attribute in the request does not have the length
+    /// of its declared type
+    LengthMismatch { declared: NlaType, len: usize },
+}
+
+impl AttributeSchema {
+    /// Declare attribute `kind`, holding a `nla_type` value
+    pub fn new(kind: u16, nla_type: NlaType) -> Self {
+        Self {
+            kind,
+            nla_type,
+            nested: Vec::new(),
+        }
+    }
+
+    /// Declare attribute `kind`, holding the `nested` attributes
+    pub fn nested(kind: u16, nested: Vec<AttributeSchema>) -> Self {
+        Self {
+            kind,
+            nla_type: NlaType::Nested,
+            nested,
+        }
+    }
+
+    /// Declare attribute `kind`, holding an array of elements holding the
+    /// `nested` attributes
+    pub fn nested_array(kind: u16, nested: Vec<AttributeSchema>) -> Self {
+        Self {
+            kind,
+            nla_type: NlaType::NestedArray,
+            nested,
+        }
+    }
+}
+
+impl CommandSchema {
+    /// Declare the `attributes` of the `request` requests of command `cmd`
+    pub fn new(
+        cmd: u8,
+        request: RequestKind,
+        attributes: Vec<AttributeSchema>,
+    ) -> Self {
+        Self {
+            cmd,
+            request,
+            attributes,
+        }
+    }
+}
+
+/// Check the schema of family `F` against the `policy` dumped from the
+/// kernel
+///
+/// The attributes declared by `F` must be in the policy of their request,
+/// with the same type. The attributes of the policy which `F` does not use
+/// are not reported.
+pub fn check_policy<F: DeclareSchema>(policy: &FamilyPolicy) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    for command in F::schema() {
+        let index =
+            policy.op(command.cmd).and_then(|op| match command.request {
+                RequestKind::Do => op.do_policy,
+                RequestKind::Dump => op.dump_policy,
+            });
+        let Some(index) = index else {
+            mismatches.push(Mismatch {
+                cmd: command.cmd,
+                request: command.request,
+                path: Vec::new(),
+                kind: MismatchKind::UnknownCommand,
+            });
+            continue;
+        };
+        let mut checker = Checker {
+            policy,
+            command: &command,
+            mismatches: &mut mismatches,
+        };
+        checker.attributes(&[], &command.attributes, Some(index));
+    }
+    mismatches
+}
+
+/// Check the schema of family `F` against a snapshot of the kernel
+///
+/// With the `serde` feature, the snapshots saved with `genl-ctrl snapshot`
+/// can be loaded with `serde_json`, so that a family can be checked against
+/// several kernels without running them.
+pub fn check_snapshot<F: DeclareSchema>(
+    snapshot: &FamilySnapshot,
+) -> Vec<Mismatch> {
+    check_policy::<F>(&snapshot.policy)
+}
+
+/// Check the schema of family `F` against the policy of the running kernel
+pub fn check_kernel<F, T>(
+    client: &mut GenlClient<T>,
+) -> io::Result<Vec<Mismatch>>
+where
+    F: DeclareSchema,
+    T: GenlTransport,
+{
+    let policy = client.get_policy(F::family_name())?;
+    Ok(check_policy::<F>(&policy))
+}
+
+/// Check that the `request` request built from `payload` follows the
+/// schema of family `F`
+///
+/// The attributes of `payload` must be declared, and the integers must have
+/// the width of their declared type. The payload must be made of attributes
+/// only, without family specific header.
+pub fn check_request<F>(payload: &F, request: RequestKind) -> Vec<Mismatch>
+where
+    F: DeclareSchema + Emitable,
+{
+    let cmd = payload.command();
+    let mut mismatches = Vec::new();
+    let Some(command) = F::schema()
+        .into_iter()
+        .find(|command| command.cmd == cmd && command.request == request)
+    else {
+        mismatches.push(Mismatch {
+            cmd,
+            request,
+            path: Vec::new(),
+            kind: MismatchKind::UndeclaredCommand,
+        });
+        return mismatches;
+    };
+    let mut buf = vec![0u8; payload.buffer_len()];
+    payload.emit(&mut buf);
+    let mut checker = RequestChecker {
+        command: &command,
+        mismatches: &mut mismatches,
+    };
+    checker.nlas(&[], &command.attributes, &buf);
+    mismatches
+}
+
+struct Checker<'a> {
+    policy: &'a FamilyPolicy,
+    command: &'a CommandSchema,
+    mismatches: &'a mut Vec<Mismatch>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, path: Vec<u16>, kind: MismatchKind) {
+        self.mismatches.push(Mismatch {
+            cmd: self.command.cmd,
+            request: self.command.request,
+            path,
+            kind,
+        });
+    }
+
+    fn attributes(
+        &mut self,
+        path: &[u16],
+        attributes: &[AttributeSchema],
+        index: Option<u32>,
+    ) {
+        let empty = BTreeMap::new();
+        let policies = index
+            .and_then(|index| self.policy.policies.get(&index))
+            .unwrap_or(&empty);
+        for attribute in attributes {
+            let mut path = path.to_vec();
+            path.push(attribute.kind);
+            let Some(policy) = policies.get(&attribute.kind) else {
+                self.push(path, MismatchKind::UnknownAttribute);
+                continue;
+            };
+            if policy.nla_type != attribute.nla_type {
+                self.push(
+                    path,
+                    MismatchKind::TypeMismatch {
+                        declared: attribute.nla_type,
+                        kernel: policy.nla_type,
+                    },
+                );
+                continue;
+            }
+            if !attribute.nested.is_empty() {
+                self.attributes(&path, &attribute.nested, policy.policy_idx);
+            }
+        }
+    }
+}
+
+struct RequestChecker<'a> {
+    command: &'a CommandSchema,
+    mismatches: &'a mut Vec<Mismatch>,
+}
+
+impl RequestChecker<'_> {
+    fn push(&mut self, path: Vec<u16>, kind: MismatchKind) {
+        self.mismatches.push(Mismatch {
+            cmd: self.command.cmd,
+            request: self.command.request,
+            path,
+            kind,
+        });
+    }
+
+    fn nlas(
+        &mut self,
+        path: &[u16],
+        attributes: &[AttributeSchema],
+        buf: &[u8],
+    ) {
+        // The attributes which cannot be parsed are left to the parsing
+        // tests of the family
+        for nla in NlasIterator::new(buf).map_while(Result::ok) {
+            let kind = nla.kind() & NLA_TYPE_MASK;
+            let mut path = path.to_vec();
+            path.push(kind);
+            let Some(attribute) = attributes.iter().find(|a| a.kind == kind)
+            else {
+                self.push(path, MismatchKind::UndeclaredAttribute);
+                continue;
+            };
+            let value = nla.value();
+            match attribute.nla_type {
+                NlaType::Nested => self.nlas(&path, &attribute.nested, value),
+                NlaType::NestedArray => {
+                    for item in NlasIterator::new(value).map_while(Result::ok) {
+                        self.nlas(&path, &attribute.nested, item.value());
+                    }
+                }
+                nla_type => match width(nla_type) {
+                    Some(width) if width != value.len() => self.push(
+                        path,
+                        MismatchKind::LengthMismatch {
+                            declared: nla_type,
+                            len: value.len(),
+                        },
+                    ),
+                    _ => (),
+                },
+            }
+        }
+    }
+}
+
+/// Return the length of the values of type `nla_type`, if fixed
+fn width(nla_type: NlaType) -> Option<usize> {
+    match nla_type {
+        NlaType::Flag => Some(0),
+        NlaType::U8 | NlaType::S8 => Some(1),
+        NlaType::U16 | NlaType::S16 => Some(2),
+        NlaType::U32 | NlaType::S32 => Some(4),
+        NlaType::U64 | NlaType::S64 | NlaType::Bitfield32 => Some(8),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "command {}", self.cmd)?;
+        match self.request {
+            RequestKind::Do => write!(f, " do")?,
+            RequestKind::Dump => write!(f, " dump")?,
+        }
+        let mut separator = " attribute ";
+        for kind in &self.path {
+            write!(f, "{separator}{kind}")?;
+            separator = "/";
+        }
+        match &self.kind {
+            MismatchKind::UnknownCommand | MismatchKind::UnknownAttribute => {
+                write!(f, ": not in the policy of the kernel")
+            }
+            MismatchKind::TypeMismatch { declared, kernel } => {
+                write!(f, ": declared {declared}, {kernel} in the kernel")
+            }
+            MismatchKind::UndeclaredCommand
+            | MismatchKind::UndeclaredAttribute => write!(f, ": not declared"),
+            MismatchKind::LengthMismatch { declared, len } => {
+                write!(f, ": declared {declared}, {len} bytes in the request")
+            }
+        }
+    }
+}
+
+impl DeclareSchema for GenlCtrl {
+    fn schema() -> Vec<CommandSchema> {
+        let family = || {
+            vec![
+                AttributeSchema::new(CTRL_ATTR_FAMILY_ID, NlaType::U16),
+                AttributeSchema::new(CTRL_ATTR_FAMILY_NAME, NlaType::NulString),
+            ]
+        };
+        let mut policy = family();
+        policy.push(AttributeSchema::new(CTRL_ATTR_OP, NlaType::U32));
+        vec![
+            CommandSchema::new(CTRL_CMD_GETFAMILY, RequestKind::Do, family()),
+            CommandSchema::new(CTRL_CMD_GETFAMILY, RequestKind::Dump, family()),
+            CommandSchema::new(CTRL_CMD_GETPOLICY, RequestKind::Dump, policy),
+        ]
+    }
+}
@@ -0,0 +1,462 @@
+// SPDX-License-Identifier: MIT
+
+use super::{datagram, done_frame, error_frame, serialize};
+use crate::{
+    client::split_frames,
+    constants::*,
+    ctrl::{
+        nlas::{
+            GenlCtrlAttrs, NlPolicyTypeAttrs, NlaType, OppolicyAttr,
+            OppolicyIndexAttr, PolicyAttr,
+        },
+        AttributePolicy, FamilyInfo, FamilyMcastGroup, FamilyOp, FamilyPolicy,
+        GenlCtrl, GenlCtrlCmd, OpPolicy,
+    },
+    errno::{EINVAL, ENOENT, EOPNOTSUPP},
+    message::GenlMessage,
+    transport::GenlTransport,
+    NlaArray,
+};
+use netlink_packet_core::{
+    NetlinkBuffer, NetlinkHeader, NetlinkMessage, NetlinkPayload, Parseable,
+    NLM_F_ACK, NLM_F_DUMP, NLM_F_MULTIPART,
+};
+use std::{collections::VecDeque, io};
+
+/// A family registered in a [`FakeController`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FakeFamily {
+    /// Information replied to `CTRL_CMD_GETFAMILY`
+    pub info: FamilyInfo,
+    /// Per command policy indexes replied to `CTRL_CMD_GETPOLICY`
+    pub op_policies: Vec<OppolicyAttr>,
+    /// Attribute policies replied to `CTRL_CMD_GETPOLICY`
+    pub policies: Vec<PolicyAttr>,
+}
+
+impl From<FamilyInfo> for FakeFamily {
+    fn from(info: FamilyInfo) -> Self {
+        Self {
+            info,
+            ..Default::default()
+        }
+    }
+}
+
+/// In-process stand-in for the kernel generic netlink controller
+///
+/// The controller answers `CTRL_CMD_GETFAMILY` (by name, by id or as a dump)
+/// and `CTRL_CMD_GETPOLICY` from an in-memory table of families, framing the
+/// replies as the kernel does: `NLM_F_MULTI` messages terminated by
+/// `NLMSG_DONE` for dumps, and `NLMSG_ERROR` for failures and
+/// acknowledgements.
+///
+/// It implements [`GenlTransport`], so it can be given to a
+/// [`GenlClient`](crate::GenlClient) in place of a kernel socket. When the
+/// `notify` group is joined, adding or removing a family sends the
+/// controller notifications to the client.
+#[derive(Debug)]
+pub struct FakeController {
+    families: Vec<FakeFamily>,
+    port_number: u32,
+    memberships: Vec<u32>,
+    datagrams: VecDeque<Vec<u8>>,
+}
+
+impl Default for FakeController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeController {
+    /// Create a controller knowing only the `nlctrl` family
+    ///
+    /// As with kernels older than 5.10, the family has no policy, and its
+    /// commands do not advertise `GENL_CMD_CAP_HASPOL`.
+    pub fn new() -> Self {
+        let nlctrl = FamilyInfo {
+            id: GENL_ID_CTRL,
+            name: "nlctrl".to_owned(),
+            version: 2,
+            hdrsize: 0,
+            maxattr: CTRL_ATTR_OP as u32,
+            ops: vec![
+                FamilyOp {
+                    id: CTRL_CMD_GETFAMILY as u32,
+                    flags: GENL_CMD_CAP_DO | GENL_CMD_CAP_DUMP,
+                },
+                FamilyOp {
+                    id: CTRL_CMD_GETPOLICY as u32,
+                    flags: GENL_CMD_CAP_DUMP,
+                },
+            ],
+            mcast_groups: vec![FamilyMcastGroup {
+                name: "notify".to_owned(),
+                id: GENL_ID_CTRL as u32,
+            }],
+        };
+        Self {
+            families: vec![nlctrl.into()],
+            port_number: 0,
+            memberships: Vec::new(),
+            datagrams: VecDeque::new(),
+        }
+    }
+
+    /// Create a controller knowing only the `nlctrl` family, with the policy
+    /// of Linux 5.10 and later, which can dump the policy of a single command
+    ///
+    /// Unlike with [`new()`](Self::new), the commands of the family
+    /// advertise `GENL_CMD_CAP_HASPOL`.
+    pub fn with_op_policies() -> Self {
+        let mut policy = FamilyPolicy::default();
+        policy.ops.insert(
+            CTRL_CMD_GETFAMILY,
+            OpPolicy {
+                do_policy: Some(0),
+                dump_policy: Some(0),
+            },
+        );
+        policy.ops.insert(
+            CTRL_CMD_GETPOLICY,
+            OpPolicy {
+                do_policy: None,
+                dump_policy: Some(1),
+            },
+        );
+        for index in [0, 1] {
+            let attributes = policy.policies.entry(index).or_default();
+            attributes.insert(
+                CTRL_ATTR_FAMILY_ID,
+                AttributePolicy::new(NlaType::U16),
+            );
+            attributes.insert(
+                CTRL_ATTR_FAMILY_NAME,
+                AttributePolicy::new(NlaType::NulString),
+            );
+        }
+        policy
+            .policies
+            .entry(1)
+            .or_default()
+            .insert(CTRL_ATTR_OP, AttributePolicy::new(NlaType::U32));
+
+        let mut ctrl = Self::new();
+        let nlctrl = &mut ctrl.families[0];
+        for op in &mut nlctrl.info.ops {
+            op.flags |= GENL_CMD_CAP_HASPOL;
+        }
+        nlctrl.op_policies = Vec::from(&policy);
+        nlctrl.policies = Vec::from(&policy);
+        ctrl
+    }
+
+    /// Set the port number the replies are addressed to
+    pub fn set_port_number(&mut self, port_number: u32) {
+        self.port_number = port_number;
+    }
+
+    /// Return the registered families
+    pub fn families(&self) -> &[FakeFamily] {
+        &self.families
+    }
+
+    /// Register `family`, replacing any family with the same name
+    ///
+    /// The `CTRL_CMD_NEWFAMILY` and `CTRL_CMD_NEWMCAST_GRP` notifications are
+    /// queued if the `notify` group is joined.
+    pub fn add_family(&mut self, family: FakeFamily) {
+        self.remove_family(&family.info.name);
+
+        let mut notifications =
+            vec![self.notification(GenlCtrlCmd::NewFamily, &family.info)];
+        notifications.extend(family.info.mcast_groups.iter().map(|grp| {
+            self.mcast_notification(GenlCtrlCmd::NewMcastGrp, &family.info, grp)
+        }));
+        self.families.push(family);
+        self.notify(notifications);
+    }
+
+    /// Unregister the family called `name`
+    ///
+    /// The `CTRL_CMD_DELMCAST_GRP` and `CTRL_CMD_DELFAMILY` notifications are
+    /// queued if the `notify` group is joined.
+    pub fn remove_family(&mut self, name: &str) -> Option<FakeFamily> {
+        let index = self.families.iter().position(|f| f.info.name == name)?;
+        let family = self.families.remove(index);
+
+        let mut notifications: Vec<Vec<u8>> = family
+            .info
+            .mcast_groups
+            .iter()
+            .map(|grp| {
+                self.mcast_notification(
+                    GenlCtrlCmd::DelMcastGrp,
+                    &family.info,
+                    grp,
+                )
+            })
+            .collect();
+        notifications
+            .push(self.notification(GenlCtrlCmd::DelFamily, &family.info));
+        self.notify(notifications);
+        Some(family)
+    }
+
+    /// Return the multicast groups joined through the transport
+    pub fn memberships(&self) -> &[u32] {
+        &self.memberships
+    }
+
+    /// Process one request and return the reply messages
+    ///
+    /// Every returned buffer holds exactly one netlink message.
+    pub fn handle(&self, request: &[u8]) -> Vec<Vec<u8>> {
+        let header = match NetlinkBuffer::new_checked(request)
+            .and_then(|buf| NetlinkHeader::parse(&buf))
+        {
+            Ok(header) => header,
+            // The kernel silently drops truncated messages
+            Err(_) => return Vec::new(),
+        };
+        if header.message_type != GENL_ID_CTRL {
+            return vec![error_frame(
+                &header,
+                request,
+                self.port_number,
+                ENOENT,
+            )];
+        }
+        let message =
+            match NetlinkMessage::<GenlMessage<GenlCtrl>>::deserialize(request)
+            {
+                Ok(NetlinkMessage {
+                    payload: NetlinkPayload::InnerMessage(message),
+                    ..
+                }) => message.payload,
+                _ => {
+                    return vec![error_frame(
+                        &header,
+                        request,
+                        self.port_number,
+                        EINVAL,
+                    )]
+                }
+            };
+
+        let dump = header.flags & NLM_F_DUMP == NLM_F_DUMP;
+        let replies = match (message.cmd, dump) {
+            (GenlCtrlCmd::GetFamily, true) => Ok(self
+                .families
+                .iter()
+                .map(|family| Vec::from(&family.info))
+                .collect()),
+            (GenlCtrlCmd::GetFamily, false) => {
+                self.find(&message.nlas).map(|f| vec![Vec::from(&f.info)])
+            }
+            (GenlCtrlCmd::GetPolicy, true) => self.get_policy(&message.nlas),
+            _ => Err(EOPNOTSUPP),
+        };
+        let replies = match replies {
+            Ok(replies) => replies,
+            Err(code) => {
+                return vec![error_frame(
+                    &header,
+                    request,
+                    self.port_number,
+                    code,
+                )]
+            }
+        };
+
+        let cmd = match message.cmd {
+            GenlCtrlCmd::GetFamily => GenlCtrlCmd::NewFamily,
+            cmd => cmd,
+        };
+        let mut frames: Vec<Vec<u8>> = replies
+            .into_iter()
+            .map(|nlas| {
+                let flags = if dump { NLM_F_MULTIPART } else { 0 };
+                self.frame(&header, flags, GenlCtrl { cmd, nlas })
+            })
+            .collect();
+        if dump {
+            frames.push(done_frame(&header, self.port_number));
+        } else if header.flags & NLM_F_ACK == NLM_F_ACK {
+            frames.push(error_frame(&header, request, self.port_number, 0));
+        }
+        frames
+    }
+
+    fn find(&self, nlas: &[GenlCtrlAttrs]) -> Result<&FakeFamily, i32> {
+        let found = nlas.iter().find_map(|nla| match nla {
+            GenlCtrlAttrs::FamilyId(id) => {
+                Some(self.families.iter().find(|f| f.info.id == *id))
+            }
+            GenlCtrlAttrs::FamilyName(name) => {
+                Some(self.families.iter().find(|f| &f.info.name == name))
+            }
+            _ => None,
+        });
+        match found {
+            Some(Some(family)) => Ok(family),
+            Some(None) => Err(ENOENT),
+            None => Err(EINVAL),
+        }
+    }
+
+    fn get_policy(
+        &self,
+        nlas: &[GenlCtrlAttrs],
+    ) -> Result<Vec<Vec<GenlCtrlAttrs>>, i32> {
+        let family = self.find(nlas)?;
+        let op = nlas.iter().find_map(|nla| match nla {
+            GenlCtrlAttrs::Op(op) => Some(*op),
+            _ => None,
+        });
+
+        let op_policies: Vec<&OppolicyAttr> = family
+            .op_policies
+            .iter()
+            .filter(|attr| op.is_none() || op == Some(attr.cmd as u32))
+            .collect();
+        if op.is_some() && op_policies.is_empty() {
+            return Err(ENOENT);
+        }
+        let indexes = if op.is_some() {
+            reachable_policies(&op_policies, &family.policies)
+        } else {
+            family.policies.iter().map(|attr| attr.index).collect()
+        };
+
+        let family_id = GenlCtrlAttrs::FamilyId(family.info.id);
+        let mut replies: Vec<Vec<GenlCtrlAttrs>> = op_policies
+            .into_iter()
+            .map(|attr| {
+                vec![family_id.clone(), GenlCtrlAttrs::OpPolicy(attr.clone())]
+            })
+            .collect();
+        replies.extend(
+            family
+                .policies
+                .iter()
+                .filter(|attr| indexes.contains(&attr.index))
+                .map(|attr| {
+                    vec![family_id.clone(), GenlCtrlAttrs::Policy(attr.clone())]
+                }),
+        );
+        Ok(replies)
+    }
+
+    fn frame(
+        &self,
+        request: &NetlinkHeader,
+        flags: u16,
+        payload: GenlCtrl,
+    ) -> Vec<u8> {
+        let mut message =
+            NetlinkMessage::from(GenlMessage::from_payload(payload));
+        message.header.flags = flags;
+        message.header.sequence_number = request.sequence_number;
+        message.header.port_number = self.port_number;
+        serialize(message)
+    }
+
+    fn notification(&self, cmd: GenlCtrlCmd, info: &FamilyInfo) -> Vec<u8> {
+        self.frame(
+            &NetlinkHeader::default(),
+            0,
+            GenlCtrl {
+                cmd,
+                nlas: info.into(),
+            },
+        )
+    }
+
+    fn mcast_notification(
+        &self,
+        cmd: GenlCtrlCmd,
+        info: &FamilyInfo,
+        grp: &FamilyMcastGroup,
+    ) -> Vec<u8> {
+        self.frame(
+            &NetlinkHeader::default(),
+            0,
+            GenlCtrl {
+                cmd,
+                nlas: vec![
+                    GenlCtrlAttrs::FamilyName(info.name.clone()),
+                    GenlCtrlAttrs::FamilyId(info.id),
+                    GenlCtrlAttrs::McastGroups(NlaArray::new([grp.into()])),
+                ],
+            },
+        )
+    }
+
+    fn notify(&mut self, notifications: Vec<Vec<u8>>) {
+        if self.memberships.contains(&(GENL_ID_CTRL as u32)) {
+            self.datagrams.extend(notifications);
+        }
+    }
+}
+
+impl GenlTransport for FakeController {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for request in split_frames(buf)? {
+            let replies = self.handle(&request);
+            if !replies.is_empty() {
+                self.datagrams.push_back(datagram(&replies));
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        self.datagrams.pop_front().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::WouldBlock, "no pending message")
+        })
+    }
+
+    fn add_membership(&mut self, group: u32) -> io::Result<()> {
+        if !self.memberships.contains(&group) {
+            self.memberships.push(group);
+        }
+        Ok(())
+    }
+
+    fn drop_membership(&mut self, group: u32) -> io::Result<()> {
+        self.memberships.retain(|g| *g != group);
+        Ok(())
+    }
+}
+
+/// Collect the policy indexes used by the commands in `op_policies`,
+/// following the nested policies
+fn reachable_policies(
+    op_policies: &[&OppolicyAttr],
+    policies: &[PolicyAttr],
+) -> Vec<u16> {
+    let mut indexes: Vec<u16> = op_policies
+        .iter()
+        .flat_map(|attr| attr.policy_idx.iter())
+        .map(|idx| match idx {
+            OppolicyIndexAttr::Do(v) | OppolicyIndexAttr::Dump(v) => *v as u16,
+        })
+        .collect();
+    let mut position = 0;
+    while position < indexes.len() {
+        let index = indexes[position];
+        for attr in policies.iter().filter(|attr| attr.index == index) {
+            for nla in &attr.attr_policy.policies {
+                if let NlPolicyTypeAttrs::PolicyIdx(v) = nla {
+                    if !indexes.contains(&(*v as u16)) {
+                        indexes.push(*v as u16);
+                    }
+                }
+            }
+        }
+        position += 1;
+    }
+    indexes
+}
@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: MIT
+
+use super::{
+    datagram, done_frame, error_frame, serialize, FakeController, FakeFamily,
+};
+use crate::{
+    client::split_frames,
+    constants::GENL_ID_CTRL,
+    ctrl::FamilyInfo,
+    errno::{ENOBUFS, ENOENT, EOPNOTSUPP},
+    header::GenlHeader,
+    message::GenlMessage,
+    raw::GenlRaw,
+    traits::GenlFamily,
+    transport::GenlTransport,
+};
+use netlink_packet_core::{
+    Emitable, NetlinkHeader, NetlinkMessage, NetlinkPayload, Nla,
+    ParseableParametrized, NLM_F_ACK, NLM_F_MULTIPART,
+};
+use std::{collections::VecDeque, fmt::Debug, io};
+
+/// Scripted answer of a [`MockTransport`] to a request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MockResponse {
+    /// A single reply, followed by an acknowledgement if it was requested
+    Reply(GenlRaw),
+    /// The messages of a dump, terminated by `NLMSG_DONE`
+    Dump(Vec<GenlRaw>),
+    /// The messages of a dump too large for a single datagram, received in
+    /// a datagram each and terminated by `NLMSG_DONE`
+    LargeDump(Vec<GenlRaw>),
+    /// An acknowledgement without reply
+    Ack,
+    /// An `NLMSG_ERROR` carrying the given (positive) errno
+    Error(i32),
+}
+
+impl MockResponse {
+    /// Reply with the payload of a family message
+    pub fn reply<F: GenlFamily + Emitable>(payload: &F) -> Self {
+        Self::Reply(payload.into())
+    }
+
+    /// Reply with a dump of family messages
+    pub fn dump<F: GenlFamily + Emitable>(payloads: &[F]) -> Self {
+        Self::Dump(payloads.iter().map(GenlRaw::from).collect())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Script {
+    family_name: String,
+    cmd: u8,
+    responses: VecDeque<MockResponse>,
+}
+
+/// In-memory transport answering requests of any family from scripted
+/// responses
+///
+/// The requests to `nlctrl` are answered by an embedded [`FakeController`],
+/// so the families registered with [`add_family()`](Self::add_family) can be
+/// resolved by name. The requests to the other families are recorded, and
+/// answered with the responses scripted with [`on()`](Self::on) for their
+/// family and command, in order. A request without scripted response is
+/// answered with `EOPNOTSUPP`.
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    ctrl: FakeController,
+    scripts: Vec<Script>,
+    requests: VecDeque<NetlinkMessage<GenlMessage<GenlRaw>>>,
+    memberships: Vec<u32>,
+    datagrams: VecDeque<Vec<u8>>,
+    overruns: usize,
+}
+
+impl MockTransport {
+    /// Create a transport without any family but `nlctrl`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the embedded controller
+    pub fn ctrl(&self) -> &FakeController {
+        &self.ctrl
+    }
+
+    /// Register a family, replacing any family with the same name
+    pub fn add_family<T: Into<FakeFamily>>(&mut self, family: T) {
+        self.ctrl.add_family(family.into());
+        self.drain_ctrl();
+    }
+
+    /// Unregister the family called `name`
+    pub fn remove_family(&mut self, name: &str) -> Option<FakeFamily> {
+        let family = self.ctrl.remove_family(name);
+        self.drain_ctrl();
+        family
+    }
+
+    /// Queue `response` for the next request of the command `cmd` of the
+    /// family called `family_name`
+    pub fn on(&mut self, family_name: &str, cmd: u8, response: MockResponse) {
+        match self
+            .scripts
+            .iter_mut()
+            .find(|s| s.family_name == family_name && s.cmd == cmd)
+        {
+            Some(script) => script.responses.push_back(response),
+            None => self.scripts.push(Script {
+                family_name: family_name.to_owned(),
+                cmd,
+                responses: VecDeque::from(vec![response]),
+            }),
+        }
+    }
+
+    /// Send `payload` as a notification of the multicast group `group_name`
+    /// of the family `family_name`
+    ///
+    /// The notification is dropped if the group is not joined.
+    ///
+    /// # Panic
+    ///
+    /// This panics if the family or the group is not registered.
+    #[track_caller]
+    pub fn notify(
+        &mut self,
+        family_name: &str,
+        group_name: &str,
+        payload: GenlRaw,
+    ) {
+        let info = self.family(family_name).unwrap_or_else(|| {
+            panic!("family {family_name} is not registered")
+        });
+        let group = info.mcast_group_id(group_name).unwrap_or_else(|| {
+            panic!("family {family_name} has no group {group_name}")
+        });
+        let mut message = GenlMessage::from_payload(payload);
+        message.set_resolved_family_id(info.id);
+        if self.memberships.contains(&group) {
+            self.datagrams
+                .push_back(serialize(NetlinkMessage::from(message)));
+        }
+    }
+
+    /// Make the next receive fail with `ENOBUFS`, as if the socket receive
+    /// buffer overflowed
+    ///
+    /// Each call makes one more receive fail.
+    pub fn overrun(&mut self) {
+        self.overruns += 1;
+    }
+
+    /// Return the multicast groups joined through the transport
+    pub fn memberships(&self) -> &[u32] {
+        &self.memberships
+    }
+
+    /// Return the recorded requests which were not taken yet, excluding the
+    /// requests to `nlctrl`
+    pub fn requests(
+        &self,
+    ) -> impl Iterator<Item = &NetlinkMessage<GenlMessage<GenlRaw>>> {
+        self.requests.iter()
+    }
+
+    /// Take the oldest recorded request
+    pub fn next_request(
+        &mut self,
+    ) -> Option<NetlinkMessage<GenlMessage<GenlRaw>>> {
+        self.requests.pop_front()
+    }
+
+    /// Take the oldest recorded request, and assert that it is a request of
+    /// the family `F` equal to `expected`
+    #[track_caller]
+    pub fn assert_request<F>(&mut self, expected: &F)
+    where
+        F: GenlFamily
+            + ParseableParametrized<[u8], GenlHeader>
+            + PartialEq
+            + Debug,
+    {
+        let request = self.expect_request(F::family_name());
+        let header = GenlHeader {
+            cmd: request.cmd,
+            version: request.version,
+        };
+        let payload = F::parse_with_param(&request.payload, header)
+            .unwrap_or_else(|e| panic!("failed to parse the request: {e}"));
+        assert_eq!(expected, &payload, "unexpected request");
+    }
+
+    /// Take the oldest recorded request, and assert that it is the command
+    /// `cmd` of the family `family_name`, holding every attribute of `nlas`
+    #[track_caller]
+    pub fn assert_request_nlas<T: Nla + Debug>(
+        &mut self,
+        family_name: &str,
+        cmd: u8,
+        nlas: &[T],
+    ) {
+        let request = self.expect_request(family_name);
+        assert_eq!(cmd, request.cmd, "unexpected command");
+        for nla in nlas {
+            assert!(
+                request.contains_nla(nla),
+                "request has no attribute {nla:?}: {request:?}"
+            );
+        }
+    }
+
+    #[track_caller]
+    fn expect_request(&mut self, family_name: &str) -> GenlRaw {
+        let request = match self.next_request() {
+            Some(NetlinkMessage {
+                payload: NetlinkPayload::InnerMessage(request),
+                ..
+            }) => request,
+            Some(request) => panic!("unexpected request {request:?}"),
+            None => panic!("no request was sent"),
+        };
+        let id = request.resolved_family_id();
+        let name =
+            self.ctrl.families().iter().find_map(|f| {
+                (f.info.id == id).then_some(f.info.name.as_str())
+            });
+        assert_eq!(Some(family_name), name, "request sent to family id {id}");
+        request.payload
+    }
+
+    fn family(&self, name: &str) -> Option<&FamilyInfo> {
+        self.ctrl
+            .families()
+            .iter()
+            .map(|f| &f.info)
+            .find(|info| info.name == name)
+    }
+
+    fn drain_ctrl(&mut self) {
+        while let Ok(datagram) = self.ctrl.recv() {
+            self.datagrams.push_back(datagram);
+        }
+    }
+
+    /// Return the datagrams answering `request`
+    fn handle(&mut self, request: &[u8]) -> Vec<Vec<u8>> {
+        let message = match NetlinkMessage::<GenlMessage<GenlRaw>>::deserialize(
+            request,
+        ) {
+            Ok(message) => message,
+            Err(_) => return Vec::new(),
+        };
+        let header = message.header;
+        if header.message_type == GENL_ID_CTRL {
+            return vec![datagram(&self.ctrl.handle(request))];
+        }
+        let family = self
+            .ctrl
+            .families()
+            .iter()
+            .find(|f| f.info.id == header.message_type)
+            .map(|f| f.info.name.clone());
+        let cmd = match &message.payload {
+            NetlinkPayload::InnerMessage(msg) => msg.header.cmd,
+            _ => return Vec::new(),
+        };
+        self.requests.push_back(message);
+
+        let family = match family {
+            Some(family) => family,
+            None => return vec![error_frame(&header, request, 0, ENOENT)],
+        };
+        let response = self
+            .scripts
+            .iter_mut()
+            .find(|s| s.family_name == family && s.cmd == cmd)
+            .and_then(|s| s.responses.pop_front());
+        match response {
+            Some(MockResponse::Reply(payload)) => {
+                let mut frames = vec![reply_frame(&header, 0, payload)];
+                if header.flags & NLM_F_ACK == NLM_F_ACK {
+                    frames.push(error_frame(&header, request, 0, 0));
+                }
+                vec![datagram(&frames)]
+            }
+            Some(MockResponse::Dump(payloads)) => {
+                let mut frames: Vec<Vec<u8>> = payloads
+                    .into_iter()
+                    .map(|p| reply_frame(&header, NLM_F_MULTIPART, p))
+                    .collect();
+                frames.push(done_frame(&header, 0));
+                vec![datagram(&frames)]
+            }
+            Some(MockResponse::LargeDump(payloads)) => {
+                let mut datagrams: Vec<Vec<u8>> = payloads
+                    .into_iter()
+                    .map(|p| reply_frame(&header, NLM_F_MULTIPART, p))
+                    .collect();
+                datagrams.push(done_frame(&header, 0));
+                datagrams
+            }
+            Some(MockResponse::Ack) => {
+                vec![error_frame(&header, request, 0, 0)]
+            }
+            Some(MockResponse::Error(code)) => {
+                vec![error_frame(&header, request, 0, code)]
+            }
+            None => vec![error_frame(&header, request, 0, EOPNOTSUPP)],
+        }
+    }
+}
+
+impl GenlTransport for MockTransport {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for request in split_frames(buf)? {
+            let datagrams = self.handle(&request);
+            self.datagrams
+                .extend(datagrams.into_iter().filter(|d| !d.is_empty()));
+        }
+        Ok(buf.len())
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        if self.overruns > 0 {
+            self.overruns -= 1;
+            return Err(io::Error::from_raw_os_error(ENOBUFS));
+        }
+        self.datagrams.pop_front().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::WouldBlock, "no pending message")
+        })
+    }
+
+    fn add_membership(&mut self, group: u32) -> io::Result<()> {
+        self.ctrl.add_membership(group)?;
+        if !self.memberships.contains(&group) {
+            self.memberships.push(group);
+        }
+        Ok(())
+    }
+
+    fn drop_membership(&mut self, group: u32) -> io::Result<()> {
+        self.ctrl.drop_membership(group)?;
+        self.memberships.retain(|g| *g != group);
+        Ok(())
+    }
+}
+
+fn reply_frame(
+    request: &NetlinkHeader,
+    flags: u16,
+    payload: GenlRaw,
+) -> Vec<u8> {
+    let mut message = GenlMessage::from_payload(payload);
+    message.set_resolved_family_id(request.message_type);
+    let mut message = NetlinkMessage::from(message);
+    message.header.flags = flags;
+    message.header.sequence_number = request.sequence_number;
+    serialize(message)
+}
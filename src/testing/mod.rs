@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: MIT
+
+//! In-memory peers for testing generic netlink code without a kernel
+
+use crate::{
+    batch::align, message::GenlMessage, raw::GenlRaw, traits::GenlFamily,
+};
+use netlink_packet_core::{
+    DoneMessage, Emitable, ErrorMessage, NetlinkHeader, NetlinkMessage,
+    NetlinkPayload, NLM_F_MULTIPART,
+};
+use std::{fmt::Debug, num::NonZeroI32};
+
+mod conformance;
+mod ctrl;
+mod mock;
+
+pub use self::conformance::*;
+pub use self::ctrl::*;
+pub use self::mock::*;
+
+/// Length of `struct nlmsghdr`
+const NETLINK_HEADER_LEN: usize = 16;
+
+fn serialize<F>(mut message: NetlinkMessage<GenlMessage<F>>) -> Vec<u8>
+where
+    F: GenlFamily + Emitable + Debug,
+{
+    message.finalize();
+    let mut buf = vec![0u8; message.buffer_len()];
+    message.serialize(&mut buf);
+    buf
+}
+
+/// Pack `frames` into a datagram, padding each message to the alignment of
+/// netlink messages as the kernel does
+fn datagram(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for frame in frames {
+        buf.extend_from_slice(frame);
+        buf.resize(align(buf.len()), 0);
+    }
+    buf
+}
+
+/// Build the `NLMSG_DONE` message terminating the dump `request`
+fn done_frame(request: &NetlinkHeader, port_number: u32) -> Vec<u8> {
+    let mut header = NetlinkHeader::default();
+    header.flags = NLM_F_MULTIPART;
+    header.sequence_number = request.sequence_number;
+    header.port_number = port_number;
+    serialize::<GenlRaw>(NetlinkMessage::new(
+        header,
+        NetlinkPayload::Done(DoneMessage::default()),
+    ))
+}
+
+/// Build the `NLMSG_ERROR` message answering `request`, which is an
+/// acknowledgement if `code` is 0
+fn error_frame(
+    request: &NetlinkHeader,
+    request_bytes: &[u8],
+    port_number: u32,
+    code: i32,
+) -> Vec<u8> {
+    let mut header = NetlinkHeader::default();
+    header.sequence_number = request.sequence_number;
+    header.port_number = port_number;
+    let mut error = ErrorMessage::default();
+    error.code = NonZeroI32::new(-code);
+    // Acknowledgements only echo the header of the request
+    error.header = if code == 0 {
+        request_bytes[..NETLINK_HEADER_LEN].to_vec()
+    } else {
+        request_bytes.to_vec()
+    };
+    serialize::<GenlRaw>(NetlinkMessage::new(
+        header,
+        NetlinkPayload::Error(error),
+    ))
+}
@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: MIT
+
+//! Transport abstraction used by the blocking client
+use std::io;
+
+/// A datagram oriented transport carrying netlink messages
+///
+/// The [`GenlClient`](crate::client::GenlClient) is built on top of this
+/// trait, so the same client code can run against a kernel socket or any
+/// other implementation (e.g. an in-memory peer in the tests).
+///
+/// With the `socket` feature enabled, this trait is implemented for
+/// [`netlink_sys::Socket`].
+pub trait GenlTransport {
+    /// Send a datagram, which may hold several netlink messages
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize>;
+
+    /// Receive a whole datagram
+    fn recv(&mut self) -> io::Result<Vec<u8>>;
+
+    /// Join the multicast group `group`
+    fn add_membership(&mut self, group: u32) -> io::Result<()>;
+
+    /// Leave the multicast group `group`
+    fn drop_membership(&mut self, group: u32) -> io::Result<()>;
+}
+
+impl<T: GenlTransport + ?Sized> GenlTransport for &mut T {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (**self).send(buf)
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        (**self).recv()
+    }
+
+    fn add_membership(&mut self, group: u32) -> io::Result<()> {
+        (**self).add_membership(group)
+    }
+
+    fn drop_membership(&mut self, group: u32) -> io::Result<()> {
+        (**self).drop_membership(group)
+    }
+}
+
+#[cfg(feature = "socket")]
+impl GenlTransport for netlink_sys::Socket {
+    fn send(&mut self, buf: &[u8]) -> io::Result<usize> {
+        netlink_sys::Socket::send(self, buf, 0)
+    }
+
+    fn recv(&mut self) -> io::Result<Vec<u8>> {
+        self.recv_from_full().map(|(buf, _addr)| buf)
+    }
+
+    fn add_membership(&mut self, group: u32) -> io::Result<()> {
+        netlink_sys::Socket::add_membership(self, group)
+    }
+
+    fn drop_membership(&mut self, group: u32) -> io::Result<()> {
+        netlink_sys::Socket::drop_membership(self, group)
+    }
+}
@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: MIT
+
+//! Streaming parser for the attributes of generic netlink messages
+//!
+//! Parsing a dump into [`GenlMessage`](crate::GenlMessage)s allocates every
+//! attribute of every message, which is wasteful when only aggregates are
+//! needed, e.g. counting the attributes of the policies of a large family.
+//! The functions of this module instead walk the attributes in place and
+//! report them to an [`NlaVisitor`]: attribute values are passed as slices of
+//! the parsed buffer, and nothing is allocated unless an attribute is
+//! malformed.
+//!
+//! Netlink attributes do not tell reliably whether their value holds nested
+//! attributes, as many kernel families do not set `NLA_F_NESTED`, so the
+//! visitor decides which attributes to descend into.
+
+use crate::{batch::NetlinkFrames, buffer::GenlBuffer, header::GenlHeader};
+use alloc::format;
+use netlink_packet_core::{
+    DecodeError, DoneBuffer, ErrorBuffer, NetlinkBuffer, NetlinkHeader,
+    NlasIterator, Parseable, NLA_F_NESTED, NLA_F_NET_BYTEORDER, NLA_TYPE_MASK,
+    NLMSG_DONE, NLMSG_ERROR, NLMSG_NOOP, NLMSG_OVERRUN,
+};
+
+/// Maximum nesting depth of the visited attributes
+///
+/// The kernel limits its own policies to a depth of 10, so a deeper nesting
+/// comes from a malformed message or a visitor descending into values which
+/// are not nested attributes.
+pub const MAX_NESTING_DEPTH: usize = 32;
+
+/// Receiver of the events of the streaming parser
+///
+/// All the methods have a default implementation, so that visitors only
+/// implement the events they need. The visitor tracks the position of the
+/// visited attributes itself, from the [`enter_nest`](Self::enter_nest) and
+/// [`leave_nest`](Self::leave_nest) events.
+pub trait NlaVisitor {
+    /// Called for each generic netlink message of a dump before its
+    /// attributes, which are skipped when returning `false`
+    fn message(&mut self, _header: &NetlinkHeader, _genl: GenlHeader) -> bool {
+        true
+    }
+
+    /// Return whether the attribute of type `kind` at the current position
+    /// holds nested attributes
+    ///
+    /// `flagged` tells whether the attribute has the `NLA_F_NESTED` flag set,
+    /// which is what the default implementation relies on.
+    fn is_nested(&mut self, _kind: u16, flagged: bool) -> bool {
+        flagged
+    }
+
+    /// Called before the attributes nested in the attribute of type `kind`
+    fn enter_nest(&mut self, _kind: u16) {}
+
+    /// Called after the attributes nested in the attribute of type `kind`
+    fn leave_nest(&mut self, _kind: u16) {}
+
+    /// Called for each attribute which is not nested, with its value
+    fn attribute(&mut self, _kind: u16, _value: &[u8]) {}
+
+    /// Called for each attribute which is not nested, with its type
+    /// including the `NLA_F_NESTED` and `NLA_F_NET_BYTEORDER` flags, and its
+    /// value
+    ///
+    /// The default implementation calls [`attribute`](Self::attribute)
+    /// without the flags.
+    fn raw_attribute(&mut self, nla_type: u16, value: &[u8]) {
+        self.attribute(nla_type & NLA_TYPE_MASK, value);
+    }
+}
+
+/// How a visited dump ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpStatus {
+    /// The dump ended with a successful `NLMSG_DONE`, or was acknowledged
+    Done,
+    /// The kernel reported an error, as a negative errno
+    Error(i32),
+    /// The buffer ended before the end of the dump
+    Incomplete,
+}
+
+/// Visit a sequence of attributes
+pub fn visit_nlas<V: NlaVisitor + ?Sized>(
+    buf: &[u8],
+    visitor: &mut V,
+) -> Result<(), DecodeError> {
+    visit_nested(buf, visitor, 0)
+}
+
+fn visit_nested<V: NlaVisitor + ?Sized>(
+    buf: &[u8],
+    visitor: &mut V,
+    depth: usize,
+) -> Result<(), DecodeError> {
+    for nla in NlasIterator::new(buf) {
+        let nla = nla?;
+        let kind = nla.kind();
+        if visitor.is_nested(kind, nla.nested_flag()) {
+            if depth >= MAX_NESTING_DEPTH {
+                return Err(DecodeError::from(format!(
+                    "attribute {kind} is nested deeper than \
+                     {MAX_NESTING_DEPTH} levels"
+                )));
+            }
+            visitor.enter_nest(kind);
+            visit_nested(nla.value(), visitor, depth + 1)?;
+            visitor.leave_nest(kind);
+        } else {
+            let mut nla_type = kind;
+            if nla.nested_flag() {
+                nla_type |= NLA_F_NESTED;
+            }
+            if nla.network_byte_order_flag() {
+                nla_type |= NLA_F_NET_BYTEORDER;
+            }
+            visitor.raw_attribute(nla_type, nla.value());
+        }
+    }
+    Ok(())
+}
+
+/// Visit the attributes of a generic netlink message, following its generic
+/// netlink header and the `hdrsize` bytes of its family header
+pub fn visit_genl<T, V>(
+    buf: &GenlBuffer<&T>,
+    hdrsize: usize,
+    visitor: &mut V,
+) -> Result<(), DecodeError>
+where
+    T: AsRef<[u8]> + ?Sized,
+    V: NlaVisitor + ?Sized,
+{
+    let payload = buf.payload();
+    let nlas = payload.get(hdrsize..).ok_or_else(|| {
+        DecodeError::from(format!(
+            "family header of {hdrsize} bytes exceeds the payload of {} bytes",
+            payload.len()
+        ))
+    })?;
+    visit_nlas(nlas, visitor)
+}
+
+/// Visit the messages of a dump
+///
+/// `buf` holds the netlink messages of the dump, back to back, as received
+/// from one or more datagrams. For each generic netlink message, the visitor
+/// receives [`NlaVisitor::message`] and then the attributes following the
+/// `hdrsize` bytes of its family header. Visiting stops at `NLMSG_DONE` or at
+/// an error reported by the kernel, including a negative error code in the
+/// `NLMSG_DONE` message.
+pub fn visit_dump<V: NlaVisitor + ?Sized>(
+    buf: &[u8],
+    hdrsize: usize,
+    visitor: &mut V,
+) -> Result<DumpStatus, DecodeError> {
+    for frame in NetlinkFrames::new(buf) {
+        let packet = NetlinkBuffer::new(frame?);
+        let header = NetlinkHeader::parse(&packet)?;
+        match header.message_type {
+            NLMSG_DONE => {
+                // A dump failing after its first messages ends with the
+                // error code in the done message
+                let done = DoneBuffer::new_checked(packet.payload())?;
+                return Ok(match done.code() {
+                    code if code < 0 => DumpStatus::Error(code),
+                    _ => DumpStatus::Done,
+                });
+            }
+            NLMSG_ERROR => {
+                let error = ErrorBuffer::new_checked(packet.payload())?;
+                return Ok(match error.code() {
+                    Some(code) => DumpStatus::Error(code.get()),
+                    None => DumpStatus::Done,
+                });
+            }
+            NLMSG_NOOP | NLMSG_OVERRUN => (),
+            _ => {
+                let genl = GenlBuffer::new_checked(packet.payload())?;
+                if visitor.message(&header, GenlHeader::parse(&genl)?) {
+                    visit_genl(&genl, hdrsize, visitor)?;
+                }
+            }
+        }
+    }
+    Ok(DumpStatus::Incomplete)
+}
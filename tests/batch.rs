@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_core::{
+    NetlinkPayload, NLM_F_ACK, NLM_F_DUMP, NLM_F_REQUEST,
+};
+use netlink_packet_generic::{
+    batch::NetlinkFrames,
+    constants::GENL_ID_CTRL,
+    ctrl::{nlas::GenlCtrlAttrs, GenlCtrl, GenlCtrlCmd},
+    GenlBatch, GenlMessage, GenlMessages, GenlRaw,
+};
+
+/// Batch of a controller dump request and a 3 bytes request to family 0x20
+fn batch() -> GenlBatch {
+    let mut batch = GenlBatch::with_sequence_number(7);
+    let seq = batch.push(
+        GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetFamily,
+            nlas: vec![],
+        }),
+        NLM_F_REQUEST | NLM_F_DUMP,
+    );
+    assert_eq!(7, seq);
+
+    let mut raw = GenlMessage::from_payload(GenlRaw {
+        cmd: 1,
+        version: 1,
+        payload: vec![1, 2, 3],
+    });
+    raw.set_resolved_family_id(0x20);
+    let seq = batch.push(raw, NLM_F_REQUEST | NLM_F_ACK);
+    assert_eq!(8, seq);
+    batch
+}
+
+#[test]
+fn pack() {
+    let batch = batch();
+    assert_eq!(2, batch.len());
+    assert_eq!(9, batch.next_sequence_number());
+    // 20 bytes for the first message, 23 padded to 24 for the second one
+    assert_eq!(44, batch.as_bytes().len());
+    assert_eq!(&[0; 1], &batch.as_bytes()[43..]);
+
+    let frames = NetlinkFrames::new(batch.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        vec![20, 23],
+        frames.iter().map(|f| f.len()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn split() {
+    let batch = batch();
+    let messages = GenlMessages::<GenlRaw>::new(batch.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(2, messages.len());
+
+    assert_eq!(GENL_ID_CTRL, messages[0].header.message_type);
+    assert_eq!(7, messages[0].header.sequence_number);
+    assert_eq!(NLM_F_REQUEST | NLM_F_DUMP, messages[0].header.flags);
+
+    assert_eq!(0x20, messages[1].header.message_type);
+    assert_eq!(8, messages[1].header.sequence_number);
+    match &messages[1].payload {
+        NetlinkPayload::InnerMessage(msg) => {
+            assert_eq!(vec![1, 2, 3], msg.payload.payload)
+        }
+        payload => panic!("unexpected payload {payload:?}"),
+    }
+}
+
+#[test]
+fn skip_zero_sequence_number() {
+    let mut batch = GenlBatch::with_sequence_number(u32::MAX);
+    let message = GenlMessage::from_payload(GenlCtrl {
+        cmd: GenlCtrlCmd::GetFamily,
+        nlas: vec![GenlCtrlAttrs::FamilyName("nlctrl".to_owned())],
+    });
+    assert_eq!(u32::MAX, batch.push(message.clone(), NLM_F_REQUEST));
+    assert_eq!(1, batch.push(message, NLM_F_REQUEST));
+}
+
+#[test]
+fn stop_at_malformed_message() {
+    let mut buf = batch().into_bytes();
+    // Make the second message longer than the buffer
+    buf[20] = 0xff;
+    let mut messages = GenlMessages::<GenlRaw>::new(&buf);
+    assert!(messages.next().unwrap().is_ok());
+    assert!(messages.next().unwrap().is_err());
+    assert!(messages.next().is_none());
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_generic::{
+    array::NlaArrayItem,
+    canonical::{
+        canonical_bytes, diff_messages, diff_nlas, semantic_eq, NlaDiff,
+    },
+    constants::{
+        NL_POLICY_TYPE_ATTR_MAX_LENGTH, NL_POLICY_TYPE_ATTR_MIN_LENGTH,
+        NL_POLICY_TYPE_ATTR_TYPE,
+    },
+    ctrl::{
+        nlas::{
+            AttributePolicyAttr, GenlCtrlAttrs, NlPolicyTypeAttrs, NlaType,
+            OpAttrs, PolicyAttr,
+        },
+        GenlCtrl, GenlCtrlCmd,
+    },
+    Canonicalize, GenlMessage, NlaArray,
+};
+
+fn family(nlas: Vec<GenlCtrlAttrs>) -> GenlMessage<GenlCtrl> {
+    GenlMessage::from_payload(GenlCtrl {
+        cmd: GenlCtrlCmd::NewFamily,
+        nlas,
+    })
+}
+
+fn ops() -> NlaArray<OpAttrs> {
+    NlaArray::new([
+        vec![OpAttrs::Id(3), OpAttrs::Flags(0x0a)],
+        vec![OpAttrs::Id(1), OpAttrs::Flags(0x0e)],
+    ])
+}
+
+#[test]
+fn attribute_order() {
+    let a = family(vec![
+        GenlCtrlAttrs::FamilyId(0x17),
+        GenlCtrlAttrs::FamilyName("ethtool".to_owned()),
+        GenlCtrlAttrs::Ops(ops()),
+    ]);
+    let b = family(vec![
+        GenlCtrlAttrs::Ops(ops()),
+        GenlCtrlAttrs::FamilyName("ethtool".to_owned()),
+        GenlCtrlAttrs::FamilyId(0x17),
+    ]);
+    assert_ne!(a, b);
+    assert!(semantic_eq(&a, &b));
+    assert_eq!(canonical_bytes(&a), canonical_bytes(&b));
+    assert!(diff_messages(&a, &b).is_empty());
+}
+
+#[test]
+fn array_indices() {
+    // Same elements, indexed from 4 by steps of 2, and with the attributes
+    // of the elements swapped
+    let mut sparse = NlaArray {
+        items: vec![
+            NlaArrayItem {
+                index: 6,
+                nlas: vec![OpAttrs::Flags(0x0e), OpAttrs::Id(1)],
+            },
+            NlaArrayItem {
+                index: 4,
+                nlas: vec![OpAttrs::Flags(0x0a), OpAttrs::Id(3)],
+            },
+        ],
+    };
+    sparse.canonicalize();
+
+    let mut expected = ops();
+    expected.canonicalize();
+    assert_eq!(expected, sparse);
+    assert_eq!(1, sparse.items[0].index);
+    assert_eq!(2, sparse.items[1].index);
+}
+
+#[test]
+fn attribute_diff() {
+    let old = vec![
+        GenlCtrlAttrs::FamilyId(0x17),
+        GenlCtrlAttrs::Version(1),
+        GenlCtrlAttrs::HdrSize(0),
+        GenlCtrlAttrs::Ops(ops()),
+    ];
+    let mut new_ops = ops();
+    new_ops.push(vec![OpAttrs::Id(4), OpAttrs::Flags(0x0a)]);
+    let new = vec![
+        GenlCtrlAttrs::Ops(new_ops.clone()),
+        GenlCtrlAttrs::MaxAttr(12),
+        GenlCtrlAttrs::Version(2),
+        GenlCtrlAttrs::FamilyId(0x17),
+    ];
+
+    let mut canonical_ops = ops();
+    canonical_ops.canonicalize();
+    new_ops.canonicalize();
+    assert_eq!(
+        vec![
+            NlaDiff::Changed {
+                old: GenlCtrlAttrs::Version(1),
+                new: GenlCtrlAttrs::Version(2),
+                paths: vec![],
+            },
+            NlaDiff::Removed(GenlCtrlAttrs::HdrSize(0)),
+            NlaDiff::Added(GenlCtrlAttrs::MaxAttr(12)),
+            NlaDiff::Changed {
+                old: GenlCtrlAttrs::Ops(canonical_ops),
+                new: GenlCtrlAttrs::Ops(new_ops),
+                paths: vec![],
+            },
+        ],
+        diff_nlas(&old, &new)
+    );
+}
+
+fn policy(index: u16, policies: Vec<NlPolicyTypeAttrs>) -> GenlCtrlAttrs {
+    GenlCtrlAttrs::Policy(PolicyAttr {
+        index: 0,
+        attr_policy: AttributePolicyAttr { index, policies },
+    })
+}
+
+#[test]
+fn nested_diff() {
+    let old = vec![
+        policy(
+            1,
+            vec![
+                NlPolicyTypeAttrs::Type(NlaType::String),
+                NlPolicyTypeAttrs::MinLength(1),
+            ],
+        ),
+        policy(2, vec![NlPolicyTypeAttrs::Type(NlaType::U8)]),
+    ];
+    let new = vec![
+        policy(
+            1,
+            vec![
+                NlPolicyTypeAttrs::MaxLength(16),
+                NlPolicyTypeAttrs::Type(NlaType::String),
+            ],
+        ),
+        policy(2, vec![NlPolicyTypeAttrs::Type(NlaType::U32)]),
+    ];
+
+    let paths: Vec<_> = diff_nlas(&old, &new)
+        .into_iter()
+        .map(|diff| match diff {
+            NlaDiff::Changed { paths, .. } => paths,
+            diff => panic!("unexpected {diff:?}"),
+        })
+        .collect();
+    // The policies are paired in their canonical order, the shorter first
+    assert_eq!(
+        vec![
+            vec![vec![0, 2, NL_POLICY_TYPE_ATTR_TYPE]],
+            vec![
+                vec![0, 1, NL_POLICY_TYPE_ATTR_MIN_LENGTH],
+                vec![0, 1, NL_POLICY_TYPE_ATTR_MAX_LENGTH],
+            ],
+        ],
+        paths
+    );
+}
+
+#[test]
+fn command_diff() {
+    let old = family(vec![GenlCtrlAttrs::FamilyId(0x17)]);
+    let mut new = old.clone();
+    new.payload.cmd = GenlCtrlCmd::DelFamily;
+    let diff = diff_messages(&old, &new);
+    assert_eq!(Some((1, 2)), diff.command);
+    assert_eq!(None, diff.version);
+    assert!(diff.nlas.is_empty());
+}
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "std")]
+
+use netlink_packet_generic::{
+    ctrl::{
+        nlas::NlaType, AttributePolicy, FamilyInfo, FamilyOp, FamilyPolicy,
+        OpPolicy, RequestKind,
+    },
+    testing::{FakeController, FakeFamily, MockTransport},
+    Capabilities, GenlClient, NetnsId,
+};
+
+const NETNS: NetnsId = NetnsId { dev: 4, ino: 1 };
+
+fn test_family() -> FakeFamily {
+    let info = FamilyInfo {
+        id: 0x20,
+        name: "test".to_owned(),
+        version: 3,
+        // Command 1 supports do requests, command 2 dump requests
+        ops: vec![
+            FamilyOp { id: 1, flags: 0x0a },
+            FamilyOp { id: 2, flags: 0x0c },
+        ],
+        ..Default::default()
+    };
+    let mut policy = FamilyPolicy::default();
+    policy.ops.insert(
+        1,
+        OpPolicy {
+            do_policy: Some(0),
+            dump_policy: None,
+        },
+    );
+    policy.ops.insert(
+        2,
+        OpPolicy {
+            do_policy: None,
+            dump_policy: Some(0),
+        },
+    );
+    policy
+        .policies
+        .entry(0)
+        .or_default()
+        .insert(1, AttributePolicy::new(NlaType::U32));
+    FakeFamily {
+        info,
+        op_policies: Vec::from(&policy),
+        policies: Vec::from(&policy),
+    }
+}
+
+/// Return a controller knowing the test family, with the `nlctrl` policy
+/// of a kernel supporting the policy dumps of single commands if `op_dump`
+fn controller(op_dump: bool) -> FakeController {
+    let mut ctrl = if op_dump {
+        FakeController::with_op_policies()
+    } else {
+        FakeController::new()
+    };
+    ctrl.add_family(test_family());
+    ctrl
+}
+
+#[test]
+fn families() {
+    let mut client = GenlClient::new(controller(true));
+    let mut capabilities = Capabilities::new();
+    let mut probe = capabilities.probe(&mut client, NETNS);
+
+    assert!(probe.has_family("test").unwrap());
+    assert_eq!(Some(3), probe.version("test").unwrap());
+    assert!(!probe.has_family("missing").unwrap());
+    assert_eq!(None, probe.version("missing").unwrap());
+
+    assert!(probe.supports("test", 1, RequestKind::Do).unwrap());
+    assert!(!probe.supports("test", 1, RequestKind::Dump).unwrap());
+    assert!(probe.supports("test", 2, RequestKind::Dump).unwrap());
+    assert!(!probe.supports("test", 3, RequestKind::Do).unwrap());
+    assert!(!probe.supports("missing", 1, RequestKind::Do).unwrap());
+}
+
+#[test]
+fn attributes() {
+    let mut client = GenlClient::new(controller(true));
+    let mut capabilities = Capabilities::new();
+    let mut probe = capabilities.probe(&mut client, NETNS);
+
+    assert!(probe.op_policy_dump().unwrap());
+    assert_eq!(
+        Some(true),
+        probe.accepts("test", 1, RequestKind::Do, 1).unwrap()
+    );
+    assert_eq!(
+        Some(false),
+        probe.accepts("test", 1, RequestKind::Do, 2).unwrap()
+    );
+    assert_eq!(
+        Some(true),
+        probe.accepts("test", 2, RequestKind::Dump, 1).unwrap()
+    );
+    assert_eq!(
+        Some(false),
+        probe.accepts("test", 1, RequestKind::Dump, 1).unwrap()
+    );
+}
+
+#[test]
+fn without_op_policy_dump() {
+    let mut client = GenlClient::new(controller(false));
+    let mut capabilities = Capabilities::new();
+    let mut probe = capabilities.probe(&mut client, NETNS);
+
+    assert!(!probe.op_policy_dump().unwrap());
+    assert!(probe.supports("nlctrl", 10, RequestKind::Dump).unwrap());
+}
+
+#[test]
+fn cache() {
+    let mut client = GenlClient::new(controller(true));
+    let mut capabilities = Capabilities::new();
+    assert!(capabilities
+        .probe(&mut client, NETNS)
+        .has_family("test")
+        .unwrap());
+    client.transport_mut().remove_family("test");
+
+    // The replies of the controller are cached per network namespace
+    let mut probe = capabilities.probe(&mut client, NETNS);
+    assert!(probe.has_family("test").unwrap());
+    let other = NetnsId { dev: 4, ino: 2 };
+    let mut probe = capabilities.probe(&mut client, other);
+    assert!(!probe.has_family("test").unwrap());
+
+    capabilities.invalidate(NETNS);
+    let mut probe = capabilities.probe(&mut client, NETNS);
+    assert!(!probe.has_family("test").unwrap());
+}
+
+#[test]
+fn transient_errors() {
+    let mut mock = MockTransport::new();
+    let nlctrl = FakeController::with_op_policies().remove_family("nlctrl");
+    mock.add_family(nlctrl.unwrap());
+    mock.add_family(test_family());
+    let mut client = GenlClient::new(mock);
+    let mut capabilities = Capabilities::new();
+
+    // A receive buffer overflow is not cached as an unsupported query
+    client.transport_mut().overrun();
+    let mut probe = capabilities.probe(&mut client, NETNS);
+    assert!(probe.op_policy("test", 1).is_err());
+    assert!(probe.op_policy("test", 1).unwrap().is_some());
+}
@@ -0,0 +1,345 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "std")]
+
+use netlink_packet_core::{
+    ErrorMessage, NetlinkHeader, NetlinkMessage, NetlinkPayload, NLM_F_DUMP,
+};
+use netlink_packet_generic::{
+    capture::{DissectedPayload, LINKTYPE_NETLINK, NETLINK_GENERIC},
+    ctrl::{nlas::GenlCtrlAttrs, FamilyInfo, GenlCtrl, GenlCtrlCmd},
+    session::Direction,
+    testing::{MockResponse, MockTransport},
+    CaptureReader, GenlClient, GenlDissector, GenlMessage, GenlRaw,
+    GenlRecorder, GenlSession, PcapngTap, PcapngWriter,
+};
+use std::{fmt::Debug, io, time::Duration};
+
+/// Record a session resolving the `test` family and dumping its objects
+fn session() -> GenlSession {
+    let mut mock = MockTransport::new();
+    mock.add_family(FamilyInfo {
+        id: 0x23,
+        name: "test".to_owned(),
+        version: 1,
+        hdrsize: 0,
+        maxattr: 1,
+        ops: vec![],
+        mcast_groups: vec![],
+    });
+    let reply = GenlRaw::from_nlas(1, 1, &[GenlCtrlAttrs::Version(3)]);
+    mock.on("test", 1, MockResponse::Dump(vec![reply]));
+
+    let mut client =
+        GenlClient::new(GenlRecorder::new(mock, Vec::new()).unwrap());
+    let id = client.get_family("test").unwrap().id;
+    let mut request = GenlMessage::from_payload(GenlRaw {
+        cmd: 1,
+        version: 1,
+        payload: vec![],
+    });
+    request.set_resolved_family_id(id);
+    client.request(request, NLM_F_DUMP).unwrap();
+    let (_, file) = client.into_transport().into_parts();
+    GenlSession::read_from(&file[..]).unwrap()
+}
+
+/// Build the packets captured on `nlmon` for the recorded session, with a
+/// `NETLINK_ROUTE` packet in the middle
+fn packets(session: &GenlSession) -> Vec<Vec<u8>> {
+    let mut packets = Vec::new();
+    for (i, frame) in session.frames.iter().enumerate() {
+        let pkttype: u16 = match frame.direction {
+            Direction::Sent => 6,
+            Direction::Received => 7,
+        };
+        let mut packet = pkttype.to_be_bytes().to_vec();
+        packet.extend(824u16.to_be_bytes());
+        packet.extend([0u8; 10]);
+        packet.extend(NETLINK_GENERIC.to_be_bytes());
+        packet.extend(&frame.data);
+        packets.push(packet);
+        if i == 1 {
+            let mut route = packets[0].clone();
+            route[14..16].copy_from_slice(&0u16.to_be_bytes());
+            packets.push(route);
+        }
+    }
+    packets
+}
+
+fn pcap(packets: &[Vec<u8>]) -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend(0xa1b2_c3d4u32.to_be_bytes());
+    file.extend(2u16.to_be_bytes());
+    file.extend(4u16.to_be_bytes());
+    file.extend([0u8; 8]);
+    file.extend(65535u32.to_be_bytes());
+    file.extend(u32::from(LINKTYPE_NETLINK).to_be_bytes());
+    for (i, packet) in packets.iter().enumerate() {
+        file.extend(1_700_000_000u32.to_be_bytes());
+        file.extend((i as u32 * 10).to_be_bytes());
+        file.extend((packet.len() as u32).to_be_bytes());
+        file.extend((packet.len() as u32).to_be_bytes());
+        file.extend(packet);
+    }
+    file
+}
+
+fn pcapng_block(file: &mut Vec<u8>, kind: u32, body: &[u8]) {
+    let padded = (body.len() + 3) & !3;
+    let length = (padded + 12) as u32;
+    file.extend(kind.to_le_bytes());
+    file.extend(length.to_le_bytes());
+    file.extend(body);
+    file.resize(file.len() + padded - body.len(), 0);
+    file.extend(length.to_le_bytes());
+}
+
+fn pcapng(packets: &[Vec<u8>]) -> Vec<u8> {
+    let mut file = Vec::new();
+    let mut shb = 0x1a2b_3c4du32.to_le_bytes().to_vec();
+    shb.extend(1u16.to_le_bytes());
+    shb.extend(0u16.to_le_bytes());
+    shb.extend(u64::MAX.to_le_bytes());
+    pcapng_block(&mut file, 0x0a0d_0d0a, &shb);
+    let mut idb = LINKTYPE_NETLINK.to_le_bytes().to_vec();
+    idb.extend([0u8; 6]);
+    // if_tsresol: nanoseconds
+    idb.extend(9u16.to_le_bytes());
+    idb.extend(1u16.to_le_bytes());
+    idb.extend([9u8, 0, 0, 0]);
+    idb.extend([0u8; 4]);
+    pcapng_block(&mut file, 1, &idb);
+    for (i, packet) in packets.iter().enumerate() {
+        let units = 1_700_000_000_000_000_000u64 + i as u64 * 10;
+        let mut epb = 0u32.to_le_bytes().to_vec();
+        epb.extend(((units >> 32) as u32).to_le_bytes());
+        epb.extend((units as u32).to_le_bytes());
+        epb.extend((packet.len() as u32).to_le_bytes());
+        epb.extend((packet.len() as u32).to_le_bytes());
+        epb.extend(packet);
+        pcapng_block(&mut file, 6, &epb);
+    }
+    file
+}
+
+fn check(file: &[u8], unit: Duration) {
+    let mut dissector = GenlDissector::new();
+    let mut messages = Vec::new();
+    for (i, packet) in CaptureReader::new(file).unwrap().enumerate() {
+        let packet = packet.unwrap();
+        assert_eq!(LINKTYPE_NETLINK, packet.linktype);
+        assert_eq!(
+            Duration::from_secs(1_700_000_000) + unit * (i as u32 * 10),
+            packet.timestamp
+        );
+        messages.extend(dissector.dissect(&packet).unwrap());
+    }
+
+    let labels: Vec<_> = messages
+        .iter()
+        .map(|m| (m.direction, m.family.as_deref()))
+        .collect();
+    assert_eq!(
+        vec![
+            (Some(Direction::Sent), Some("nlctrl")),
+            (Some(Direction::Received), Some("nlctrl")),
+            (Some(Direction::Received), Some("nlctrl")),
+            (Some(Direction::Sent), Some("test")),
+            (Some(Direction::Received), Some("test")),
+            (Some(Direction::Received), Some("test")),
+        ],
+        labels
+    );
+    assert!(matches!(
+        &messages[0].payload,
+        DissectedPayload::Ctrl(ctrl) if ctrl.cmd == GenlCtrlCmd::GetFamily
+    ));
+    assert!(matches!(
+        &messages[1].payload,
+        DissectedPayload::Ctrl(ctrl) if ctrl.cmd == GenlCtrlCmd::NewFamily
+    ));
+    assert!(matches!(&messages[2].payload, DissectedPayload::Error(_)));
+    assert!(matches!(
+        &messages[4].payload,
+        DissectedPayload::Family(raw) if raw.cmd == 1
+    ));
+    assert!(matches!(&messages[5].payload, DissectedPayload::Done(_)));
+    assert_eq!(Some("test"), dissector.family_name(0x23));
+}
+
+#[test]
+fn dissect_pcap() {
+    let packets = packets(&session());
+    check(&pcap(&packets), Duration::from_micros(1));
+}
+
+#[test]
+fn dissect_pcapng() {
+    let packets = packets(&session());
+    check(&pcapng(&packets), Duration::from_nanos(1));
+}
+
+fn invalid_data<T: Debug>(result: io::Result<T>) {
+    assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+}
+
+#[test]
+fn corrupt_lengths() {
+    let packets = packets(&session());
+    // Length of the first packet of a pcap file
+    let mut file = pcap(&packets);
+    file[32..36].copy_from_slice(&u32::MAX.to_be_bytes());
+    invalid_data(CaptureReader::new(&file[..]).unwrap().next_packet());
+
+    // Length of the interface description block of a pcapng file
+    let mut file = pcapng(&packets);
+    let shb_len = u32::from_le_bytes(file[4..8].try_into().unwrap()) as usize;
+    file[shb_len + 4..shb_len + 8]
+        .copy_from_slice(&0xffff_fffcu32.to_le_bytes());
+    invalid_data(CaptureReader::new(&file[..]).unwrap().next_packet());
+
+    // Length of the section header block of a pcapng file
+    let mut file = pcapng(&packets);
+    file[4..8].copy_from_slice(&0xffff_fffcu32.to_le_bytes());
+    invalid_data(CaptureReader::new(&file[..]));
+}
+
+#[test]
+fn tap_roundtrip() {
+    let mut tap = PcapngTap::new(MockTransport::new(), Vec::new()).unwrap();
+    GenlClient::new(&mut tap).get_family("nlctrl").unwrap();
+    let (_, file) = tap.into_parts();
+
+    let mut writer = PcapngWriter::new(file).unwrap();
+    let mut notification =
+        NetlinkMessage::from(GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::NewFamily,
+            nlas: vec![
+                GenlCtrlAttrs::FamilyId(0x30),
+                GenlCtrlAttrs::FamilyName("late".to_owned()),
+            ],
+        }));
+    notification.finalize();
+    writer
+        .write_message(Direction::Received, &notification)
+        .unwrap();
+    let file = writer.into_inner();
+
+    let mut dissector = GenlDissector::new();
+    let mut messages = Vec::new();
+    for packet in CaptureReader::new(&file[..]).unwrap() {
+        messages.extend(dissector.dissect(&packet.unwrap()).unwrap());
+    }
+    let directions: Vec<_> = messages.iter().map(|m| m.direction).collect();
+    assert_eq!(
+        vec![
+            Some(Direction::Sent),
+            Some(Direction::Received),
+            Some(Direction::Received),
+            Some(Direction::Received),
+        ],
+        directions
+    );
+    assert!(messages
+        .iter()
+        .all(|m| m.family.as_deref() == Some("nlctrl")));
+    assert!(matches!(
+        &messages[3].payload,
+        DissectedPayload::Ctrl(ctrl) if ctrl.cmd == GenlCtrlCmd::NewFamily
+    ));
+    assert_eq!(Some("late"), dissector.family_name(0x30));
+}
+
+/// Build a message of the family with the id `id` or an acknowledgement,
+/// with the port id `port` and the sequence number `seq`
+fn raw_message(
+    id: Option<u16>,
+    port: u32,
+    seq: u32,
+) -> NetlinkMessage<GenlMessage<GenlRaw>> {
+    let mut message = match id {
+        Some(id) => {
+            let mut genl = GenlMessage::from_payload(GenlRaw {
+                cmd: 1,
+                version: 1,
+                payload: vec![],
+            });
+            genl.set_resolved_family_id(id);
+            NetlinkMessage::from(genl)
+        }
+        None => NetlinkMessage::new(
+            NetlinkHeader::default(),
+            NetlinkPayload::Error(ErrorMessage::default()),
+        ),
+    };
+    message.header.port_number = port;
+    message.header.sequence_number = seq;
+    message.finalize();
+    message
+}
+
+#[test]
+fn pending_requests() {
+    let mut writer = PcapngWriter::new(Vec::new()).unwrap();
+    let mut notification =
+        NetlinkMessage::from(GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::NewFamily,
+            nlas: vec![
+                GenlCtrlAttrs::FamilyId(0x23),
+                GenlCtrlAttrs::FamilyName("test".to_owned()),
+            ],
+        }));
+    notification.finalize();
+    writer
+        .write_message(Direction::Received, &notification)
+        .unwrap();
+    // Two sockets using the same sequence number
+    let sent = [(0x10, 100, 1), (0x23, 200, 1)];
+    for (id, port, seq) in sent {
+        writer
+            .write_message(Direction::Sent, &raw_message(Some(id), port, seq))
+            .unwrap();
+    }
+    let answers = [(200, 1), (100, 1), (100, 1)];
+    for (port, seq) in answers {
+        writer
+            .write_message(Direction::Received, &raw_message(None, port, seq))
+            .unwrap();
+    }
+    // More requests than remembered
+    for seq in 1..=1025 {
+        writer
+            .write_message(Direction::Sent, &raw_message(Some(0x23), 0, seq))
+            .unwrap();
+    }
+    for seq in [1, 2] {
+        writer
+            .write_message(Direction::Received, &raw_message(None, 300, seq))
+            .unwrap();
+    }
+    let file = writer.into_inner();
+
+    let mut dissector = GenlDissector::new();
+    let mut labels = Vec::new();
+    for packet in CaptureReader::new(&file[..]).unwrap() {
+        for message in dissector.dissect(&packet.unwrap()).unwrap() {
+            if message.direction == Some(Direction::Received) {
+                labels.push(message.family);
+            }
+        }
+    }
+    let labels: Vec<_> = labels.iter().map(|f| f.as_deref()).collect();
+    assert_eq!(
+        vec![
+            Some("nlctrl"),
+            Some("test"),
+            Some("nlctrl"),
+            None,
+            None,
+            Some("test"),
+        ],
+        labels
+    );
+}
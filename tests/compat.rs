@@ -0,0 +1,171 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_generic::ctrl::{
+    nlas::NlaType, AttributePolicy, Bounds, ChangeKind, FamilyInfo, FamilyOp,
+    FamilySnapshot, OpPolicy, PolicyChange, RequestKind,
+};
+
+fn snapshot(
+    ops: Vec<FamilyOp>,
+    attributes: Vec<(u16, AttributePolicy)>,
+) -> FamilySnapshot {
+    let mut snapshot = FamilySnapshot {
+        info: FamilyInfo {
+            id: 0x20,
+            name: "test".to_owned(),
+            version: 1,
+            ops,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    snapshot.policy.ops.insert(
+        1,
+        OpPolicy {
+            do_policy: Some(0),
+            dump_policy: None,
+        },
+    );
+    snapshot
+        .policy
+        .policies
+        .insert(0, attributes.into_iter().collect());
+    snapshot
+}
+
+fn range(min: u64, max: u64) -> AttributePolicy {
+    AttributePolicy {
+        min_value_unsigned: Some(min),
+        max_value_unsigned: Some(max),
+        ..AttributePolicy::new(NlaType::U32)
+    }
+}
+
+#[test]
+fn compatible_changes() {
+    let old = snapshot(
+        vec![FamilyOp { id: 1, flags: 0x0b }],
+        vec![(1, range(1, 10))],
+    );
+    let new = snapshot(
+        vec![
+            FamilyOp { id: 1, flags: 0x0e },
+            FamilyOp { id: 2, flags: 0x02 },
+        ],
+        vec![(1, range(0, 20)), (2, AttributePolicy::new(NlaType::Flag))],
+    );
+
+    let changes = old.diff(&new);
+    assert_eq!(
+        vec![
+            "command 1: flags 0xb -> 0xe (compatible)",
+            "command 1 do attribute 1: values 1..=10 -> 0..=20 (compatible)",
+            "command 1 do attribute 2: added (compatible)",
+            "command 2: added (compatible)",
+        ],
+        changes.iter().map(ToString::to_string).collect::<Vec<_>>()
+    );
+    assert!(changes.iter().all(|change| !change.is_breaking()));
+}
+
+#[test]
+fn breaking_changes() {
+    let old = snapshot(
+        vec![
+            FamilyOp { id: 1, flags: 0x0e },
+            FamilyOp { id: 2, flags: 0x02 },
+        ],
+        vec![
+            (1, range(1, 10)),
+            (2, AttributePolicy::new(NlaType::Flag)),
+            (3, AttributePolicy::new(NlaType::U8)),
+        ],
+    );
+    let new = snapshot(
+        vec![FamilyOp { id: 1, flags: 0x0b }],
+        vec![(1, range(1, 5)), (3, AttributePolicy::new(NlaType::U16))],
+    );
+
+    let changes = old.diff(&new);
+    assert_eq!(
+        vec![
+            PolicyChange {
+                cmd: 1,
+                request: None,
+                path: vec![],
+                kind: ChangeKind::FlagsChanged {
+                    old: 0x0e,
+                    new: 0x0b
+                },
+            },
+            PolicyChange {
+                cmd: 1,
+                request: Some(RequestKind::Do),
+                path: vec![1],
+                kind: ChangeKind::ValuesChanged {
+                    old: Bounds {
+                        min: Some(1),
+                        max: Some(10)
+                    },
+                    new: Bounds {
+                        min: Some(1),
+                        max: Some(5)
+                    },
+                },
+            },
+            PolicyChange {
+                cmd: 1,
+                request: Some(RequestKind::Do),
+                path: vec![2],
+                kind: ChangeKind::AttributeRemoved,
+            },
+            PolicyChange {
+                cmd: 1,
+                request: Some(RequestKind::Do),
+                path: vec![3],
+                kind: ChangeKind::TypeChanged {
+                    old: NlaType::U8,
+                    new: NlaType::U16,
+                },
+            },
+            PolicyChange {
+                cmd: 2,
+                request: None,
+                path: vec![],
+                kind: ChangeKind::CommandRemoved,
+            },
+        ],
+        changes
+    );
+    assert!(changes.iter().all(PolicyChange::is_breaking));
+}
+
+#[test]
+fn shared_nested_policy() {
+    let nested = |index| AttributePolicy {
+        policy_idx: Some(index),
+        ..AttributePolicy::new(NlaType::Nested)
+    };
+    // Attributes 1 and 2 share policy 1, attribute 3 nests policy 0 itself
+    let attributes = vec![(1, nested(1)), (2, nested(1)), (3, nested(0))];
+    let ops = vec![FamilyOp { id: 1, flags: 0x0a }];
+    let mut old = snapshot(ops.clone(), attributes.clone());
+    old.policy
+        .policies
+        .insert(1, [(1, range(1, 10))].into_iter().collect());
+    let mut new = snapshot(ops, attributes);
+    new.policy
+        .policies
+        .insert(1, [(1, range(1, 5))].into_iter().collect());
+
+    assert_eq!(
+        vec![
+            "command 1 do attribute 1/1: values 1..=10 -> 1..=5 (breaking)",
+            "command 1 do attribute 2/1: values 1..=10 -> 1..=5 (breaking)",
+        ],
+        old.diff(&new)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "std")]
+
+use netlink_packet_core::{DefaultNla, Emitable};
+use netlink_packet_generic::{
+    ctrl::{
+        nlas::{GenlCtrlAttrs, NlaType},
+        AttributePolicy, FamilyPolicy, FamilySnapshot, GenlCtrl, GenlCtrlCmd,
+        RequestKind,
+    },
+    testing::{
+        check_kernel, check_policy, check_request, check_snapshot,
+        AttributeSchema, CommandSchema, DeclareSchema, FakeController,
+        Mismatch, MismatchKind,
+    },
+    GenlClient, GenlFamily,
+};
+
+/// Policy of `nlctrl`, as dumped from the kernel
+fn ctrl_policy() -> FamilyPolicy {
+    GenlClient::new(FakeController::with_op_policies())
+        .get_policy("nlctrl")
+        .unwrap()
+}
+
+#[test]
+fn conforming_family() {
+    assert_eq!(
+        Vec::<Mismatch>::new(),
+        check_policy::<GenlCtrl>(&ctrl_policy())
+    );
+}
+
+#[test]
+fn mismatches() {
+    let mut policy = ctrl_policy();
+    policy.ops.remove(&10);
+    policy.policies.get_mut(&0).unwrap().remove(&2);
+    policy
+        .policies
+        .get_mut(&0)
+        .unwrap()
+        .insert(1, AttributePolicy::new(NlaType::U32));
+    let snapshot = FamilySnapshot {
+        policy,
+        ..Default::default()
+    };
+
+    let mismatches = check_snapshot::<GenlCtrl>(&snapshot);
+    assert_eq!(
+        vec![
+            "command 3 do attribute 1: declared u16, u32 in the kernel",
+            "command 3 do attribute 2: not in the policy of the kernel",
+            "command 3 dump attribute 1: declared u16, u32 in the kernel",
+            "command 3 dump attribute 2: not in the policy of the kernel",
+            "command 10 dump: not in the policy of the kernel",
+        ],
+        mismatches
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Mismatch {
+            cmd: 10,
+            request: RequestKind::Dump,
+            path: vec![],
+            kind: MismatchKind::UnknownCommand,
+        },
+        mismatches[4]
+    );
+}
+
+#[test]
+fn fake_kernel() {
+    let mut client = GenlClient::new(FakeController::with_op_policies());
+
+    assert_eq!(
+        Vec::<Mismatch>::new(),
+        check_kernel::<GenlCtrl, _>(&mut client).unwrap()
+    );
+}
+
+/// Family declaring a u16 attribute, which it emits as a u32
+struct Wide;
+
+impl GenlFamily for Wide {
+    fn family_name() -> &'static str {
+        "wide"
+    }
+
+    fn command(&self) -> u8 {
+        1
+    }
+
+    fn version(&self) -> u8 {
+        1
+    }
+}
+
+impl DeclareSchema for Wide {
+    fn schema() -> Vec<CommandSchema> {
+        vec![CommandSchema::new(
+            1,
+            RequestKind::Do,
+            vec![AttributeSchema::new(1, NlaType::U16)],
+        )]
+    }
+}
+
+impl Emitable for Wide {
+    fn buffer_len(&self) -> usize {
+        8
+    }
+
+    fn emit(&self, buffer: &mut [u8]) {
+        [DefaultNla::new(1, 7u32.to_ne_bytes().to_vec())]
+            .as_slice()
+            .emit(buffer)
+    }
+}
+
+#[test]
+fn requests() {
+    let request = |cmd, nlas| GenlCtrl { cmd, nlas };
+    let get_family = request(
+        GenlCtrlCmd::GetFamily,
+        vec![GenlCtrlAttrs::FamilyName("nlctrl".to_owned())],
+    );
+    let get_policy = request(
+        GenlCtrlCmd::GetPolicy,
+        vec![GenlCtrlAttrs::FamilyId(0x10), GenlCtrlAttrs::Op(3)],
+    );
+    assert_eq!(
+        Vec::<Mismatch>::new(),
+        check_request(&get_family, RequestKind::Do)
+    );
+    assert_eq!(
+        Vec::<Mismatch>::new(),
+        check_request(&get_policy, RequestKind::Dump)
+    );
+
+    let version =
+        request(GenlCtrlCmd::GetFamily, vec![GenlCtrlAttrs::Version(1)]);
+    let mismatches = [
+        check_request(&version, RequestKind::Dump),
+        check_request(&get_policy, RequestKind::Do),
+        check_request(&Wide, RequestKind::Do),
+    ]
+    .concat();
+    assert_eq!(
+        vec![
+            "command 3 dump attribute 3: not declared",
+            "command 10 do: not declared",
+            "command 1 do attribute 1: declared u16, 4 bytes in the request",
+        ],
+        mismatches
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    );
+}
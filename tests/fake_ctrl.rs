@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "std")]
+
+use netlink_packet_core::{
+    NetlinkMessage, NetlinkPayload, NLM_F_DUMP, NLM_F_MULTIPART, NLM_F_REQUEST,
+};
+use netlink_packet_generic::{
+    ctrl::{
+        nlas::{
+            AttributePolicyAttr, GenlCtrlAttrs, NlPolicyTypeAttrs, NlaType,
+            OppolicyAttr, OppolicyIndexAttr, PolicyAttr,
+        },
+        AttributePolicy, FamilyInfo, FamilyMcastGroup, FamilyOp, FamilyPolicy,
+        GenlCtrl, GenlCtrlCmd, OpPolicy,
+    },
+    testing::{FakeController, FakeFamily},
+    GenlClient, GenlMessage, McastSubscription,
+};
+use std::io;
+
+fn test_family(id: u16, group_id: u32) -> FakeFamily {
+    FakeFamily {
+        info: FamilyInfo {
+            id,
+            name: "test".to_owned(),
+            version: 1,
+            hdrsize: 0,
+            maxattr: 2,
+            ops: vec![FamilyOp { id: 1, flags: 0x0a }],
+            mcast_groups: vec![FamilyMcastGroup {
+                name: "events".to_owned(),
+                id: group_id,
+            }],
+        },
+        op_policies: vec![OppolicyAttr {
+            cmd: 1,
+            policy_idx: vec![OppolicyIndexAttr::Do(0)],
+        }],
+        policies: vec![PolicyAttr {
+            index: 0,
+            attr_policy: AttributePolicyAttr {
+                index: 1,
+                policies: vec![NlPolicyTypeAttrs::Type(NlaType::U32)],
+            },
+        }],
+    }
+}
+
+#[test]
+fn get_family() {
+    let mut ctrl = FakeController::new();
+    ctrl.add_family(test_family(0x20, 5));
+    let mut client = GenlClient::new(ctrl);
+
+    let info = client.get_family("test").unwrap();
+    assert_eq!(test_family(0x20, 5).info, info);
+    assert_eq!(0x10, client.get_family("nlctrl").unwrap().id);
+
+    let err = client.get_family("missing").unwrap_err();
+    assert_eq!(io::ErrorKind::NotFound, err.kind());
+}
+
+#[test]
+fn nlctrl_policy() {
+    // The commands advertise a policy only if the controller has one
+    let mut client = GenlClient::new(FakeController::new());
+    let nlctrl = client.get_family("nlctrl").unwrap();
+    assert!(nlctrl.ops.iter().all(|op| !op.has_policy()));
+
+    let mut client = GenlClient::new(FakeController::with_op_policies());
+    let nlctrl = client.get_family("nlctrl").unwrap();
+    assert!(nlctrl.ops.iter().all(FamilyOp::has_policy));
+    assert!(!client.get_policy("nlctrl").unwrap().ops.is_empty());
+}
+
+#[test]
+fn dump_families() {
+    let mut ctrl = FakeController::new();
+    ctrl.add_family(test_family(0x20, 5));
+
+    let mut request =
+        NetlinkMessage::from(GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetFamily,
+            nlas: vec![],
+        }));
+    request.header.flags = NLM_F_REQUEST | NLM_F_DUMP;
+    request.header.sequence_number = 7;
+    request.finalize();
+    let mut buf = vec![0u8; request.buffer_len()];
+    request.serialize(&mut buf);
+
+    let frames = ctrl.handle(&buf);
+    assert_eq!(3, frames.len());
+    for frame in &frames {
+        let msg = <NetlinkMessage<GenlMessage<GenlCtrl>>>::deserialize(frame)
+            .unwrap();
+        assert_eq!(7, msg.header.sequence_number);
+        assert_eq!(NLM_F_MULTIPART, msg.header.flags);
+    }
+    let done = <NetlinkMessage<GenlMessage<GenlCtrl>>>::deserialize(&frames[2])
+        .unwrap();
+    assert!(matches!(done.payload, NetlinkPayload::Done(_)));
+}
+
+#[test]
+fn get_policy() {
+    let mut ctrl = FakeController::new();
+    ctrl.add_family(test_family(0x20, 5));
+    let mut client = GenlClient::new(ctrl);
+
+    let replies = client
+        .request(
+            GenlMessage::from_payload(GenlCtrl {
+                cmd: GenlCtrlCmd::GetPolicy,
+                nlas: vec![
+                    GenlCtrlAttrs::FamilyName("test".to_owned()),
+                    GenlCtrlAttrs::Op(1),
+                ],
+            }),
+            NLM_F_DUMP,
+        )
+        .unwrap();
+    let nlas: Vec<_> = replies.into_iter().map(|m| m.payload.nlas).collect();
+    assert_eq!(
+        vec![
+            vec![
+                GenlCtrlAttrs::FamilyId(0x20),
+                GenlCtrlAttrs::OpPolicy(
+                    test_family(0x20, 5).op_policies[0].clone()
+                ),
+            ],
+            vec![
+                GenlCtrlAttrs::FamilyId(0x20),
+                GenlCtrlAttrs::Policy(test_family(0x20, 5).policies[0].clone()),
+            ],
+        ],
+        nlas
+    );
+}
+
+#[test]
+fn list_families() {
+    let mut ctrl = FakeController::new();
+    ctrl.add_family(test_family(0x20, 5));
+    let mut client = GenlClient::new(ctrl);
+
+    let families = client.list_families().unwrap();
+    assert_eq!(
+        vec!["nlctrl", "test"],
+        families.iter().map(|f| f.name.as_str()).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        "test (id 0x0020, version 1, header size 0, max attribute 2)\n\
+         \x20 commands:\n\
+         \x20   1: do, policy\n\
+         \x20 multicast groups:\n\
+         \x20   5: events\n",
+        families[1].to_string()
+    );
+}
+
+#[test]
+fn resolve_policy() {
+    let mut ctrl = FakeController::new();
+    ctrl.add_family(test_family(0x20, 5));
+    let mut client = GenlClient::new(ctrl);
+
+    let policy = client.get_policy("test").unwrap();
+    assert_eq!(
+        Some(&OpPolicy {
+            do_policy: Some(0),
+            dump_policy: None,
+        }),
+        policy.op(1)
+    );
+    assert_eq!(
+        Some(&AttributePolicy::new(NlaType::U32)),
+        policy.attribute(0, 1)
+    );
+    assert_eq!(
+        "command 1: do policy 0\npolicy 0:\n  attribute 1: u32\n",
+        policy.to_string()
+    );
+    assert_eq!(test_family(0x20, 5).op_policies, Vec::from(&policy));
+    assert_eq!(test_family(0x20, 5).policies, Vec::from(&policy));
+}
+
+#[test]
+fn skip_policy_index_out_of_range() {
+    let mut policy = FamilyPolicy::default();
+    for index in [1, 0x1_0001] {
+        policy
+            .policies
+            .entry(index)
+            .or_default()
+            .insert(1, AttributePolicy::new(NlaType::U32));
+    }
+    let attrs = Vec::<PolicyAttr>::from(&policy);
+    assert_eq!(vec![1], attrs.iter().map(|a| a.index).collect::<Vec<_>>());
+}
+
+#[test]
+fn decode_with_policy() {
+    let mut ctrl = FakeController::new();
+    ctrl.add_family(test_family(0x20, 5));
+    let mut client = GenlClient::new(ctrl);
+
+    let policy = client.get_policy("test").unwrap();
+    // Notifications use the policy shared by all the commands
+    assert_eq!(Some(0), policy.message_policy(1));
+    assert_eq!(Some(0), policy.message_policy(7));
+
+    let nlas = [
+        8, 0, 1, 0, 42, 0, 0, 0, // u32 attribute of the policy
+        6, 0, 2, 0, 0xab, 0xcd, 0, 0, // unknown attribute
+        12, 0, 3, 0x80, 8, 0, 1, 0, 7, 0, 0,
+        0, // unknown nested attribute
+    ];
+    assert_eq!(
+        "1: u32 42\n2: abcd\n3: nested\n  1: 07000000\n",
+        policy.format_nlas(Some(0), &nlas).unwrap()
+    );
+
+    // u32 attribute flagged with NLA_F_NET_BYTEORDER
+    let nlas = [8, 0, 1, 0x40, 0, 0, 1, 2];
+    assert_eq!("1: u32 258\n", policy.format_nlas(Some(0), &nlas).unwrap());
+}
+
+#[test]
+fn rejoin_after_reload() {
+    let mut ctrl = FakeController::new();
+    ctrl.add_family(test_family(0x20, 5));
+    let mut subscription =
+        McastSubscription::new(GenlClient::new(ctrl)).unwrap();
+    subscription.subscribe("test", "events").unwrap();
+    assert_eq!(Some(5), subscription.group_id("test", "events"));
+
+    let ctrl = subscription.client_mut().transport_mut();
+    ctrl.remove_family("test");
+    ctrl.add_family(test_family(0x21, 9));
+
+    // Only the controller notifications are pending
+    let err = subscription.recv_frame().unwrap_err();
+    assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+
+    assert_eq!(Some(0x21), subscription.family_id("test"));
+    assert_eq!(Some(9), subscription.group_id("test", "events"));
+    let ctrl = subscription.client_mut().transport_mut();
+    assert!(ctrl.memberships().contains(&9));
+}
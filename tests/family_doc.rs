@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_generic::ctrl::{
+    nlas::NlaType, AttributePolicy, FamilyDoc, FamilyInfo, FamilyMcastGroup,
+    FamilyOp, FamilyPolicy, FamilySpec, OpPolicy,
+};
+use std::collections::BTreeMap;
+
+fn test_family() -> (FamilyInfo, FamilyPolicy) {
+    let info = FamilyInfo {
+        id: 0x20,
+        name: "test".to_owned(),
+        version: 1,
+        hdrsize: 0,
+        maxattr: 3,
+        ops: vec![
+            FamilyOp { id: 1, flags: 0x0f },
+            FamilyOp { id: 2, flags: 0x04 },
+        ],
+        mcast_groups: vec![FamilyMcastGroup {
+            name: "events".to_owned(),
+            id: 5,
+        }],
+    };
+    let mut policy = FamilyPolicy::default();
+    policy.ops.insert(
+        1,
+        OpPolicy {
+            do_policy: Some(0),
+            dump_policy: Some(1),
+        },
+    );
+    let attributes = policy.policies.entry(0).or_default();
+    attributes.insert(
+        1,
+        AttributePolicy {
+            min_value_unsigned: Some(1),
+            max_value_unsigned: Some(10),
+            ..AttributePolicy::new(NlaType::U32)
+        },
+    );
+    attributes.insert(
+        2,
+        AttributePolicy {
+            max_length: Some(15),
+            ..AttributePolicy::new(NlaType::NulString)
+        },
+    );
+    attributes.insert(
+        3,
+        AttributePolicy {
+            policy_idx: Some(1),
+            policy_max_type: Some(1),
+            ..AttributePolicy::new(NlaType::Nested)
+        },
+    );
+    policy
+        .policies
+        .entry(1)
+        .or_default()
+        .insert(1, AttributePolicy::new(NlaType::Flag));
+    (info, policy)
+}
+
+#[test]
+fn markdown() {
+    let (info, policy) = test_family();
+    assert_eq!(
+        "\
+# Family `test`
+
+| Id | Version | Header size | Max attribute |
+|----|---------|-------------|---------------|
+| 0x0020 | 1 | 0 | 3 |
+
+## Multicast groups
+
+| Name | Id |
+|------|----|
+| `events` | 5 |
+
+## Commands
+
+### Command 1
+
+- Requests: do, dump
+- Permission: `CAP_NET_ADMIN`
+- Do attributes: [policy 0](#policy-0)
+- Dump attributes: [policy 1](#policy-1)
+
+### Command 2
+
+- Requests: dump
+- Permission: none
+
+## Policies
+
+### Policy 0
+
+| Attribute | Type | Values | Length | Nested |
+|-----------|------|--------|--------|--------|
+| 1 | u32 | 1..=10 |  |  |
+| 2 | nul-string |  | <= 15 |  |
+| 3 | nested |  |  | [policy 1](#policy-1), max type 1 |
+
+### Policy 1
+
+| Attribute | Type | Values | Length | Nested |
+|-----------|------|--------|--------|--------|
+| 1 | flag |  |  |  |
+
+",
+        FamilyDoc::new(&info, &policy).to_string()
+    );
+}
+
+#[test]
+fn yaml_spec() {
+    let (info, policy) = test_family();
+    assert_eq!(
+        "\
+name: test
+protocol: genetlink
+doc: >-
+  Skeleton generated from the policy of version 1 of the family.
+
+attribute-sets:
+  -
+    name: policy-0
+    attributes:
+      -
+        name: attr-1
+        value: 1
+        type: u32
+        checks:
+          min: 1
+          max: 10
+      -
+        name: attr-2
+        value: 2
+        type: string
+        checks:
+          max-len: 15
+      -
+        name: attr-3
+        value: 3
+        type: nest
+        nested-attributes: policy-1
+  -
+    name: policy-1
+    attributes:
+      -
+        name: attr-1
+        value: 1
+        type: flag
+
+operations:
+  list:
+    -
+      name: op-1
+      value: 1
+      doc: The do requests follow policy-0 and the dump requests policy-1.
+      flags: [admin-perm]
+      do:
+        # The attributes follow policy-0
+        request: {}
+      dump:
+        # The attributes follow policy-1
+        request: {}
+    -
+      name: op-2
+      value: 2
+      dump:
+        request:
+          attributes: []
+
+mcast-groups:
+  list:
+    -
+      name: events
+",
+        FamilySpec::new(&info, &policy).to_string()
+    );
+}
+
+/// Check the references between the sections of a spec generated by
+/// `FamilySpec`, which `genetlink.yaml` requires on top of its structure: the
+/// nested attributes name an attribute set, and the attributes of the
+/// requests of a command are named in its attribute set
+fn check_spec_references(spec: &str) {
+    let mut sets: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut nested = Vec::new();
+    let mut set = None;
+    let mut op_set = None;
+    for line in spec.lines() {
+        let line = line.trim_end();
+        if let Some(name) = line.strip_prefix("    name: ") {
+            set = Some(name);
+            sets.insert(name, Vec::new());
+        } else if let Some(name) = line.strip_prefix("        name: ") {
+            sets.get_mut(set.unwrap()).unwrap().push(name);
+        } else if let Some(name) =
+            line.strip_prefix("        nested-attributes: ")
+        {
+            nested.push(name);
+        } else if line.starts_with("      name: ") {
+            op_set = None;
+        } else if let Some(name) = line.strip_prefix("      attribute-set: ") {
+            op_set = Some(name);
+        } else if let Some(name) = line.strip_prefix("            - ") {
+            let op_set = op_set.expect("request attributes without set");
+            assert!(sets[op_set].contains(&name), "{name} not in {op_set}");
+        }
+    }
+    for name in nested {
+        assert!(sets.contains_key(name), "no attribute set {name}");
+    }
+}
+
+#[test]
+fn yaml_spec_references() {
+    let (info, mut policy) = test_family();
+    check_spec_references(&FamilySpec::new(&info, &policy).to_string());
+
+    // Requests sharing the policy of the attribute set of the command
+    policy.ops.get_mut(&1).unwrap().dump_policy = Some(0);
+    let spec = FamilySpec::new(&info, &policy).to_string();
+    assert!(spec.contains("      attribute-set: policy-0\n"));
+    assert!(spec.contains("            - attr-3\n"));
+    check_spec_references(&spec);
+}
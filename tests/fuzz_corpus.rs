@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MIT
+
+//! Run the seed corpus of the fuzz targets through their round trip checks
+
+use netlink_packet_core::{NetlinkMessage, NetlinkPayload};
+use netlink_packet_generic::{ctrl::GenlCtrl, GenlMessage, GenlRaw};
+use std::{fs, path::Path};
+
+fn seeds(target: &str) -> Vec<(String, Vec<u8>)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/corpus")
+        .join(target);
+    let mut seeds: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read(path).unwrap())
+        })
+        .collect();
+    seeds.sort();
+    seeds
+}
+
+#[test]
+fn genl_ctrl_seeds() {
+    for (name, data) in seeds("genl_ctrl") {
+        let mut message =
+            NetlinkMessage::<GenlMessage<GenlCtrl>>::deserialize(&data)
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+        message.finalize();
+        let mut emitted = vec![0u8; message.buffer_len()];
+        message.serialize(&mut emitted);
+        // The seeds were captured from the kernel, which emits the
+        // attributes the same way
+        assert_eq!(data, emitted, "{name}");
+
+        let parsed =
+            NetlinkMessage::<GenlMessage<GenlCtrl>>::deserialize(&emitted)
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+        match (message.payload, parsed.payload) {
+            (
+                NetlinkPayload::InnerMessage(message),
+                NetlinkPayload::InnerMessage(parsed),
+            ) => assert_eq!(message.payload, parsed.payload, "{name}"),
+            (message, parsed) => assert_eq!(message, parsed, "{name}"),
+        }
+    }
+}
+
+#[test]
+fn genl_raw_seeds() {
+    for (name, data) in seeds("genl_raw") {
+        let mut message =
+            NetlinkMessage::<GenlMessage<GenlRaw>>::deserialize(&data)
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+        message.finalize();
+        let mut emitted = vec![0u8; message.buffer_len()];
+        message.serialize(&mut emitted);
+        assert_eq!(data, emitted, "{name}");
+    }
+}
@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_core::{NlaBuffer, NlasIterator, NLM_F_DUMP, NLM_F_REQUEST};
+use netlink_packet_generic::{
+    ctrl::{
+        nlas::NlaType, AttributePolicy, FamilyInfo, FamilyOp, FamilySnapshot,
+        OpPolicy, RequestKind,
+    },
+    generate::GeneratedRequest,
+    RequestGenerator,
+};
+
+fn test_family() -> FamilySnapshot {
+    let mut snapshot = FamilySnapshot {
+        info: FamilyInfo {
+            id: 0x20,
+            name: "test".to_owned(),
+            version: 2,
+            ops: vec![FamilyOp { id: 1, flags: 0x0e }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    snapshot.policy.ops.insert(
+        1,
+        OpPolicy {
+            do_policy: Some(0),
+            dump_policy: None,
+        },
+    );
+    let attributes = snapshot.policy.policies.entry(0).or_default();
+    attributes.insert(
+        1,
+        AttributePolicy {
+            min_value_unsigned: Some(1),
+            max_value_unsigned: Some(10),
+            ..AttributePolicy::new(NlaType::U32)
+        },
+    );
+    attributes.insert(
+        2,
+        AttributePolicy {
+            max_length: Some(3),
+            ..AttributePolicy::new(NlaType::NulString)
+        },
+    );
+    attributes.insert(
+        3,
+        AttributePolicy {
+            policy_idx: Some(1),
+            ..AttributePolicy::new(NlaType::Nested)
+        },
+    );
+    snapshot
+        .policy
+        .policies
+        .entry(1)
+        .or_default()
+        .insert(1, AttributePolicy::new(NlaType::Flag));
+    snapshot
+}
+
+/// Validate the attributes of policy 0 of the test family like the kernel
+fn validate(buf: &[u8], nested: bool) -> bool {
+    for nla in NlasIterator::new(buf) {
+        let Ok(nla) = nla else {
+            return false;
+        };
+        if !validate_nla(&nla, nested) {
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_nla(nla: &NlaBuffer<&[u8]>, nested: bool) -> bool {
+    let value = nla.value();
+    match (nested, nla.kind(), nla.nested_flag()) {
+        (false, 1, false) => {
+            let Ok(value) = <[u8; 4]>::try_from(value) else {
+                return false;
+            };
+            (1..=10).contains(&u32::from_ne_bytes(value))
+        }
+        (false, 2, false) => value.len() <= 4 && value.last() == Some(&0),
+        (false, 3, true) => {
+            (value.is_empty() || value.len() >= 4) && validate(value, true)
+        }
+        (true, 1, false) => value.is_empty(),
+        _ => false,
+    }
+}
+
+fn check(request: &GeneratedRequest) {
+    let message = &request.message;
+    assert_eq!(1, message.header.cmd);
+    assert_eq!(2, message.header.version);
+    assert_eq!(0x20, message.resolved_family_id());
+    let nlas = &message.payload.payload;
+    match request.request {
+        RequestKind::Do => assert_eq!(
+            validate(nlas, false),
+            request.valid,
+            "{:?} {nlas:02x?}",
+            request.request
+        ),
+        // Without policy, the kernel accepts any attribute
+        RequestKind::Dump => assert!(request.valid),
+    }
+}
+
+#[test]
+fn boundary_requests() {
+    let snapshot = test_family();
+    let requests = RequestGenerator::new(&snapshot).boundary_requests();
+    requests.iter().for_each(check);
+
+    let valid = requests.iter().filter(|request| request.valid).count();
+    assert_eq!((18, 8), (requests.len(), valid));
+    let u32_nla = |value: u32| {
+        let mut nla = vec![8, 0, 1, 0];
+        nla.extend_from_slice(&value.to_ne_bytes());
+        nla
+    };
+    for (value, valid) in [(0, false), (1, true), (10, true), (11, false)] {
+        assert!(requests.iter().any(|request| {
+            request.message.payload.payload == u32_nla(value)
+                && request.valid == valid
+        }));
+    }
+    // The only request without policy is the empty dump
+    let dumps: Vec<_> = requests
+        .iter()
+        .filter(|request| request.request == RequestKind::Dump)
+        .collect();
+    assert_eq!(1, dumps.len());
+    assert_eq!(
+        NLM_F_REQUEST | NLM_F_DUMP,
+        dumps[0].to_netlink().header.flags
+    );
+}
+
+#[test]
+fn max_depth() {
+    let snapshot = test_family();
+    let requests = RequestGenerator::new(&snapshot)
+        .max_depth(1)
+        .boundary_requests();
+    requests.iter().for_each(check);
+    // Without the attributes nested in attribute 3
+    assert_eq!(15, requests.len());
+}
+
+#[test]
+fn unknown_attributes() {
+    let mut snapshot = test_family();
+    snapshot.info.maxattr = 5;
+    snapshot.info.version = 0x100;
+    let policy = snapshot.policy.policies.get_mut(&0).unwrap();
+    policy.get_mut(&3).unwrap().policy_max_type = Some(2);
+    let requests = RequestGenerator::new(&snapshot).boundary_requests();
+
+    // The types 4 and 5 are accepted by the kernel, without policy
+    let unknown = [4, 0, 6, 0];
+    assert!(requests.iter().any(|request| {
+        request.message.payload.payload == unknown[..] && !request.valid
+    }));
+    let nested_unknown = [8, 0, 3, 0x80, 4, 0, 3, 0];
+    assert!(requests.iter().any(|request| {
+        request.message.payload.payload == nested_unknown[..] && !request.valid
+    }));
+    assert!(requests
+        .iter()
+        .all(|request| request.message.header.version == u8::MAX));
+}
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary {
+    use super::*;
+    use ::arbitrary::Unstructured;
+    use proptest::{collection::vec, prelude::*};
+
+    proptest! {
+        #[test]
+        fn arbitrary_requests(
+            data in vec(any::<u8>(), 0..256),
+            violate in any::<bool>(),
+        ) {
+            let snapshot = test_family();
+            let generator = RequestGenerator::new(&snapshot);
+            let mut u = Unstructured::new(&data);
+            let request = generator.arbitrary_request(&mut u, violate).unwrap();
+            prop_assert_eq!(!violate, request.valid);
+            check(&request);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_core::{
+    Emitable, NetlinkMessage, NLM_F_DUMP, NLM_F_REQUEST,
+};
+use netlink_packet_generic::{
+    ctrl::{
+        nlas::{GenlCtrlAttrs, McastGrpAttrs},
+        GenlCtrl, GenlCtrlCmd,
+    },
+    hexdump::dump_message,
+    GenlMessage, GenlRaw, HexDump, NlaArray,
+};
+
+fn request() -> NetlinkMessage<GenlMessage<GenlCtrl>> {
+    let mut message =
+        NetlinkMessage::from(GenlMessage::from_payload(GenlCtrl {
+            cmd: GenlCtrlCmd::GetFamily,
+            nlas: vec![GenlCtrlAttrs::FamilyName("nlctrl".to_owned())],
+        }));
+    message.header.flags = NLM_F_REQUEST | NLM_F_DUMP;
+    message.header.sequence_number = 1;
+    message.finalize();
+    message
+}
+
+/// Return the lines of `dump`, without the trailing spaces
+fn lines(dump: &str) -> Vec<&str> {
+    dump.lines().map(str::trim_end).collect()
+}
+
+#[test]
+fn ctrl_request() {
+    assert_eq!(
+        vec![
+            "0000  20 00 00 00                                       nlmsghdr length 32",
+            "0004  10 00                                               type 16 (GENL_ID_CTRL)",
+            "0006  01 03                                               flags 0x0301",
+            "0008  01 00 00 00                                         sequence 1",
+            "000c  00 00 00 00                                         port 0",
+            "0010  03                                                genlmsghdr cmd 3 (CTRL_CMD_GETFAMILY)",
+            "0011  02                                                  version 2",
+            "0012  00 00                                               reserved",
+            "0014  0b 00 02 00                                       nla length 11 type 2 (CTRL_ATTR_FAMILY_NAME)",
+            "0018  6e 6c 63 74 72 6c 00                                \"nlctrl\"",
+            "001f  00                                                padding",
+        ],
+        lines(&dump_message(&request()))
+    );
+}
+
+#[test]
+fn unknown_family() {
+    let message = request();
+    let mut buf = vec![0; message.buffer_len()];
+    message.serialize(&mut buf);
+    let dump = HexDump::<GenlRaw>::new(&buf).to_string();
+    assert_eq!(
+        vec![
+            "0010  03                                                genlmsghdr cmd 3",
+            "0011  02                                                  version 2",
+            "0012  00 00                                               reserved",
+            "0014  0b 00 02 00                                       nla length 11 type 2",
+            "0018  6e 6c 63 74 72 6c 00                                value",
+            "001f  00                                                padding",
+        ],
+        lines(&dump)[5..]
+    );
+}
+
+#[test]
+fn nested_attributes() {
+    let groups = GenlCtrlAttrs::McastGroups(NlaArray::new([vec![
+        McastGrpAttrs::Id(7),
+        McastGrpAttrs::Name("group1".to_owned()),
+    ]]));
+    let mut buf = vec![0; groups.buffer_len()];
+    groups.emit(&mut buf);
+    let dump = HexDump::<GenlCtrl>::attributes(&buf).to_string();
+    assert_eq!(
+        vec![
+            "0000  1c 00 07 00                                       nla length 28 type 7 (CTRL_ATTR_MCAST_GROUPS)",
+            "0004  18 00 01 00                                         nla length 24 type 1 (mcast group)",
+            "0008  08 00 02 00                                           nla length 8 type 2 (CTRL_ATTR_MCAST_GRP_ID)",
+            "000c  07 00 00 00                                             7",
+            "0010  0b 00 01 00                                           nla length 11 type 1 (CTRL_ATTR_MCAST_GRP_NAME)",
+            "0014  67 72 6f 75 70 31 00                                    \"group1\"",
+            "001b  00                                                    padding",
+        ],
+        lines(&dump)
+    );
+}
+
+#[test]
+fn byte_order() {
+    // Family id 0x10, in host then network byte order
+    let buf = [6, 0, 1, 0, 0x10, 0, 0, 0, 6, 0, 1, 0x40, 0, 0x10, 0, 0];
+    let dump = HexDump::<GenlCtrl>::attributes(&buf).to_string();
+    assert_eq!(
+        vec![
+            "0000  06 00 01 00                                       nla length 6 type 1 (CTRL_ATTR_FAMILY_ID)",
+            "0004  10 00                                               16",
+            "0006  00 00                                             padding",
+            "0008  06 00 01 40                                       nla length 6 type 1 (CTRL_ATTR_FAMILY_ID) net-byteorder",
+            "000c  00 10                                               16",
+            "000e  00 00                                             padding",
+        ],
+        lines(&dump)
+    );
+}
+
+#[test]
+fn malformed() {
+    let message = request();
+    let mut buf = vec![0; message.buffer_len()];
+    message.serialize(&mut buf);
+    // Attribute longer than the message, followed by a truncated message
+    buf[20] = 0x40;
+    buf.extend([8, 0, 0, 0]);
+    let dump = HexDump::<GenlCtrl>::new(&buf).to_string();
+    assert_eq!(
+        vec![
+            "0014  40 00 02 00 6e 6c 63 74 72 6c 00 00               malformed attribute of length 64",
+            "0020  08 00 00 00                                       truncated message",
+        ],
+        lines(&dump)[8..]
+    );
+}
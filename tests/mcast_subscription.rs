@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "socket")]
+
+use netlink_packet_generic::{
+    constants::GENL_ID_CTRL, ctrl::GenlCtrl, GenlClient, GenlFamily,
+    McastSubscription,
+};
+use std::io;
+
+#[cfg(not(target_arch = "s390x"))]
+#[test]
+fn subscribe_by_name() {
+    let client = GenlClient::connect().unwrap();
+    let mut subscription = McastSubscription::new(client).unwrap();
+    subscription
+        .subscribe(GenlCtrl::family_name(), "notify")
+        .unwrap();
+
+    assert_eq!(
+        Some(GENL_ID_CTRL),
+        subscription.family_id(GenlCtrl::family_name())
+    );
+    // nlctrl's notify group shares the family id
+    assert_eq!(
+        Some(GENL_ID_CTRL as u32),
+        subscription.group_id(GenlCtrl::family_name(), "notify")
+    );
+
+    let err = subscription
+        .subscribe(GenlCtrl::family_name(), "no-such-group")
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::NotFound, err.kind());
+}
+
+#[cfg(not(target_arch = "s390x"))]
+#[test]
+fn recv_unsubscribed_family() {
+    let client = GenlClient::connect().unwrap();
+    let mut subscription = McastSubscription::new(client).unwrap();
+
+    let err = subscription.recv::<GenlCtrl>().unwrap_err();
+    assert_eq!(io::ErrorKind::NotFound, err.kind());
+}
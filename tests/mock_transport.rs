@@ -0,0 +1,377 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "std")]
+
+use netlink_packet_core::{
+    parse_u32, DecodeError, DefaultNla, Emitable, Nla, NlasIterator, Parseable,
+    ParseableParametrized, NLM_F_DUMP,
+};
+use netlink_packet_generic::{
+    ctrl::{FamilyInfo, FamilyMcastGroup},
+    testing::{MockResponse, MockTransport},
+    GenlClient, GenlFamily, GenlHeader, GenlListener, GenlMessage, GenlRaw,
+    ListenerEvent, McastSubscription,
+};
+use std::io;
+
+const FAMILY_ID: u16 = 0x22;
+const CMD_GET: u8 = 1;
+const ATTR_VALUE: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Counter {
+    cmd: u8,
+    value: Option<u32>,
+}
+
+impl Counter {
+    fn nlas(&self) -> Vec<DefaultNla> {
+        self.value
+            .iter()
+            .map(|v| DefaultNla::new(ATTR_VALUE, v.to_ne_bytes().to_vec()))
+            .collect()
+    }
+}
+
+impl GenlFamily for Counter {
+    fn family_name() -> &'static str {
+        "counter"
+    }
+
+    fn command(&self) -> u8 {
+        self.cmd
+    }
+
+    fn version(&self) -> u8 {
+        1
+    }
+}
+
+impl Emitable for Counter {
+    fn buffer_len(&self) -> usize {
+        self.nlas().as_slice().buffer_len()
+    }
+
+    fn emit(&self, buffer: &mut [u8]) {
+        self.nlas().as_slice().emit(buffer)
+    }
+}
+
+impl ParseableParametrized<[u8], GenlHeader> for Counter {
+    fn parse_with_param(
+        buf: &[u8],
+        header: GenlHeader,
+    ) -> Result<Self, DecodeError> {
+        let mut value = None;
+        for nla in NlasIterator::new(buf) {
+            let nla = DefaultNla::parse(&nla?)?;
+            let mut payload = vec![0u8; nla.value_len()];
+            nla.emit_value(&mut payload);
+            if nla.kind() == ATTR_VALUE {
+                value = Some(parse_u32(&payload)?);
+            }
+        }
+        Ok(Self {
+            cmd: header.cmd,
+            value,
+        })
+    }
+}
+
+fn mock() -> MockTransport {
+    let mut mock = MockTransport::new();
+    mock.add_family(FamilyInfo {
+        id: FAMILY_ID,
+        name: "counter".to_owned(),
+        version: 1,
+        hdrsize: 0,
+        maxattr: 1,
+        ops: vec![],
+        mcast_groups: vec![FamilyMcastGroup {
+            name: "changes".to_owned(),
+            id: 12,
+        }],
+    });
+    mock
+}
+
+fn get(value: Option<u32>) -> GenlMessage<Counter> {
+    let mut msg = GenlMessage::from_payload(Counter {
+        cmd: CMD_GET,
+        value,
+    });
+    msg.set_resolved_family_id(FAMILY_ID);
+    msg
+}
+
+#[test]
+fn scripted_reply() {
+    let mut mock = mock();
+    let reply = Counter {
+        cmd: CMD_GET,
+        value: Some(42),
+    };
+    mock.on("counter", CMD_GET, MockResponse::reply(&reply));
+    let mut client = GenlClient::new(&mut mock);
+
+    assert_eq!(
+        "counter",
+        client.get_family("counter").unwrap().name.as_str()
+    );
+    let replies = client.request(get(Some(1)), 0).unwrap();
+    assert_eq!(
+        vec![reply],
+        replies.into_iter().map(|m| m.payload).collect::<Vec<_>>()
+    );
+
+    // Without scripted response, the request is rejected
+    let err = client.request(get(None), 0).unwrap_err();
+    assert_eq!(Some(95), err.raw_os_error());
+
+    mock.assert_request(&Counter {
+        cmd: CMD_GET,
+        value: Some(1),
+    });
+    mock.assert_request_nlas::<DefaultNla>("counter", CMD_GET, &[]);
+    assert!(mock.next_request().is_none());
+}
+
+#[test]
+fn scripted_dump() {
+    let mut mock = mock();
+    let values: Vec<_> = (0..3)
+        .map(|v| {
+            GenlRaw::from_nlas(
+                CMD_GET,
+                1,
+                &Counter {
+                    cmd: CMD_GET,
+                    value: Some(v),
+                }
+                .nlas(),
+            )
+        })
+        .collect();
+    mock.on("counter", CMD_GET, MockResponse::Dump(values.clone()));
+    let mut client = GenlClient::new(&mut mock);
+
+    let mut request = GenlMessage::from_payload(GenlRaw {
+        cmd: CMD_GET,
+        version: 1,
+        payload: vec![],
+    });
+    request.set_resolved_family_id(FAMILY_ID);
+    let replies = client.request(request, NLM_F_DUMP).unwrap();
+    assert_eq!(
+        values,
+        replies.into_iter().map(|m| m.payload).collect::<Vec<_>>()
+    );
+
+    mock.assert_request_nlas::<DefaultNla>("counter", CMD_GET, &[]);
+}
+
+#[test]
+fn unaligned_dump() {
+    let mut mock = mock();
+    // Messages whose length is not a multiple of 4, padded in the datagram
+    let values: Vec<_> = (1..4)
+        .map(|len| GenlRaw {
+            cmd: CMD_GET,
+            version: 1,
+            payload: vec![0xff; len],
+        })
+        .collect();
+    mock.on("counter", CMD_GET, MockResponse::Dump(values.clone()));
+    let mut client = GenlClient::new(&mut mock);
+
+    let mut request = GenlMessage::from_payload(GenlRaw {
+        cmd: CMD_GET,
+        version: 1,
+        payload: vec![],
+    });
+    request.set_resolved_family_id(FAMILY_ID);
+    let replies = client.request(request, NLM_F_DUMP).unwrap();
+    assert_eq!(
+        values,
+        replies.into_iter().map(|m| m.payload).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn dump_parse_error() {
+    let mut mock = mock();
+    let value = |v: u32| {
+        GenlRaw::from_nlas(
+            CMD_GET,
+            1,
+            &Counter {
+                cmd: CMD_GET,
+                value: Some(v),
+            }
+            .nlas(),
+        )
+    };
+    // The second message of the dump holds a truncated value
+    let truncated = GenlRaw {
+        cmd: CMD_GET,
+        version: 1,
+        payload: vec![6, 0, 1, 0, 7, 0, 0, 0],
+    };
+    mock.on(
+        "counter",
+        CMD_GET,
+        MockResponse::LargeDump(vec![value(1), truncated, value(3)]),
+    );
+    mock.on("counter", CMD_GET, MockResponse::Dump(vec![value(4)]));
+    let mut client = GenlClient::new(&mut mock);
+
+    let err = client.request(get(None), NLM_F_DUMP).unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidData, err.kind());
+
+    // The rest of the dump was read and dropped with the failed request
+    let replies = client.request(get(None), NLM_F_DUMP).unwrap();
+    assert_eq!(
+        vec![Some(4)],
+        replies.iter().map(|m| m.payload.value).collect::<Vec<_>>()
+    );
+    let err = client.recv_frame().unwrap_err();
+    assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+}
+
+#[test]
+fn listener_resync_after_overrun() {
+    let mut mock = mock();
+    let state = Counter {
+        cmd: CMD_GET,
+        value: Some(7),
+    };
+    mock.on(
+        "counter",
+        CMD_GET,
+        MockResponse::dump(std::slice::from_ref(&state)),
+    );
+
+    let mut subscription =
+        McastSubscription::new(GenlClient::new(mock)).unwrap();
+    subscription.subscribe("counter", "changes").unwrap();
+    let mut listener = GenlListener::new(subscription);
+    listener.set_resync(|| get(None));
+
+    let mock = listener.subscription_mut().client_mut().transport_mut();
+    assert!(mock.memberships().contains(&12));
+    mock.notify("counter", "changes", GenlRaw::from(&state));
+    mock.overrun();
+
+    assert_eq!(ListenerEvent::EventsLost, listener.next_event().unwrap());
+    let mut expected = GenlMessage::from_payload(state.clone());
+    expected.set_resolved_family_id(FAMILY_ID);
+    assert_eq!(
+        ListenerEvent::Resync(vec![expected.clone()]),
+        listener.next_event().unwrap()
+    );
+    assert_eq!(
+        ListenerEvent::Message(expected),
+        listener.next_event().unwrap()
+    );
+    let err = listener.next_event().unwrap_err();
+    assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+}
+
+#[test]
+fn listener_overrun_during_refresh() {
+    let mut mock = mock();
+    let state = Counter {
+        cmd: CMD_GET,
+        value: Some(7),
+    };
+    mock.on(
+        "counter",
+        CMD_GET,
+        MockResponse::dump(std::slice::from_ref(&state)),
+    );
+
+    let mut subscription =
+        McastSubscription::new(GenlClient::new(mock)).unwrap();
+    subscription.subscribe("counter", "changes").unwrap();
+    let mut listener = GenlListener::new(subscription);
+    listener.set_resync(|| get(None));
+
+    // The second overrun hits the refresh of the subscriptions
+    let mock = listener.subscription_mut().client_mut().transport_mut();
+    mock.overrun();
+    mock.overrun();
+
+    assert_eq!(ListenerEvent::EventsLost, listener.next_event().unwrap());
+    assert_eq!(ListenerEvent::EventsLost, listener.next_event().unwrap());
+    let mut expected = GenlMessage::from_payload(state);
+    expected.set_resolved_family_id(FAMILY_ID);
+    assert_eq!(
+        ListenerEvent::Resync(vec![expected]),
+        listener.next_event().unwrap()
+    );
+}
+
+#[test]
+fn refresh_after_lost_notifications() {
+    let mut mock = mock();
+    mock.add_family(FamilyInfo {
+        id: 0x23,
+        name: "other".to_owned(),
+        mcast_groups: vec![FamilyMcastGroup {
+            name: "events".to_owned(),
+            id: 14,
+        }],
+        ..Default::default()
+    });
+    let mut subscription =
+        McastSubscription::new(GenlClient::new(mock)).unwrap();
+    subscription.subscribe("counter", "changes").unwrap();
+    subscription.subscribe("other", "events").unwrap();
+
+    // Reload the family with new ids, without receiving the notifications
+    let mock = subscription.client_mut().transport_mut();
+    mock.add_family(FamilyInfo {
+        id: FAMILY_ID + 2,
+        name: "counter".to_owned(),
+        mcast_groups: vec![FamilyMcastGroup {
+            name: "changes".to_owned(),
+            id: 15,
+        }],
+        ..Default::default()
+    });
+    mock.remove_family("other");
+    subscription.refresh().unwrap();
+
+    assert_eq!(Some(FAMILY_ID + 2), subscription.family_id("counter"));
+    assert_eq!(Some(15), subscription.group_id("counter", "changes"));
+    assert_eq!(None, subscription.family_id("other"));
+    let mock = subscription.client_mut().transport_mut();
+    assert!(mock.memberships().contains(&15));
+}
+
+#[test]
+fn recv_during_reload() {
+    let mut subscription =
+        McastSubscription::new(GenlClient::new(mock())).unwrap();
+    subscription.subscribe("counter", "changes").unwrap();
+
+    // The family leaves the kernel, and the subscription sees it
+    let counter = subscription
+        .client_mut()
+        .transport_mut()
+        .remove_family("counter")
+        .unwrap();
+    let err = subscription.recv_frame().unwrap_err();
+    assert_eq!(io::ErrorKind::WouldBlock, err.kind());
+    assert_eq!(None, subscription.family_id("counter"));
+
+    // The family comes back and sends a notification
+    let mock = subscription.client_mut().transport_mut();
+    mock.add_family(counter);
+    let state = Counter {
+        cmd: CMD_GET,
+        value: Some(7),
+    };
+    mock.notify("counter", "changes", GenlRaw::from(&state));
+    assert_eq!(state, subscription.recv::<Counter>().unwrap().payload);
+}
@@ -0,0 +1,95 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_core::{
+    Emitable, NlaBuffer, Parseable, NLA_F_NESTED, NLA_TYPE_MASK,
+};
+use netlink_packet_generic::{
+    array::NlaArrayItem,
+    ctrl::nlas::{McastGrpAttrs, OpAttrs},
+    NlaArray, NlaArrayAttr,
+};
+
+/// Array of type 6 holding the ops 3 and 1, keyed by the indices 4 and 2
+const SPARSE: [u8; 28] = [
+    28, 0, 6, 0, // array
+    12, 0, 4, 0, // element 4
+    8, 0, 1, 0, 3, 0, 0, 0, // id
+    12, 0, 2, 0, // element 2
+    8, 0, 1, 0, 1, 0, 0, 0, // id
+];
+
+#[test]
+fn keep_indices() {
+    let array = NlaArray::<OpAttrs>::parse(
+        &NlaBuffer::new_checked(&SPARSE[..]).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        NlaArray {
+            items: vec![
+                NlaArrayItem {
+                    index: 4,
+                    nlas: vec![OpAttrs::Id(3)],
+                },
+                NlaArrayItem {
+                    index: 2,
+                    nlas: vec![OpAttrs::Id(1)],
+                },
+            ],
+        },
+        array
+    );
+    assert_eq!(Some(&[OpAttrs::Id(1)][..]), array.get(2));
+    assert_eq!(None, array.get(1));
+
+    // The array emits the value of the attribute only
+    let mut buf = vec![0u8; array.buffer_len()];
+    array.emit(&mut buf);
+    assert_eq!(&SPARSE[4..], &buf[..]);
+}
+
+#[test]
+fn push() {
+    let mut array = NlaArray::new([vec![McastGrpAttrs::Id(2)]]);
+    array.items[0].index = 5;
+    array.push(vec![McastGrpAttrs::Id(3)]);
+    let indices: Vec<_> = array.iter().map(|item| item.index).collect();
+    assert_eq!(vec![5, 6], indices);
+}
+
+#[test]
+#[should_panic(expected = "no array index left")]
+fn push_past_last_index() {
+    let mut array = NlaArray::new([vec![McastGrpAttrs::Id(2)]]);
+    array.items[0].index = NLA_TYPE_MASK;
+    array.push(vec![McastGrpAttrs::Id(3)]);
+}
+
+#[test]
+fn attribute() {
+    let mut bytes = SPARSE;
+    bytes[3] |= (NLA_F_NESTED >> 8) as u8;
+    let attr = NlaArrayAttr::<OpAttrs>::parse(
+        &NlaBuffer::new_checked(&bytes[..]).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(6, attr.kind);
+    assert_eq!(Some(&[OpAttrs::Id(3)][..]), attr.array.get(4));
+
+    // The attribute is emitted whole, with NLA_F_NESTED
+    let mut buf = vec![0u8; attr.buffer_len()];
+    attr.emit(&mut buf);
+    assert_eq!(&bytes[..], &buf[..]);
+}
+
+#[test]
+fn report_failing_index() {
+    let mut bytes = SPARSE;
+    // Truncate the id of the element 2
+    bytes[20] = 6;
+    let err = NlaArray::<OpAttrs>::parse(
+        &NlaBuffer::new_checked(&bytes[..]).unwrap(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("array element 2"), "{err}");
+}
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: MIT
+
+//! Round trip of the controller model through its wire format, run with
+//! `cargo test --features arbitrary`
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use netlink_packet_core::{
+    Emitable, NetlinkMessage, NetlinkPayload, Nla, NlaBuffer, Parseable,
+};
+use netlink_packet_generic::{
+    array::NlaArrayItem,
+    ctrl::{nlas::*, GenlCtrl, GenlCtrlCmd},
+    GenlBuffer, GenlHeader, GenlMessage, NlaArray,
+};
+use proptest::{collection::vec, prelude::*};
+use std::fmt::Debug;
+
+/// Byte written after the emitted buffer, which must be left untouched
+const SENTINEL: u8 = 0xa5;
+
+/// Generate a `T` from random bytes, so that proptest shrinks the bytes
+fn arb<T: for<'a> Arbitrary<'a> + Debug>() -> impl Strategy<Value = T> {
+    vec(any::<u8>(), 0..1024)
+        .prop_map(|data| T::arbitrary(&mut Unstructured::new(&data)).unwrap())
+}
+
+/// Emit `value` into a buffer larger than its `buffer_len`, and return the
+/// `buffer_len` first bytes after checking that nothing was written past them
+fn emit<T: Emitable>(value: &T) -> Vec<u8> {
+    let len = value.buffer_len();
+    let mut buf = vec![SENTINEL; len + 16];
+    value.emit(&mut buf);
+    assert!(buf[len..].iter().all(|b| *b == SENTINEL));
+    buf.truncate(len);
+    buf
+}
+
+/// Emit and parse back an attribute
+fn roundtrip<T>(nla: &T)
+where
+    T: Nla + Debug + PartialEq + for<'a> Parseable<NlaBuffer<&'a [u8]>>,
+{
+    let buf = emit(nla);
+    let nla_buf = NlaBuffer::new_checked(&buf[..]).unwrap();
+    assert_eq!(buf.len(), (usize::from(nla_buf.length()) + 3) & !3);
+    assert_eq!(nla, &T::parse(&nla_buf).unwrap());
+}
+
+proptest! {
+    #[test]
+    fn genl_header(header in arb::<GenlHeader>()) {
+        let buf = emit(&header);
+        let parsed =
+            GenlHeader::parse(&GenlBuffer::new_checked(&buf[..]).unwrap());
+        prop_assert_eq!(header, parsed.unwrap());
+    }
+
+    #[test]
+    fn genl_ctrl_cmd(cmd in arb::<GenlCtrlCmd>()) {
+        prop_assert_eq!(cmd, GenlCtrlCmd::try_from(u8::from(cmd)).unwrap());
+    }
+
+    #[test]
+    fn genl_ctrl(ctrl in arb::<GenlCtrl>()) {
+        let mut message = NetlinkMessage::from(GenlMessage::from_payload(ctrl));
+        message.finalize();
+        let buf = emit(&message);
+        prop_assert_eq!(buf.len(), message.header.length as usize);
+        let parsed =
+            NetlinkMessage::<GenlMessage<GenlCtrl>>::deserialize(&buf).unwrap();
+        match (message.payload, parsed.payload) {
+            (
+                NetlinkPayload::InnerMessage(message),
+                NetlinkPayload::InnerMessage(parsed),
+            ) => {
+                prop_assert_eq!(message.header, parsed.header);
+                prop_assert_eq!(message.payload, parsed.payload);
+            }
+            (message, parsed) => prop_assert_eq!(message, parsed),
+        }
+    }
+
+    #[test]
+    fn genl_ctrl_attrs(nla in arb::<GenlCtrlAttrs>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn ops(items in arb::<Vec<NlaArrayItem<OpAttrs>>>()) {
+        roundtrip(&GenlCtrlAttrs::Ops(NlaArray {
+            items,
+        }));
+    }
+
+    #[test]
+    fn op_attrs(nla in arb::<OpAttrs>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn mcast_groups(items in arb::<Vec<NlaArrayItem<McastGrpAttrs>>>()) {
+        roundtrip(&GenlCtrlAttrs::McastGroups(NlaArray {
+            items,
+        }));
+    }
+
+    #[test]
+    fn mcast_grp_attrs(nla in arb::<McastGrpAttrs>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn oppolicy_attr(nla in arb::<OppolicyAttr>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn oppolicy_index_attr(nla in arb::<OppolicyIndexAttr>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn policy_attr(nla in arb::<PolicyAttr>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn attribute_policy_attr(nla in arb::<AttributePolicyAttr>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn nl_policy_type_attrs(nla in arb::<NlPolicyTypeAttrs>()) {
+        roundtrip(&nla);
+    }
+
+    #[test]
+    fn nla_type(kind in arb::<NlaType>()) {
+        prop_assert_eq!(kind, NlaType::try_from(u32::from(kind)).unwrap());
+    }
+}
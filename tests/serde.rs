@@ -0,0 +1,121 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "serde")]
+
+use netlink_packet_generic::{
+    ctrl::{
+        nlas::{
+            AttributePolicyAttr, GenlCtrlAttrs, McastGrpAttrs,
+            NlPolicyTypeAttrs, NlaType, OpAttrs, PolicyAttr,
+        },
+        AttributePolicy, FamilyInfo, FamilyOp, FamilySnapshot, GenlCtrl,
+        GenlCtrlCmd, OpPolicy,
+    },
+    GenlMessage, NlaArray,
+};
+use serde_json::json;
+
+#[test]
+fn ctrl_message() {
+    let message = GenlMessage::from_payload(GenlCtrl {
+        cmd: GenlCtrlCmd::NewFamily,
+        nlas: vec![
+            GenlCtrlAttrs::FamilyId(0x17),
+            GenlCtrlAttrs::FamilyName("ethtool".to_owned()),
+            GenlCtrlAttrs::Ops(NlaArray::new([vec![
+                OpAttrs::Id(1),
+                OpAttrs::Flags(8),
+            ]])),
+            GenlCtrlAttrs::McastGroups(NlaArray::new([vec![
+                McastGrpAttrs::Name("monitor".to_owned()),
+                McastGrpAttrs::Id(5),
+            ]])),
+        ],
+    });
+    let value = json!({
+        "header": { "cmd": 1, "version": 2 },
+        "payload": {
+            "cmd": "new_family",
+            "nlas": [
+                { "family_id": 23 },
+                { "family_name": "ethtool" },
+                {
+                    "ops": [
+                        {
+                            "index": 1,
+                            "nlas": [{ "id": 1 }, { "flags": 8 }],
+                        },
+                    ],
+                },
+                {
+                    "mcast_groups": [
+                        {
+                            "index": 1,
+                            "nlas": [{ "name": "monitor" }, { "id": 5 }],
+                        },
+                    ],
+                },
+            ],
+        },
+        "resolved_family_id": 0,
+    });
+    assert_eq!(value, serde_json::to_value(&message).unwrap());
+    assert_eq!(message, serde_json::from_value(value).unwrap());
+}
+
+#[test]
+fn policy() {
+    let policy = GenlCtrlAttrs::Policy(PolicyAttr {
+        index: 1,
+        attr_policy: AttributePolicyAttr {
+            index: 3,
+            policies: vec![
+                NlPolicyTypeAttrs::Type(NlaType::NulString),
+                NlPolicyTypeAttrs::MaxLength(15),
+            ],
+        },
+    });
+    let value = json!({
+        "policy": {
+            "index": 1,
+            "attr_policy": {
+                "index": 3,
+                "policies": [{ "type": "nul_string" }, { "max_length": 15 }],
+            },
+        },
+    });
+    assert_eq!(value, serde_json::to_value(&policy).unwrap());
+    assert_eq!(policy, serde_json::from_value(value).unwrap());
+}
+
+#[test]
+fn family_snapshot() {
+    let mut snapshot = FamilySnapshot {
+        info: FamilyInfo {
+            id: 0x20,
+            name: "test".to_owned(),
+            version: 1,
+            ops: vec![FamilyOp { id: 1, flags: 0x0a }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    snapshot.policy.ops.insert(
+        1,
+        OpPolicy {
+            do_policy: Some(2),
+            dump_policy: None,
+        },
+    );
+    snapshot.policy.policies.entry(2).or_default().insert(
+        3,
+        AttributePolicy {
+            max_length: Some(15),
+            ..AttributePolicy::new(NlaType::NulString)
+        },
+    );
+
+    // The maps keyed by integers are JSON objects keyed by strings
+    let json = serde_json::to_string(&snapshot).unwrap();
+    assert_eq!(snapshot, serde_json::from_str(&json).unwrap());
+}
@@ -0,0 +1,156 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "std")]
+
+use netlink_packet_core::NLM_F_DUMP;
+use netlink_packet_generic::{
+    ctrl::{nlas::GenlCtrlAttrs, FamilyInfo, GenlCtrl, GenlCtrlCmd},
+    session::{Direction, RecordedFrame},
+    testing::{MockResponse, MockTransport},
+    GenlClient, GenlMessage, GenlRaw, GenlRecorder, GenlReplay, GenlSession,
+    GenlTransport,
+};
+use std::{io, time::Duration};
+
+fn mock() -> MockTransport {
+    let mut mock = MockTransport::new();
+    mock.add_family(FamilyInfo {
+        id: 0x23,
+        name: "test".to_owned(),
+        version: 1,
+        hdrsize: 0,
+        maxattr: 1,
+        ops: vec![],
+        mcast_groups: vec![],
+    });
+    let reply = GenlRaw::from_nlas(1, 1, &[GenlCtrlAttrs::Version(3)]);
+    mock.on("test", 1, MockResponse::Dump(vec![reply.clone(), reply]));
+    mock
+}
+
+/// Resolve the `test` family and dump its objects
+fn run<T: GenlTransport>(transport: T) -> io::Result<Vec<GenlRaw>> {
+    let mut client = GenlClient::new(transport);
+    let info = client.get_family("test")?;
+    let mut request = GenlMessage::from_payload(GenlRaw {
+        cmd: 1,
+        version: 1,
+        payload: vec![],
+    });
+    request.set_resolved_family_id(info.id);
+    Ok(client
+        .request(request, NLM_F_DUMP)?
+        .into_iter()
+        .map(|msg| msg.payload)
+        .collect())
+}
+
+fn record() -> (Vec<GenlRaw>, GenlSession) {
+    let mut recorder = GenlRecorder::new(mock(), Vec::new()).unwrap();
+    let replies = run(&mut recorder).unwrap();
+    let (_, file) = recorder.into_parts();
+    (replies, GenlSession::read_from(&file[..]).unwrap())
+}
+
+#[test]
+fn record_session() {
+    let (_, session) = record();
+    let frames: Vec<_> = session
+        .frames
+        .iter()
+        .map(|f| (f.direction, f.family.as_deref()))
+        .collect();
+    assert_eq!(
+        vec![
+            (Direction::Sent, Some("nlctrl")),
+            (Direction::Received, Some("nlctrl")),
+            (Direction::Sent, Some("test")),
+            (Direction::Received, Some("test")),
+        ],
+        frames
+    );
+
+    let mut file = Vec::new();
+    session.write_to(&mut file).unwrap();
+    assert_eq!(session, GenlSession::read_from(&file[..]).unwrap());
+}
+
+#[test]
+fn replay_session() {
+    let (replies, session) = record();
+    let mut replay = GenlReplay::new(session.clone());
+    assert_eq!(replies, run(&mut replay).unwrap());
+    assert!(replay.is_finished());
+
+    // The sequence numbers of the replies follow the ones of the client
+    let mut replay = GenlReplay::new(GenlSession {
+        frames: session.frames[2..].to_vec(),
+    });
+    let mut client = GenlClient::new(&mut replay);
+    let mut request = GenlMessage::from_payload(GenlRaw {
+        cmd: 1,
+        version: 1,
+        payload: vec![],
+    });
+    request.set_resolved_family_id(0x23);
+    let dumped: Vec<_> = client
+        .request(request, NLM_F_DUMP)
+        .unwrap()
+        .into_iter()
+        .map(|msg| msg.payload)
+        .collect();
+    assert_eq!(replies, dumped);
+    assert!(replay.is_finished());
+}
+
+#[test]
+fn replay_mismatch() {
+    let (_, session) = record();
+    let mut replay = GenlReplay::new(session);
+    let mut client = GenlClient::new(&mut replay);
+    let err = client
+        .request(
+            GenlMessage::from_payload(GenlCtrl {
+                cmd: GenlCtrlCmd::GetFamily,
+                nlas: vec![GenlCtrlAttrs::FamilyName("other".to_owned())],
+            }),
+            0,
+        )
+        .unwrap_err();
+    assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+}
+
+#[test]
+fn malformed_lines() {
+    for line in [
+        "< 18446744073709551615.1000000000 - 00",
+        "< 1.000000000 - 0\u{e9}0",
+        "< 1.000000000 -",
+        "! 1.000000000 - 00",
+    ] {
+        let err = line.parse::<RecordedFrame>().unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind(), "{line}");
+    }
+}
+
+#[test]
+fn replay_unaligned_messages() {
+    // Notification of 21 bytes, padded to 24, followed by one of 20 bytes
+    let mut data = vec![
+        21, 0, 0, 0, 0x23, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // nlmsghdr
+        1, 1, 0, 0, 0xff, 0, 0, 0, // genlmsghdr, payload and padding
+    ];
+    data.extend([
+        20, 0, 0, 0, 0x23, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // nlmsghdr
+        2, 1, 0, 0, // genlmsghdr
+    ]);
+    let mut replay = GenlReplay::new(GenlSession {
+        frames: vec![RecordedFrame {
+            direction: Direction::Received,
+            timestamp: Duration::ZERO,
+            family: Some("test".to_owned()),
+            data: data.clone(),
+        }],
+    });
+    assert_eq!(data, replay.recv().unwrap());
+}
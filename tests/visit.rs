@@ -0,0 +1,159 @@
+// SPDX-License-Identifier: MIT
+
+use netlink_packet_core::{NetlinkHeader, NetlinkMessage, NetlinkPayload};
+use netlink_packet_generic::{
+    constants::{
+        CTRL_ATTR_OP_POLICY, CTRL_ATTR_POLICY, NL_POLICY_TYPE_ATTR_TYPE,
+    },
+    ctrl::{
+        nlas::{GenlCtrlAttrs, NlPolicyTypeAttrs},
+        GenlCtrl,
+    },
+    visit::{visit_dump, visit_nlas, DumpStatus},
+    GenlHeader, GenlMessage, NlaVisitor,
+};
+use std::{fs, path::Path};
+
+/// Return the messages of the `CTRL_CMD_GETPOLICY` dump of `mptcp_pm`,
+/// back to back
+fn dump() -> Vec<u8> {
+    let dir =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/genl_ctrl");
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| {
+            name.starts_with("getpolicy-mptcp_pm-")
+                && !name.ends_with("request")
+        })
+        .collect();
+    // Keep the done message last
+    names.sort_by_key(|name| (name.ends_with("done"), name.clone()));
+    names
+        .into_iter()
+        .flat_map(|name| fs::read(dir.join(name)).unwrap())
+        .collect()
+}
+
+/// Count the attribute policies and their types without building the
+/// controller messages
+#[derive(Default)]
+struct PolicyCounter {
+    path: Vec<u16>,
+    messages: usize,
+    types: usize,
+}
+
+impl NlaVisitor for PolicyCounter {
+    fn message(&mut self, _header: &NetlinkHeader, _genl: GenlHeader) -> bool {
+        self.messages += 1;
+        true
+    }
+
+    fn is_nested(&mut self, kind: u16, _flagged: bool) -> bool {
+        match self.path[..] {
+            [] => kind == CTRL_ATTR_POLICY || kind == CTRL_ATTR_OP_POLICY,
+            [CTRL_ATTR_POLICY] | [CTRL_ATTR_POLICY, _] => true,
+            [CTRL_ATTR_OP_POLICY] => true,
+            _ => false,
+        }
+    }
+
+    fn enter_nest(&mut self, kind: u16) {
+        self.path.push(kind);
+    }
+
+    fn leave_nest(&mut self, _kind: u16) {
+        self.path.pop();
+    }
+
+    fn attribute(&mut self, kind: u16, _value: &[u8]) {
+        if let [CTRL_ATTR_POLICY, _, _] = self.path[..] {
+            if kind == NL_POLICY_TYPE_ATTR_TYPE {
+                self.types += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn count_policy_types() {
+    let dump = dump();
+    let mut counter = PolicyCounter::default();
+    assert_eq!(
+        DumpStatus::Done,
+        visit_dump(&dump, 0, &mut counter).unwrap()
+    );
+    assert!(counter.path.is_empty());
+
+    let mut messages = 0;
+    let mut types = 0;
+    let mut offset = 0;
+    while offset < dump.len() {
+        let message = NetlinkMessage::<GenlMessage<GenlCtrl>>::deserialize(
+            &dump[offset..],
+        )
+        .unwrap();
+        offset += message.header.length as usize;
+        if let NetlinkPayload::InnerMessage(msg) = message.payload {
+            messages += 1;
+            for nla in msg.payload.nlas {
+                if let GenlCtrlAttrs::Policy(policy) = nla {
+                    types += policy
+                        .attr_policy
+                        .policies
+                        .iter()
+                        .filter(|p| matches!(p, NlPolicyTypeAttrs::Type(_)))
+                        .count();
+                }
+            }
+        }
+    }
+    assert_eq!(messages, counter.messages);
+    assert_eq!(types, counter.types);
+    assert!(types > 0);
+}
+
+#[test]
+fn incomplete_dump() {
+    let dump = dump();
+    // Drop the done message
+    let len = dump.len() - 20;
+    let status =
+        visit_dump(&dump[..len], 0, &mut PolicyCounter::default()).unwrap();
+    assert_eq!(DumpStatus::Incomplete, status);
+}
+
+#[test]
+fn failed_dump() {
+    let mut dump = dump();
+    // -EMSGSIZE as the error code of the done message
+    let len = dump.len();
+    dump[len - 4..].copy_from_slice(&(-90i32).to_ne_bytes());
+    let status = visit_dump(&dump, 0, &mut PolicyCounter::default()).unwrap();
+    assert_eq!(DumpStatus::Error(-90), status);
+}
+
+/// Descend into every attribute
+struct Everything;
+
+impl NlaVisitor for Everything {
+    fn is_nested(&mut self, _kind: u16, _flagged: bool) -> bool {
+        true
+    }
+}
+
+#[test]
+fn nesting_limit() {
+    // 40 attributes nested into each other
+    let mut buf = Vec::new();
+    for depth in (0..40u16).rev() {
+        let len = 4 + buf.len() as u16;
+        let mut nla = len.to_ne_bytes().to_vec();
+        nla.extend(depth.to_ne_bytes());
+        nla.extend(buf);
+        buf = nla;
+    }
+    let err = visit_nlas(&buf, &mut Everything).unwrap_err();
+    assert!(err.to_string().contains("nested deeper"), "{err}");
+}